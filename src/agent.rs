@@ -1,3 +1,6 @@
+use crate::claude_settings;
+use crate::config::DEFAULT_MANAGED_DIR;
+use crate::github::{self, GitHubFileUrl};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
@@ -6,6 +9,46 @@ pub struct Agent {
     pub name: String,
     pub source: AgentSource,
     pub enabled: bool,
+    /// Optional predicate (`env:VAR`, `env:VAR=value` or `os:<name>`) that
+    /// must hold for the agent to be linked during sync.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Directory (relative to the project root) holding the downloaded file of
+    /// a GitHub agent, when it differs from `.ccagents`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub managed_dir: Option<PathBuf>,
+    /// Set by `update` when the GitHub file no longer exists upstream and the
+    /// local copy was kept.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub upstream_missing: bool,
+    /// Short names that `enable`, `disable` and `alias` accept in place of
+    /// the agent's name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// File name of the link in `.claude/agents` when it should differ from
+    /// the storage name, e.g. `backend.md` for `backend-developer.md`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_name: Option<String>,
+    /// Extra HTTP headers sent when downloading a GitHub agent, as
+    /// `[name, value]` pairs. Values must reference at least one `${VAR}`,
+    /// so secrets stay out of `.agents.json`; literal values are rejected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    /// Subdirectory of `.claude/agents` the agent is linked into, e.g.
+    /// `backend` for `.claude/agents/backend/<name>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subdir: Option<String>,
+    /// Note shown by `list`; takes precedence over the `description` in the
+    /// agent file's frontmatter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Labels for syncing a group of agents with `sync --tag`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// SHA-256 of the agent's content when it was added or last updated
+    /// (see `linker::content_digest`), checked by `verify`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +64,16 @@ impl Agent {
             name,
             source,
             enabled: true,
+            when: None,
+            managed_dir: None,
+            upstream_missing: false,
+            aliases: Vec::new(),
+            link_name: None,
+            headers: Vec::new(),
+            subdir: None,
+            description: None,
+            tags: Vec::new(),
+            sha256: None,
         }
     }
 
@@ -34,38 +87,31 @@ impl Agent {
         Ok(Self::new(name, AgentSource::Local(path.to_path_buf())))
     }
 
+    /// Creates an agent from a URL. `${VAR}` references are expanded for
+    /// parsing, while the stored source keeps the templated form.
+    #[allow(dead_code)]
     pub fn from_url(url: &str) -> anyhow::Result<Self> {
-        let parsed_url = url::Url::parse(url)?;
+        Self::from_url_with_hosts(url, &github::enterprise_hosts(&[]))
+    }
+
+    /// Like [`Agent::from_url`], additionally recognizing the given GitHub
+    /// Enterprise hosts.
+    pub fn from_url_with_hosts(url: &str, enterprise_hosts: &[String]) -> anyhow::Result<Self> {
+        let expanded = expand_env_vars(url)?;
+        let parsed_url = url::Url::parse(&expanded)?;
 
         // Extract agent name from URL
-        let name = if parsed_url.host_str() == Some("github.com") {
-            let segments: Vec<&str> = parsed_url
-                .path()
-                .trim_start_matches('/')
-                .split('/')
-                .filter(|s| !s.is_empty())
-                .collect();
-
-            // We only support file URLs (with /blob/)
-            if segments.len() >= 5 && segments[2] == "blob" {
-                // Use the filename
-                segments
-                    .last()
-                    .ok_or_else(|| anyhow::anyhow!("No filename in URL"))?
-                    .to_string()
-            } else {
-                return Err(anyhow::anyhow!(
-                    "Only direct file links are supported. Please provide a URL like:\n\
-                     https://github.com/user/repo/blob/main/agent.md"
-                ));
-            }
+        let name = if github::is_github_url(&expanded, enterprise_hosts) {
+            // We only support file URLs (with /blob/); use the filename
+            sanitize_filename(GitHubFileUrl::parse(&expanded, enterprise_hosts)?.filename())?
         } else {
             // For non-GitHub URLs, use the last segment as filename
-            parsed_url
-                .path_segments()
-                .and_then(|mut segments| segments.next_back())
-                .ok_or_else(|| anyhow::anyhow!("Invalid URL"))?
-                .to_string()
+            sanitize_filename(
+                parsed_url
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .ok_or_else(|| anyhow::anyhow!("Invalid URL"))?,
+            )?
         };
 
         Ok(Self::new(name, AgentSource::GitHub(url.to_string())))
@@ -80,15 +126,202 @@ impl Agent {
                     project_root.join(path)
                 }
             }
-            AgentSource::GitHub(_) => project_root.join(".ccagents").join(&self.name),
+            AgentSource::GitHub(_) => {
+                let managed_dir = self
+                    .managed_dir
+                    .as_deref()
+                    .unwrap_or(Path::new(DEFAULT_MANAGED_DIR));
+                project_root.join(managed_dir).join(&self.name)
+            }
         }
     }
 
     pub fn get_link_path(&self, project_root: &Path) -> PathBuf {
-        project_root.join(".claude").join("agents").join(&self.name)
+        claude_settings::agents_dir(project_root).join(self.link_key())
+    }
+
+    /// Name of the agent's entry in `.claude/agents`: `link_name` if set,
+    /// else the agent name.
+    pub fn link_file_name(&self) -> &str {
+        self.link_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Path of the agent's link relative to `.claude/agents`: the link file
+    /// name, prefixed with `subdir/` when the agent has one.
+    pub fn link_key(&self) -> String {
+        match &self.subdir {
+            Some(subdir) => format!("{}/{}", subdir, self.link_file_name()),
+            None => self.link_file_name().to_string(),
+        }
+    }
+
+    /// The configured description, else the one from the frontmatter of the
+    /// agent file (if it is a file and has one).
+    pub fn description(&self, project_root: &Path) -> Option<String> {
+        self.description
+            .clone()
+            .or_else(|| frontmatter_description(&self.get_local_path(project_root)))
+    }
+
+    /// Evaluates the agent's `when` predicate against the current environment.
+    /// Agents without a predicate are always active.
+    pub fn condition_met(&self) -> anyhow::Result<bool> {
+        match &self.when {
+            Some(condition) => evaluate_condition(condition, |var| std::env::var(var).ok()),
+            None => Ok(true),
+        }
     }
 }
 
+/// Reads the `description:` value from the YAML frontmatter (between `---`
+/// lines at the start) of the file at `path`. Only single-line values are
+/// supported; surrounding quotes are removed.
+pub fn frontmatter_description(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let mut lines = content.lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+
+    lines
+        .take_while(|line| line.trim_end() != "---")
+        .find_map(|line| line.strip_prefix("description:"))
+        .map(|value| value.trim().trim_matches(['"', '\'']).to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Checks that `subdir` is a relative path of at most
+/// [`MAX_LINK_DEPTH`](crate::config::MAX_LINK_DEPTH) directories inside
+/// `.claude/agents`, such as `backend` or `team/backend`. Hidden directories
+/// are rejected since scans skip them.
+pub fn validate_subdir(subdir: &str) -> anyhow::Result<()> {
+    let components: Vec<&str> = subdir.split('/').collect();
+    let valid = components.len() <= crate::config::MAX_LINK_DEPTH
+        && components
+            .iter()
+            .all(|c| !c.is_empty() && !c.starts_with('.') && !c.contains('\\'));
+    if !valid {
+        return Err(anyhow::anyhow!(
+            "Invalid subdirectory '{}': expected a relative path of up to {} directories such as 'backend' or 'team/backend'",
+            subdir,
+            crate::config::MAX_LINK_DEPTH
+        ));
+    }
+    Ok(())
+}
+
+/// Expands `${VAR}` references from the process environment, erroring when a
+/// referenced variable is unset.
+pub fn expand_env_vars(input: &str) -> anyhow::Result<String> {
+    expand_env_vars_with(input, |var| std::env::var(var).ok())
+}
+
+/// Expands `${VAR}` references, resolving variables via `lookup`. A `$` not
+/// followed by `{` is kept literally.
+pub fn expand_env_vars_with<F>(input: &str, lookup: F) -> anyhow::Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut expanded = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("Unterminated '${{' in '{}'", input))?;
+        let var = &after[..end];
+
+        let value = lookup(var).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Environment variable '{}' referenced in '{}' is not set",
+                var,
+                input
+            )
+        })?;
+        expanded.push_str(&value);
+        rest = &after[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Evaluates a `when` predicate, resolving environment variables via `lookup`.
+///
+/// Supported forms are `env:VAR` (variable set and non-empty), `env:VAR=value`
+/// and `os:<name>` (compared against `std::env::consts::OS`).
+pub fn evaluate_condition<F>(condition: &str, lookup: F) -> anyhow::Result<bool>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let (kind, arg) = condition
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid condition '{}'", condition))?;
+
+    match kind.trim() {
+        "env" => match arg.split_once('=') {
+            Some((var, expected)) => Ok(lookup(var.trim()).as_deref() == Some(expected)),
+            None => Ok(lookup(arg.trim()).is_some_and(|value| !value.is_empty())),
+        },
+        "os" => Ok(arg.trim().eq_ignore_ascii_case(std::env::consts::OS)),
+        other => Err(anyhow::anyhow!(
+            "Unknown condition type '{}' in '{}' (expected env: or os:)",
+            other,
+            condition
+        )),
+    }
+}
+
+/// Turns a file name taken from a URL into an on-disk name: `%XX` escapes
+/// are decoded, surrounding whitespace and trailing dots are trimmed, and
+/// names containing path separators or control characters are rejected.
+pub fn sanitize_filename(raw: &str) -> anyhow::Result<String> {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| raw.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    let decoded = String::from_utf8(decoded)
+        .map_err(|_| anyhow::anyhow!("File name {:?} is not valid UTF-8 once decoded", raw))?;
+
+    if decoded.contains(['/', '\\']) {
+        return Err(anyhow::anyhow!(
+            "File name {:?} contains a path separator",
+            raw
+        ));
+    }
+    if decoded.chars().any(char::is_control) {
+        return Err(anyhow::anyhow!(
+            "File name {:?} contains control characters",
+            raw
+        ));
+    }
+
+    let name = decoded.trim().trim_end_matches('.');
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("URL {:?} does not end in a file name", raw));
+    }
+
+    Ok(name.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,6 +339,39 @@ mod tests {
         matches!(agent.source, AgentSource::Local(_));
     }
 
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("agent.md").unwrap(), "agent.md");
+        assert_eq!(sanitize_filename("my%20agent.md").unwrap(), "my agent.md");
+        assert_eq!(sanitize_filename("caf%C3%A9.md").unwrap(), "café.md");
+        // Stray percent signs are kept
+        assert_eq!(sanitize_filename("100%.md").unwrap(), "100%.md");
+        assert_eq!(sanitize_filename("%20agent.md.%20").unwrap(), "agent.md");
+
+        for raw in [
+            "..%2Fsecret.md",
+            "a%2fb.md",
+            "a%5Cb.md",
+            "a%0Ab.md",
+            "a\tb.md",
+            "%00",
+            "..",
+            "%20",
+        ] {
+            assert!(sanitize_filename(raw).is_err(), "{:?} was accepted", raw);
+        }
+    }
+
+    #[test]
+    fn test_agent_from_url_decodes_file_name() {
+        let agent =
+            Agent::from_url("https://github.com/org/repo/blob/main/agents/code%20reviewer.md")
+                .unwrap();
+        assert_eq!(agent.name, "code reviewer.md");
+
+        assert!(Agent::from_url("https://github.com/org/repo/blob/main/a%2F..%2Fb.md").is_err());
+    }
+
     #[test]
     fn test_agent_from_path() {
         let path = Path::new("test-agent.md");
@@ -164,6 +430,18 @@ mod tests {
         assert_eq!(agent.name, "backend-developer.md");
     }
 
+    #[test]
+    fn test_agent_from_enterprise_url() {
+        let url = "https://github.mycorp.com/org/repo/blob/main/agents/reviewer.md";
+        let hosts = vec!["github.mycorp.com".to_string()];
+
+        let agent = Agent::from_url_with_hosts(url, &hosts).unwrap();
+        assert_eq!(agent.name, "reviewer.md");
+
+        let repo_url = "https://github.mycorp.com/org/repo";
+        assert!(Agent::from_url_with_hosts(repo_url, &hosts).is_err());
+    }
+
     #[test]
     fn test_get_local_path_for_local_relative() {
         let agent = Agent::new(
@@ -206,6 +484,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_local_path_for_github_with_managed_dir() {
+        let mut agent = Agent::new(
+            "agent.md".to_string(),
+            AgentSource::GitHub("https://github.com/user/repo/blob/main/agent.md".to_string()),
+        );
+        agent.managed_dir = Some(PathBuf::from("agents"));
+        let project_root = Path::new("/project");
+
+        assert_eq!(
+            agent.get_local_path(project_root),
+            PathBuf::from("/project/agents/agent.md")
+        );
+    }
+
     #[test]
     fn test_get_link_path() {
         let agent = Agent::new(
@@ -220,6 +513,144 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_link_path_uses_link_name() {
+        let mut agent = Agent::new(
+            "backend-developer.md".to_string(),
+            AgentSource::Local(PathBuf::from(".ccagents/backend-developer.md")),
+        );
+        agent.link_name = Some("backend.md".to_string());
+        let project_root = Path::new("/project");
+
+        assert_eq!(
+            agent.get_link_path(project_root),
+            PathBuf::from("/project/.claude/agents/backend.md")
+        );
+        assert_eq!(
+            agent.get_local_path(project_root),
+            PathBuf::from("/project/.ccagents/backend-developer.md")
+        );
+    }
+
+    #[test]
+    fn test_description_precedence() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        std::fs::write(
+            project_root.join("agent.md"),
+            "---\nname: agent\ndescription: \"Reviews code\"\n---\n# Agent\n",
+        )
+        .unwrap();
+        std::fs::write(project_root.join("plain.md"), "# No frontmatter\n").unwrap();
+
+        let mut agent = Agent::new(
+            "agent.md".to_string(),
+            AgentSource::Local(PathBuf::from("agent.md")),
+        );
+        assert_eq!(
+            agent.description(project_root).as_deref(),
+            Some("Reviews code")
+        );
+
+        agent.description = Some("Our reviewer".to_string());
+        assert_eq!(
+            agent.description(project_root).as_deref(),
+            Some("Our reviewer")
+        );
+
+        let plain = Agent::new(
+            "plain.md".to_string(),
+            AgentSource::Local(PathBuf::from("plain.md")),
+        );
+        assert_eq!(plain.description(project_root), None);
+    }
+
+    #[test]
+    fn test_expand_env_vars_with() {
+        let lookup = |var: &str| match var {
+            "GH_HOST" => Some("127.0.0.1:1234".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            expand_env_vars_with("https://${GH_HOST}/org/repo/blob/main/a.md", lookup).unwrap(),
+            "https://127.0.0.1:1234/org/repo/blob/main/a.md"
+        );
+        assert_eq!(
+            expand_env_vars_with("https://github.com/a/$b", lookup).unwrap(),
+            "https://github.com/a/$b"
+        );
+
+        let unset = expand_env_vars_with("https://${MISSING}/a.md", lookup);
+        assert!(unset.unwrap_err().to_string().contains("'MISSING'"));
+        assert!(expand_env_vars_with("https://${GH_HOST/a.md", lookup).is_err());
+    }
+
+    #[test]
+    fn test_from_url_keeps_templated_source() {
+        std::env::set_var("CCAGENTS_TEST_FROM_URL_HOST", "github.com");
+        let url = "https://${CCAGENTS_TEST_FROM_URL_HOST}/user/repo/blob/main/agent.md";
+        let agent = Agent::from_url(url).unwrap();
+        std::env::remove_var("CCAGENTS_TEST_FROM_URL_HOST");
+
+        assert_eq!(agent.name, "agent.md");
+        match &agent.source {
+            AgentSource::GitHub(stored) => assert_eq!(stored, url),
+            AgentSource::Local(_) => panic!("Expected GitHub source"),
+        }
+
+        let unset = Agent::from_url("https://${CCAGENTS_TEST_UNSET_HOST}/u/r/blob/main/a.md");
+        assert!(unset.is_err());
+    }
+
+    #[test]
+    fn test_evaluate_env_condition() {
+        let lookup = |var: &str| match var {
+            "CI" => Some("true".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+
+        assert!(evaluate_condition("env:CI", lookup).unwrap());
+        assert!(evaluate_condition("env:CI=true", lookup).unwrap());
+        assert!(!evaluate_condition("env:CI=false", lookup).unwrap());
+        assert!(!evaluate_condition("env:EMPTY", lookup).unwrap());
+        assert!(!evaluate_condition("env:MISSING", lookup).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_os_condition() {
+        let lookup = |_: &str| None;
+        let current = format!("os:{}", std::env::consts::OS);
+
+        assert!(evaluate_condition(&current, lookup).unwrap());
+        assert!(!evaluate_condition("os:plan9", lookup).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_invalid_condition() {
+        let lookup = |_: &str| None;
+
+        assert!(evaluate_condition("CI", lookup).is_err());
+        assert!(evaluate_condition("arch:x86", lookup).is_err());
+    }
+
+    #[test]
+    fn test_condition_met_uses_process_env() {
+        let mut agent = Agent::new(
+            "ci.md".to_string(),
+            AgentSource::Local(PathBuf::from("ci.md")),
+        );
+        assert!(agent.condition_met().unwrap());
+
+        agent.when = Some("env:CCAGENTS_TEST_WHEN_ACTIVE".to_string());
+        assert!(!agent.condition_met().unwrap());
+
+        std::env::set_var("CCAGENTS_TEST_WHEN_ACTIVE", "1");
+        assert!(agent.condition_met().unwrap());
+        std::env::remove_var("CCAGENTS_TEST_WHEN_ACTIVE");
+    }
+
     #[test]
     fn test_invalid_url() {
         let result = Agent::from_url("not-a-url");
@@ -0,0 +1,304 @@
+use crate::agent::sanitize_filename;
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use std::collections::HashSet;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+/// Largest agent file read from an archive; bigger entries are rejected so
+/// a crafted archive cannot exhaust memory.
+pub const MAX_ENTRY_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Most data decompressed from one archive, counted across all entries.
+pub const MAX_TOTAL_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Most entries, of any kind, read from one archive.
+pub const MAX_ENTRIES: usize = 1000;
+
+/// Archive formats agent bundles can be distributed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Recognizes `.zip`, `.tar.gz` and `.tgz` file names.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let lower = name.to_ascii_lowercase();
+        if lower.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// An agent file read from an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// File name of the entry, without the directories it was stored under.
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+/// Reads the Markdown files in an archive into memory. Only regular files
+/// are read; directories, symlinks, hidden files and macOS resource forks
+/// are skipped. Entries are flattened to their file name, which is checked
+/// with [`sanitize_filename`], so nothing written from them can land outside
+/// the directory it is written to. Two entries with the same file name are
+/// an error, as is an archive exceeding [`MAX_ENTRIES`] or [`MAX_TOTAL_SIZE`].
+pub fn read_agents(kind: ArchiveKind, bytes: &[u8]) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    let mut budget = Budget::default();
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive =
+                zip::ZipArchive::new(Cursor::new(bytes)).context("Failed to read zip archive")?;
+            for index in 0..archive.len() {
+                budget.add_entry()?;
+                let file = archive
+                    .by_index(index)
+                    .context("Failed to read zip archive")?;
+                // Symlinks are stored as files whose content is the target
+                let is_symlink = file
+                    .unix_mode()
+                    .is_some_and(|mode| mode & 0o170000 == 0o120000);
+                if !file.is_file() || is_symlink {
+                    continue;
+                }
+                let path = file.name().to_string();
+                let size = file.size();
+                let read = push_entry(&mut entries, &path, size, file)?;
+                budget.add_bytes(read)?;
+            }
+        }
+        ArchiveKind::TarGz => {
+            let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+            for entry in archive.entries().context("Failed to read tar archive")? {
+                let entry = entry.context("Failed to read tar archive")?;
+                budget.add_entry()?;
+                // Skipped entries are decompressed too, so all of them count
+                budget.add_bytes(entry.size())?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let path = entry.path()?.to_string_lossy().into_owned();
+                let size = entry.size();
+                push_entry(&mut entries, &path, size, entry)?;
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for entry in &entries {
+        if !seen.insert(entry.name.as_str()) {
+            return Err(anyhow::anyhow!(
+                "The archive contains more than one '{}'",
+                entry.name
+            ));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Limits on how much of an archive is read, checked while reading so a
+/// decompression bomb is abandoned early.
+#[derive(Debug, Default)]
+struct Budget {
+    entries: usize,
+    bytes: u64,
+}
+
+impl Budget {
+    fn add_entry(&mut self) -> Result<()> {
+        self.entries += 1;
+        if self.entries > MAX_ENTRIES {
+            return Err(anyhow::anyhow!(
+                "The archive has more than {} entries",
+                MAX_ENTRIES
+            ));
+        }
+        Ok(())
+    }
+
+    fn add_bytes(&mut self, bytes: u64) -> Result<()> {
+        self.bytes = self.bytes.saturating_add(bytes);
+        if self.bytes > MAX_TOTAL_SIZE {
+            return Err(anyhow::anyhow!(
+                "The archive decompresses to more than {} bytes",
+                MAX_TOTAL_SIZE
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Reads the archive entry at `path` into `entries` when it is an agent file.
+/// Returns the number of bytes read, 0 when the entry was skipped.
+fn push_entry(
+    entries: &mut Vec<ArchiveEntry>,
+    path: &str,
+    size: u64,
+    reader: impl Read,
+) -> Result<u64> {
+    let hidden = path
+        .split(['/', '\\'])
+        .any(|part| part.starts_with('.') && part != "." || part == "__MACOSX");
+    let Some(file_name) = path.rsplit(['/', '\\']).next() else {
+        return Ok(0);
+    };
+    if hidden || !is_agent_file(file_name) {
+        return Ok(0);
+    }
+
+    if size > MAX_ENTRY_SIZE {
+        return Err(anyhow::anyhow!(
+            "Archive entry '{}' is larger than {} bytes",
+            path,
+            MAX_ENTRY_SIZE
+        ));
+    }
+    // The declared size is not trusted while reading
+    let mut content = Vec::new();
+    reader
+        .take(MAX_ENTRY_SIZE + 1)
+        .read_to_end(&mut content)
+        .with_context(|| format!("Failed to read archive entry '{}'", path))?;
+    if content.len() as u64 > MAX_ENTRY_SIZE {
+        return Err(anyhow::anyhow!(
+            "Archive entry '{}' is larger than {} bytes",
+            path,
+            MAX_ENTRY_SIZE
+        ));
+    }
+
+    let read = content.len() as u64;
+    entries.push(ArchiveEntry {
+        name: sanitize_filename(file_name)
+            .with_context(|| format!("Invalid archive entry '{}'", path))?,
+        content,
+    });
+    Ok(read)
+}
+
+/// Whether `name` is a Markdown agent file.
+fn is_agent_file(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+
+    fn zip_with(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, content) in files {
+            writer.start_file(*path, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_archive_kind_from_name() {
+        assert_eq!(ArchiveKind::from_name("agents.zip"), Some(ArchiveKind::Zip));
+        assert_eq!(
+            ArchiveKind::from_name("agents.TAR.GZ"),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(
+            ArchiveKind::from_name("agents.tgz"),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(ArchiveKind::from_name("agent.md"), None);
+    }
+
+    #[test]
+    fn test_read_agents_from_zip() {
+        let bytes = zip_with(&[
+            ("bundle/reviewer.md", "# Reviewer"),
+            ("bundle/nested/tester.md", "# Tester"),
+            ("bundle/LICENSE", "MIT"),
+            ("__MACOSX/bundle/._reviewer.md", "junk"),
+            ("../../escape/.hidden.md", "# Hidden"),
+        ]);
+
+        let entries = read_agents(ArchiveKind::Zip, &bytes).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ArchiveEntry {
+                    name: "reviewer.md".to_string(),
+                    content: b"# Reviewer".to_vec(),
+                },
+                ArchiveEntry {
+                    name: "tester.md".to_string(),
+                    content: b"# Tester".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_agents_rejects_duplicate_names() {
+        let bytes = zip_with(&[("a/agent.md", "# A"), ("b/agent.md", "# B")]);
+
+        let err = read_agents(ArchiveKind::Zip, &bytes).unwrap_err();
+        assert!(err.to_string().contains("more than one 'agent.md'"));
+    }
+
+    #[test]
+    fn test_read_agents_from_tar_gz() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(7);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "agents/planner.md", &b"# Plans"[..])
+            .unwrap();
+        let bytes = builder.into_inner().unwrap().finish().unwrap();
+
+        let entries = read_agents(ArchiveKind::TarGz, &bytes).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "planner.md");
+        assert_eq!(entries[0].content, b"# Plans");
+    }
+
+    #[test]
+    fn test_read_agents_rejects_bombs() {
+        let names: Vec<String> = (0..=MAX_ENTRIES).map(|i| format!("{}.md", i)).collect();
+        let files: Vec<(&str, &str)> = names.iter().map(|n| (n.as_str(), "#")).collect();
+        let err = read_agents(ArchiveKind::Zip, &zip_with(&files)).unwrap_err();
+        assert!(err.to_string().contains("more than 1000 entries"));
+
+        // Skipped entries count too; the declared size is rejected before
+        // anything is decompressed, so only the header is written here
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1024 * 1024 * 1024);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "bomb.bin", std::io::empty())
+            .unwrap();
+        let bytes = builder.into_inner().unwrap().finish().unwrap();
+        let err = read_agents(ArchiveKind::TarGz, &bytes).unwrap_err();
+        assert!(err.to_string().contains("decompresses to more than"));
+    }
+}
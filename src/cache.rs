@@ -0,0 +1,383 @@
+use crate::config::read_dir_sorted;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Environment variable overriding the cache location.
+pub const CACHE_DIR_ENV: &str = "CCAGENTS_CACHE_DIR";
+
+/// Cached files younger than this are served without contacting the server.
+pub const FRESH_FOR: Duration = Duration::from_secs(5 * 60);
+
+/// Entries older than this are evicted.
+pub const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Once the cache grows beyond this size the oldest entries are evicted.
+pub const MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Metadata for a cached download, stored as
+/// `entries/<sha256(url, variant)>.json`. The content itself lives in
+/// `objects/<sha256(content)>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    /// Fingerprint of the request headers the content was fetched with, so
+    /// downloads with different credentials never share an entry; empty
+    /// without headers.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub variant: String,
+    pub etag: Option<String>,
+    pub digest: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch when the content was last validated.
+    pub fetched_at: u64,
+}
+
+impl CacheEntry {
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now_secs().saturating_sub(self.fetched_at))
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.age() < FRESH_FOR
+    }
+}
+
+/// Content-addressed store for downloaded agent files.
+#[derive(Debug, Clone)]
+pub struct DownloadCache {
+    root: PathBuf,
+}
+
+impl DownloadCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the cache location: `$CCAGENTS_CACHE_DIR`, else `cache` in the
+    /// global ccagents config directory.
+    pub fn default_dir() -> Option<PathBuf> {
+        match std::env::var_os(CACHE_DIR_ENV) {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+            _ => dirs::config_dir().map(|dir| dir.join("ccagents").join("cache")),
+        }
+    }
+
+    /// Points the default cache at `cache` in a test's temp dir, so tests
+    /// never read or fill the user's cache. Call it after taking the mock
+    /// server: the variable is process-wide, and a concurrent test that
+    /// overrides it never serves the same host.
+    #[cfg(test)]
+    pub fn use_test_dir(temp_dir: &Path) {
+        std::env::set_var(CACHE_DIR_ENV, temp_dir.join("cache"));
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the entry for `url` fetched with the headers fingerprinted by
+    /// `variant`, if both its metadata and content exist.
+    pub fn lookup(&self, url: &str, variant: &str) -> Option<CacheEntry> {
+        let content = fs::read_to_string(self.entry_path(url, variant)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+
+        (entry.url == url && entry.variant == variant && self.object_path(&entry.digest).exists())
+            .then_some(entry)
+    }
+
+    /// Reads the content of `entry`. Content that no longer matches the
+    /// entry's digest is removed along with the entry and reported as an
+    /// error, so it is downloaded again.
+    pub fn read(&self, entry: &CacheEntry) -> Result<Vec<u8>> {
+        let path = self.object_path(&entry.digest);
+        let content =
+            fs::read(&path).with_context(|| format!("Failed to read cached file {:?}", path))?;
+        if sha256_hex(&content) != entry.digest {
+            fs::remove_file(&path).ok();
+            fs::remove_file(self.entry_path(&entry.url, &entry.variant)).ok();
+            return Err(anyhow::anyhow!(
+                "Cached file {:?} is corrupt and was removed",
+                path
+            ));
+        }
+        Ok(content)
+    }
+
+    /// Stores `content` downloaded from `url` with the headers fingerprinted
+    /// by `variant` and returns its entry.
+    pub fn store(
+        &self,
+        url: &str,
+        variant: &str,
+        etag: Option<String>,
+        content: &[u8],
+    ) -> Result<CacheEntry> {
+        let digest = sha256_hex(content);
+        let object_path = self.object_path(&digest);
+        if !object_path.exists() {
+            write_file(&object_path, content)?;
+        }
+
+        let entry = CacheEntry {
+            url: url.to_string(),
+            variant: variant.to_string(),
+            etag,
+            digest,
+            size: content.len() as u64,
+            fetched_at: now_secs(),
+        };
+        self.write_entry(&entry)?;
+
+        Ok(entry)
+    }
+
+    /// Marks an entry as just validated (e.g. after a `304 Not Modified`).
+    pub fn touch(&self, entry: &CacheEntry) -> Result<()> {
+        let mut entry = entry.clone();
+        entry.fetched_at = now_secs();
+        self.write_entry(&entry)
+    }
+
+    /// Returns all readable entries.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let dir = self.root.join("entries");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for file in read_dir_sorted(&dir)? {
+            if is_temp_file(&file.path()) {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(file.path()) else {
+                continue;
+            };
+            if let Ok(entry) = serde_json::from_str::<CacheEntry>(&content) {
+                entries.push(entry);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Removes entries older than `max_age`, then the oldest remaining ones
+    /// until the cache is at most `max_size` bytes. Returns how many entries
+    /// were removed.
+    pub fn evict(&self, max_age: Duration, max_size: u64) -> Result<usize> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|e| std::cmp::Reverse(e.fetched_at));
+
+        let mut total = 0;
+        let mut removed = 0;
+        for entry in &entries {
+            total += entry.size;
+            if entry.age() > max_age || total > max_size {
+                fs::remove_file(self.entry_path(&entry.url, &entry.variant)).ok();
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            self.remove_unreferenced_objects()?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Deletes every cached file. Returns how many entries were removed.
+    pub fn clear(&self) -> Result<usize> {
+        let count = self.entries()?.len();
+        if self.root.exists() {
+            fs::remove_dir_all(&self.root)
+                .with_context(|| format!("Failed to remove {:?}", self.root))?;
+        }
+
+        Ok(count)
+    }
+
+    fn remove_unreferenced_objects(&self) -> Result<()> {
+        let dir = self.root.join("objects");
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        let referenced: Vec<String> = self.entries()?.into_iter().map(|e| e.digest).collect();
+        for file in read_dir_sorted(&dir)? {
+            let name = file.file_name().to_string_lossy().to_string();
+            if !referenced.contains(&name) && !is_temp_file(&file.path()) {
+                fs::remove_file(file.path()).ok();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_entry(&self, entry: &CacheEntry) -> Result<()> {
+        let content = serde_json::to_string_pretty(entry)?;
+        write_file(
+            &self.entry_path(&entry.url, &entry.variant),
+            content.as_bytes(),
+        )
+    }
+
+    fn entry_path(&self, url: &str, variant: &str) -> PathBuf {
+        let key = if variant.is_empty() {
+            sha256_hex(url.as_bytes())
+        } else {
+            sha256_hex(format!("{}\n{}", url, variant).as_bytes())
+        };
+        self.root.join("entries").join(format!("{}.json", key))
+    }
+
+    fn object_path(&self, digest: &str) -> PathBuf {
+        self.root.join("objects").join(digest)
+    }
+}
+
+/// Writes `content` to a temporary file beside `path` and renames it into
+/// place, so readers (and other processes) never see a partial file.
+fn write_file(path: &Path, content: &[u8]) -> Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let parent = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)
+        .with_context(|| format!("Failed to create cache directory {:?}", parent))?;
+    let temp = parent.join(format!(
+        ".{}.{}-{}{}",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        TEMP_SUFFIX
+    ));
+    fs::write(&temp, content).with_context(|| format!("Failed to write {:?}", temp))?;
+    fs::rename(&temp, path).map_err(|e| {
+        fs::remove_file(&temp).ok();
+        anyhow::Error::new(e).context(format!("Failed to write {:?}", path))
+    })
+}
+
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// Whether `path` is a write in progress by [`write_file`].
+fn is_temp_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.') && name.ends_with(TEMP_SUFFIX))
+}
+
+/// Lowercase hex SHA-256 of `data`, as stored in [`CacheEntry::digest`].
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_and_lookup() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path());
+
+        assert!(cache.lookup("https://example.com/a.md", "").is_none());
+
+        let entry = cache
+            .store(
+                "https://example.com/a.md",
+                "",
+                Some("\"abc\"".to_string()),
+                b"# A",
+            )
+            .unwrap();
+        let found = cache.lookup("https://example.com/a.md", "").unwrap();
+
+        assert_eq!(found.digest, entry.digest);
+        assert_eq!(found.etag.as_deref(), Some("\"abc\""));
+        assert!(found.is_fresh());
+        assert_eq!(cache.read(&found).unwrap(), b"# A");
+    }
+
+    #[test]
+    fn test_variants_are_separate_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path());
+        let url = "https://example.com/gated.md";
+
+        cache.store(url, "team-a", None, b"# A").unwrap();
+        assert!(cache.lookup(url, "").is_none());
+        assert!(cache.lookup(url, "team-b").is_none());
+        let entry = cache.lookup(url, "team-a").unwrap();
+        assert_eq!(cache.read(&entry).unwrap(), b"# A");
+    }
+
+    #[test]
+    fn test_corrupt_content_is_dropped() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path());
+        let entry = cache
+            .store("https://example.com/a.md", "", None, b"# A")
+            .unwrap();
+
+        fs::write(cache.object_path(&entry.digest), "# Tampered").unwrap();
+        let err = cache.read(&entry).unwrap_err();
+        assert!(err.to_string().contains("corrupt"));
+        assert!(cache.lookup("https://example.com/a.md", "").is_none());
+        assert!(cache.entries().unwrap().is_empty());
+
+        // No temporary files are left behind by writes
+        let names: Vec<_> = fs::read_dir(temp_dir.path().join("objects"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert!(names.is_empty(), "{:?}", names);
+    }
+
+    #[test]
+    fn test_evict_by_age_and_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path());
+
+        let mut old = cache
+            .store("https://example.com/old.md", "", None, b"old")
+            .unwrap();
+        old.fetched_at -= MAX_AGE.as_secs() + 1;
+        cache.write_entry(&old).unwrap();
+        cache
+            .store("https://example.com/a.md", "", None, b"aaaa")
+            .unwrap();
+
+        assert_eq!(cache.evict(MAX_AGE, MAX_SIZE).unwrap(), 1);
+        assert!(cache.lookup("https://example.com/old.md", "").is_none());
+        assert!(!cache.object_path(&old.digest).exists());
+
+        assert_eq!(cache.evict(MAX_AGE, 2).unwrap(), 1);
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path().join("cache"));
+        cache
+            .store("https://example.com/a.md", "", None, b"a")
+            .unwrap();
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert!(!cache.root().exists());
+        assert_eq!(cache.clear().unwrap(), 0);
+    }
+}
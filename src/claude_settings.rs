@@ -0,0 +1,86 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Claude Code's project settings file, relative to the project root.
+pub const SETTINGS_FILE: &str = ".claude/settings.json";
+
+/// The parts of `.claude/settings.json` that ccagents cares about. Other
+/// keys are ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeSettings {
+    /// Directory Claude Code loads project agents from, relative to the
+    /// project root (or absolute).
+    #[serde(default)]
+    pub agents_dir: Option<PathBuf>,
+}
+
+impl ClaudeSettings {
+    /// Loads the project's settings. A missing or unparseable file yields the
+    /// defaults, since the file belongs to Claude Code rather than ccagents.
+    pub fn load(project_root: &Path) -> Self {
+        fs::read_to_string(project_root.join(SETTINGS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the directory agents are linked into: `agentsDir` from
+/// `.claude/settings.json` when set, else `.claude/agents`.
+pub fn agents_dir(project_root: &Path) -> PathBuf {
+    match ClaudeSettings::load(project_root).agents_dir {
+        Some(dir) if !dir.as_os_str().is_empty() => project_root.join(dir),
+        _ => project_root.join(".claude").join("agents"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSource};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_agents_dir_from_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".claude")).unwrap();
+
+        fs::write(
+            project_root.join(SETTINGS_FILE),
+            r#"{"permissions": {"allow": []}, "agentsDir": "tools/claude-agents"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            agents_dir(project_root),
+            project_root.join("tools/claude-agents")
+        );
+
+        let agent = Agent::new(
+            "agent.md".to_string(),
+            AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+        );
+        assert_eq!(
+            agent.get_link_path(project_root),
+            project_root.join("tools/claude-agents/agent.md")
+        );
+    }
+
+    #[test]
+    fn test_agents_dir_falls_back_to_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let default = project_root.join(".claude").join("agents");
+
+        assert_eq!(agents_dir(project_root), default);
+
+        fs::create_dir_all(project_root.join(".claude")).unwrap();
+        fs::write(project_root.join(SETTINGS_FILE), "{ not json").unwrap();
+        assert_eq!(agents_dir(project_root), default);
+
+        fs::write(project_root.join(SETTINGS_FILE), r#"{"model": "opus"}"#).unwrap();
+        assert_eq!(agents_dir(project_root), default);
+    }
+}
@@ -1,63 +1,242 @@
-use crate::agent::{Agent, AgentSource};
+use crate::agent::{expand_env_vars, validate_subdir, Agent, AgentSource};
+use crate::archive::ArchiveKind;
+use crate::commands::print_download_warnings;
 use crate::config::{
-    ensure_ccagents_dir, ensure_claude_agents_dir, get_project_root, AgentsConfig,
+    canonicalize_parent, ensure_claude_agents_dir, ensure_managed_dir, get_project_root,
+    normalize_path, AgentsConfig, DEFAULT_MANAGED_DIR,
 };
-use crate::downloader::download_from_github;
-use crate::linker::create_symlink;
-use anyhow::Result;
+use crate::downloader::{
+    download_archive, download_github_file, resolve_default_branch, DownloadOptions,
+};
+use crate::github::{self, GitHubFileUrl, GitHubRepoPath};
+use crate::linker::{content_digest, copy_dir_all, copy_file, link_agent};
+use anyhow::{Context, Result};
 use colored::*;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-pub async fn execute(source: &str) -> Result<()> {
+/// Settings for adding an agent, from the `add` command line.
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    /// Directory for copies and downloads instead of the configured one.
+    pub output_dir: Option<PathBuf>,
+    /// Overrides the config's `default_enabled` setting for the new agent.
+    pub enabled: Option<bool>,
+    /// Reject GitHub files with an unexpected extension instead of warning.
+    pub strict: bool,
+    /// Agent name (and file name for copies and downloads) instead of the
+    /// one derived from the source.
+    pub name: Option<String>,
+    /// Name GitHub downloads after their full repository path, e.g.
+    /// `agents-universal-backend.md`, so equal file names from different
+    /// folders do not collide.
+    pub preserve_path: bool,
+    /// File name of the link in `.claude/agents` when it should differ from
+    /// the agent (storage) name.
+    pub link_name: Option<String>,
+    /// Subdirectory of `.claude/agents` to link the agent into.
+    pub subdir: Option<String>,
+    /// Description stored in `.agents.json` for the new agent.
+    pub description: Option<String>,
+    /// Succeed without changes when an agent of the same name is already
+    /// configured with the same source.
+    pub idempotent: bool,
+    /// Replace an agent of the same name that has a different source.
+    pub force: bool,
+    /// Record a GitHub agent in `.agents.json` without downloading or
+    /// linking it; the next `sync` fetches it.
+    pub manifest_only: bool,
+}
+
+pub async fn execute(sources: &[String], options: &AddOptions) -> Result<()> {
     let project_root = get_project_root()?;
-    let mut config = AgentsConfig::load(&project_root)?;
+    match sources {
+        [source] => run(&project_root, source, options).await,
+        _ => run_all(&project_root, sources, options).await,
+    }
+}
+
+/// Adds each of `sources` in turn. A failing source (e.g. a duplicate) does
+/// not stop the others; the run fails at the end if any source failed.
+async fn run_all(project_root: &Path, sources: &[String], options: &AddOptions) -> Result<()> {
+    if options.name.is_some() || options.link_name.is_some() {
+        return Err(anyhow::anyhow!(
+            "--name and --link-name can only be used with a single source"
+        ));
+    }
+
+    let mut failed = Vec::new();
+    for source in sources {
+        if let Err(e) = run(project_root, source, options).await {
+            println!("  {} {}", "✗".red(), e);
+            failed.push(source.as_str());
+        }
+        println!();
+    }
+
+    let added = sources.len() - failed.len();
+    println!(
+        "{} Added {} of {} agent{}",
+        if failed.is_empty() {
+            "✓".green().bold()
+        } else {
+            "⚠".yellow().bold()
+        },
+        added,
+        sources.len(),
+        if sources.len() == 1 { "" } else { "s" }
+    );
+
+    if !failed.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Failed to add {} source{}: {}",
+            failed.len(),
+            if failed.len() == 1 { "" } else { "s" },
+            failed.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Adds `source` to the project.
+async fn run(project_root: &Path, source: &str, add_options: &AddOptions) -> Result<()> {
+    if let Some(subdir) = &add_options.subdir {
+        validate_subdir(subdir)?;
+    }
+    let mut config = AgentsConfig::load(project_root)?;
+
+    // Copies and downloads land in --output-dir, else the configured managed dir
+    let managed_dir = add_options
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| config.managed_dir().to_path_buf());
 
     println!("{} agent from {}", "Adding".cyan().bold(), source);
 
+    // `gh:owner/repo/path[@ref]` stands for the github.com file URL
+    let expanded = github::expand_shorthand(source)?;
+    let source = expanded.as_deref().unwrap_or(source);
+
     // Determine if source is a URL or local path
-    let agent = if source.starts_with("http://") || source.starts_with("https://") {
+    let mut agent = if source.starts_with("http://") || source.starts_with("https://") {
         // Handle GitHub URL
-        if !source.contains("github.com") {
+        let options = DownloadOptions {
+            strict: add_options.strict,
+            progress: true,
+            ..DownloadOptions::from_config(&config)
+        };
+        if !github::is_github_url(&expand_env_vars(source)?, &options.github_hosts) {
             return Err(anyhow::anyhow!("Only GitHub URLs are currently supported"));
         }
+        // A file URL without `/blob/<branch>/` uses the repository's default
+        // branch, which cannot be looked up without the network
+        let source = &if add_options.manifest_only {
+            if GitHubFileUrl::parse(&expand_env_vars(source)?, &options.github_hosts).is_err()
+                && GitHubRepoPath::parse(&expand_env_vars(source)?, &options.github_hosts).is_ok()
+            {
+                return Err(anyhow::anyhow!(
+                    "--manifest-only needs a URL that names the branch (/blob/<branch>/<path>), since finding the default branch requires the network"
+                ));
+            }
+            source.to_string()
+        } else {
+            resolve_default_branch(source, &options).await?
+        };
+
+        let file_name = GitHubFileUrl::parse(&expand_env_vars(source)?, &options.github_hosts)?
+            .filename()
+            .to_string();
+        if let Some(kind) = ArchiveKind::from_name(&file_name) {
+            if add_options.manifest_only {
+                return Err(anyhow::anyhow!(
+                    "--manifest-only cannot be used with an archive, whose agents are only known once it is downloaded"
+                ));
+            }
+            return add_archive(
+                project_root,
+                &mut config,
+                source,
+                kind,
+                &managed_dir,
+                add_options,
+                &options,
+            )
+            .await;
+        }
 
-        let agent = Agent::from_url(source)?;
+        let mut agent = Agent::from_url_with_hosts(source, &options.github_hosts)?;
+        check_extension(&agent.name, &config.allowed_extensions, add_options.strict)?;
+        if let Some(name) = &add_options.name {
+            agent.name = name.clone();
+        } else if add_options.preserve_path {
+            agent.name = GitHubFileUrl::parse(&expand_env_vars(source)?, &options.github_hosts)?
+                .flattened_name();
+        }
+        if managed_dir != Path::new(DEFAULT_MANAGED_DIR) {
+            agent.managed_dir = Some(managed_dir.clone());
+        }
+
+        if check_existing(&mut config, &agent, project_root, add_options)? {
+            return Ok(());
+        }
+
+        // Refuse before downloading, which would overwrite the other file
+        check_download_collision(&config, &agent, project_root)?;
 
         // Download the agent
-        let ccagents_dir = ensure_ccagents_dir(&project_root)?;
-        println!("  {} from GitHub...", "Downloading".yellow());
-        download_from_github(source, &ccagents_dir).await?;
+        if !add_options.manifest_only {
+            let target_dir = ensure_managed_dir(project_root, &managed_dir)?;
+            println!("  {} from GitHub...", "Downloading".yellow());
+            let warnings =
+                download_github_file(source, &target_dir.join(&agent.name), &options).await?;
+            print_download_warnings(&warnings);
+        }
 
         agent
+    } else if add_options.manifest_only {
+        return Err(anyhow::anyhow!(
+            "--manifest-only only applies to GitHub URLs; local sources are copied or referenced directly"
+        ));
     } else {
         // Handle local path
-        let path = PathBuf::from(source);
-        let absolute_path = if path.is_absolute() {
-            path
-        } else {
-            project_root.join(&path)
-        };
+        let absolute_path = resolve_local_path(source, project_root);
 
         if !absolute_path.exists() {
             return Err(anyhow::anyhow!("Path does not exist: {:?}", absolute_path));
         }
 
-        // If the path is outside the project, copy it to .ccagents
-        let agent = if !absolute_path.starts_with(&project_root) {
-            let ccagents_dir = ensure_ccagents_dir(&project_root)?;
-            let agent_name = absolute_path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .ok_or_else(|| anyhow::anyhow!("Invalid path"))?;
+        // If the path is outside the project, copy it to the managed dir
+        let agent = if !absolute_path.starts_with(project_root) {
+            let target_dir = ensure_managed_dir(project_root, &managed_dir)?;
+            let agent_name = match &add_options.name {
+                Some(name) => name.as_str(),
+                None => absolute_path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .ok_or_else(|| anyhow::anyhow!("Invalid path"))?,
+            };
 
-            let target_path = ccagents_dir.join(agent_name);
+            let target_path = target_dir.join(agent_name);
 
-            println!("  {} agent to .ccagents/...", "Copying".yellow());
+            // Stored relative to the project root on save
+            let agent = Agent::new(
+                agent_name.to_string(),
+                AgentSource::Local(target_path.clone()),
+            );
+            if check_existing(&mut config, &agent, project_root, add_options)? {
+                return Ok(());
+            }
+
+            println!(
+                "  {} agent to {}/...",
+                "Copying".yellow(),
+                managed_dir.display()
+            );
 
             // Check if source is a file or directory
             if absolute_path.is_file() {
-                fs::copy(&absolute_path, &target_path)?;
+                copy_file(&absolute_path, &target_path)?;
             } else if absolute_path.is_dir() {
                 copy_dir_all(&absolute_path, &target_path)?;
             } else {
@@ -67,38 +246,51 @@ pub async fn execute(source: &str) -> Result<()> {
                 ));
             }
 
-            // Use relative path for portability
-            let relative_target = target_path
-                .strip_prefix(&project_root)
-                .unwrap_or(&target_path)
-                .to_path_buf();
-
-            Agent::new(agent_name.to_string(), AgentSource::Local(relative_target))
+            agent
         } else {
-            // Use relative path for agents within the project
-            let relative_path = absolute_path
-                .strip_prefix(&project_root)
-                .unwrap_or(&absolute_path)
-                .to_path_buf();
-
-            Agent::from_path(&relative_path)?
+            let mut agent = Agent::from_path(&absolute_path)?;
+            if let Some(name) = &add_options.name {
+                agent.name = name.clone();
+            }
+            if check_existing(&mut config, &agent, project_root, add_options)? {
+                return Ok(());
+            }
+            agent
         };
 
         agent
     };
 
-    // Add to config
-    config.add_agent(agent.clone())?;
-    config.save(&project_root)?;
+    agent.enabled = add_options.enabled.unwrap_or(config.default_enabled);
+    agent.link_name = add_options.link_name.clone();
+    agent.subdir = add_options.subdir.clone();
+    agent.description = add_options.description.clone();
+    agent.sha256 = content_digest(&agent.get_local_path(project_root)).ok();
 
-    // Create symlink if enabled
-    if agent.enabled {
-        let _claude_agents_dir = ensure_claude_agents_dir(&project_root)?;
-        let local_path = agent.get_local_path(&project_root);
-        let link_path = agent.get_link_path(&project_root);
-
-        create_symlink(&local_path, &link_path)?;
-        println!("  {} symlink in .claude/agents/", "Created".green());
+    // Add to config and create the symlink (or copy) if enabled
+    config.add_and_link(agent.clone(), project_root, !add_options.manifest_only)?;
+    if add_options.manifest_only {
+        println!(
+            "  {} Recorded in .agents.json without downloading. Run 'ccagents sync' to fetch{} it.",
+            "ℹ".blue(),
+            if agent.enabled { " and link" } else { "" }
+        );
+    } else if agent.enabled {
+        println!(
+            "  {} {} in .claude/agents/",
+            "Created".green(),
+            if config.prefer_copy {
+                "copy"
+            } else {
+                "symlink"
+            }
+        );
+    } else {
+        println!(
+            "  {} Added as disabled. Run 'ccagents enable {}' to activate it.",
+            "ℹ".blue(),
+            agent.name
+        );
     }
 
     println!(
@@ -110,21 +302,649 @@ pub async fn execute(source: &str) -> Result<()> {
     Ok(())
 }
 
-fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
-    fs::create_dir_all(dst)?;
+/// Adds every Markdown file in the `.zip` or `.tar.gz` at `url` as a local
+/// agent in `managed_dir`. All agents are checked before any file is
+/// written, so a name clash leaves the project unchanged.
+async fn add_archive(
+    project_root: &Path,
+    config: &mut AgentsConfig,
+    url: &str,
+    kind: ArchiveKind,
+    managed_dir: &Path,
+    add_options: &AddOptions,
+    options: &DownloadOptions,
+) -> Result<()> {
+    if add_options.name.is_some() || add_options.link_name.is_some() || add_options.preserve_path {
+        return Err(anyhow::anyhow!(
+            "--name, --link-name and --preserve-path cannot be used with an archive"
+        ));
+    }
+
+    let entries = download_archive(url, kind, options).await?;
+    if entries.is_empty() {
+        return Err(anyhow::anyhow!("No Markdown files found in {}", url));
+    }
 
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
+    let target_dir = ensure_managed_dir(project_root, managed_dir)?;
+    let mut updated = config.clone();
+    let mut added = Vec::new();
+    for entry in &entries {
+        let target_path = target_dir.join(&entry.name);
+        let mut agent = Agent::new(entry.name.clone(), AgentSource::Local(target_path.clone()));
+        agent.enabled = add_options.enabled.unwrap_or(config.default_enabled);
+        agent.subdir = add_options.subdir.clone();
+        agent.description = add_options.description.clone();
 
-        if ty.is_dir() {
-            copy_dir_all(&src_path, &dst_path)?;
-        } else {
-            fs::copy(&src_path, &dst_path)?;
+        if check_existing(&mut updated, &agent, project_root, add_options)? {
+            println!("  {} {} is already added", "→".cyan(), agent.name);
+            continue;
+        }
+        // A taken name is reported by `add_agent` rather than as a collision
+        if updated.get_agent(&agent.name).is_none() {
+            check_download_collision(&updated, &agent, project_root)?;
+        }
+        updated.add_agent(agent.clone())?;
+        // Never overwrite a file no agent owns
+        if target_path.exists() && !add_options.force {
+            return Err(anyhow::anyhow!(
+                "{:?} already exists. Move it aside or use --force to overwrite it.",
+                target_path
+            ));
+        }
+        added.push((agent, entry));
+    }
+
+    for (agent, entry) in &added {
+        let local_path = agent.get_local_path(project_root);
+        fs::write(&local_path, &entry.content)
+            .with_context(|| format!("Failed to write {:?}", local_path))?;
+        if let Some(stored) = updated.get_agent_mut(&agent.name) {
+            stored.sha256 = content_digest(&local_path).ok();
         }
     }
+    *config = updated;
+    config.save(project_root)?;
+
+    for (agent, _) in &added {
+        if agent.enabled {
+            ensure_claude_agents_dir(project_root)?;
+            link_agent(
+                &agent.get_local_path(project_root),
+                &agent.get_link_path(project_root),
+                config.link_mode(),
+                config.link_style,
+            )?;
+        }
+        println!(
+            "  {} {}{}",
+            "→".cyan(),
+            agent.name,
+            if agent.enabled { "" } else { " (disabled)" }
+        );
+    }
+
+    println!(
+        "\n{} Added {} agent{} from the archive",
+        "✓".green().bold(),
+        added.len(),
+        if added.len() == 1 { "" } else { "s" }
+    );
 
     Ok(())
 }
+
+/// Handles an agent named like `agent` that is already configured. With
+/// `--idempotent` the same source makes the add a no-op (returns `true`);
+/// with `--force` the existing entry is dropped so `agent` replaces it.
+/// Otherwise a differing source is an error, and without either flag the
+/// usual "already exists" error is left to [`AgentsConfig::add_agent`].
+fn check_existing(
+    config: &mut AgentsConfig,
+    agent: &Agent,
+    project_root: &Path,
+    options: &AddOptions,
+) -> Result<bool> {
+    if !options.idempotent && !options.force {
+        return Ok(false);
+    }
+    let Some(existing) = config.get_agent(&agent.name) else {
+        return Ok(false);
+    };
+
+    if options.idempotent && same_source(existing, agent, project_root) {
+        println!(
+            "{} Agent '{}' is already configured with this source",
+            "✓".green().bold(),
+            agent.name
+        );
+        return Ok(true);
+    }
+    if !options.force {
+        return Err(anyhow::anyhow!(
+            "Agent '{}' already exists with a different source. Use --force to replace it.",
+            agent.name
+        ));
+    }
+
+    println!("  {} existing agent '{}'", "Replacing".yellow(), agent.name);
+    config.agents.retain(|a| a.name != agent.name);
+
+    Ok(false)
+}
+
+/// Compares the sources of two agents: GitHub URLs as written in the config,
+/// local sources by the path they resolve to.
+fn same_source(a: &Agent, b: &Agent, project_root: &Path) -> bool {
+    match (&a.source, &b.source) {
+        (AgentSource::GitHub(a), AgentSource::GitHub(b)) => a == b,
+        (AgentSource::Local(_), AgentSource::Local(_)) => {
+            normalize_path(&a.get_local_path(project_root))
+                == normalize_path(&b.get_local_path(project_root))
+        }
+        _ => false,
+    }
+}
+
+/// Fails when a configured agent already lives at the path `agent` would be
+/// downloaded to, e.g. two GitHub URLs ending in the same file name.
+fn check_download_collision(
+    config: &AgentsConfig,
+    agent: &Agent,
+    project_root: &Path,
+) -> Result<()> {
+    let target = normalize_path(&agent.get_local_path(project_root));
+
+    match config
+        .agents
+        .iter()
+        .find(|a| normalize_path(&a.get_local_path(project_root)) == target)
+    {
+        Some(existing) => Err(anyhow::anyhow!(
+            "{:?} is already used by agent '{}'. Use --name to download this agent under a different name.",
+            target,
+            existing.name
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Warns about (or, with `strict`, rejects) a GitHub file whose extension is
+/// not in `allowed`, which usually means a mistaken URL.
+fn check_extension(filename: &str, allowed: &[String], strict: bool) -> Result<()> {
+    match github::unexpected_extension(filename, allowed) {
+        Some(warning) if strict => Err(anyhow::anyhow!(
+            "{}. Add the extension to allowed_extensions in .agents.json to accept it.",
+            warning
+        )),
+        Some(warning) => {
+            println!("  {} {}", "⚠".yellow(), warning);
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Resolves a local source argument to an absolute path, expanding a leading
+/// `~` to the home directory and joining relative paths onto the project root.
+/// `..` is collapsed first, so `../shared/agent.md` is recognized as outside
+/// the project and `docs/../agent.md` as inside it. Its directories are then
+/// canonicalized so that paths reached through symlinks compare against the
+/// canonical project root.
+fn resolve_local_path(source: &str, project_root: &Path) -> PathBuf {
+    let path = expand_tilde(source, dirs::home_dir().as_deref());
+
+    if path.is_absolute() {
+        canonicalize_parent(&normalize_path(&path))
+    } else {
+        canonicalize_parent(&normalize_path(&project_root.join(path)))
+    }
+}
+
+/// Expands `~` and `~/...` using `home`. Other forms (including `~user`) are
+/// returned unchanged.
+fn expand_tilde(source: &str, home: Option<&Path>) -> PathBuf {
+    match (source.strip_prefix('~'), home) {
+        (Some(""), Some(home)) => home.to_path_buf(),
+        (Some(rest), Some(home)) if rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(source),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_add_multiple_sources() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/remote.md")
+            .with_status(200)
+            .with_body("# Remote")
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::write(project_root.join("local.md"), "# Local").unwrap();
+
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+
+        let config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        config.save(project_root).unwrap();
+
+        let sources = vec![
+            "local.md".to_string(),
+            format!("{}/org/repo/blob/main/remote.md", server.url()),
+            // A duplicate fails without stopping the others
+            "local.md".to_string(),
+        ];
+        let result = run_all(project_root, &sources, &AddOptions::default()).await;
+
+        mock.assert_async().await;
+        assert!(result.unwrap_err().to_string().contains("1 source"));
+        let config = AgentsConfig::load(project_root).unwrap();
+        let names: Vec<&str> = config.agents.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["local.md", "remote.md"]);
+        assert!(project_root.join(".claude/agents/remote.md").is_symlink());
+    }
+
+    #[tokio::test]
+    async fn test_add_upward_relative_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let workspace = temp_dir.path().canonicalize().unwrap();
+        let project_root = workspace.join("project");
+        fs::create_dir_all(workspace.join("shared")).unwrap();
+        fs::create_dir_all(&project_root).unwrap();
+        fs::write(workspace.join("shared/outside.md"), "# Outside").unwrap();
+        fs::write(project_root.join("inside.md"), "# Inside").unwrap();
+        AgentsConfig::default().save(&project_root).unwrap();
+
+        // Leaving the project copies the file into the managed dir
+        run(
+            &project_root,
+            "../shared/outside.md",
+            &AddOptions::default(),
+        )
+        .await
+        .unwrap();
+        // A detour through a missing directory still lands inside the project
+        run(&project_root, "docs/../inside.md", &AddOptions::default())
+            .await
+            .unwrap();
+
+        let content = fs::read_to_string(project_root.join(".agents.json")).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let paths: Vec<&str> = config["agents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["source"]["value"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, vec![".ccagents/outside.md", "inside.md"]);
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/outside.md")).unwrap(),
+            "# Outside"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_archive_registers_each_markdown_file() {
+        use std::io::Write;
+
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (path, content) in [
+            ("bundle/reviewer.md", "# Reviewer"),
+            ("bundle/tester.md", "# Tester"),
+            ("bundle/README.txt", "Not an agent"),
+        ] {
+            writer
+                .start_file(path, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/releases/download/v1/agents.zip")
+            .with_header("content-type", "application/zip")
+            .with_body(bytes)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+        let config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        config.save(project_root).unwrap();
+
+        let url = format!("{}/org/repo/releases/download/v1/agents.zip", server.url());
+        run(project_root, &url, &AddOptions::default())
+            .await
+            .unwrap();
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        let names: Vec<&str> = config.agents.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["reviewer.md", "tester.md"]);
+        assert!(config.agents.iter().all(|a| a.sha256.is_some()));
+        assert!(matches!(
+            &config.agents[0].source,
+            AgentSource::Local(path) if path == Path::new(".ccagents/reviewer.md")
+        ));
+        assert_eq!(
+            fs::read_to_string(project_root.join(".claude/agents/tester.md")).unwrap(),
+            "# Tester"
+        );
+        assert!(!project_root.join(".ccagents/README.txt").exists());
+
+        // Adding it again clashes before anything is written
+        let err = run(project_root, &url, &AddOptions::default())
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Agent 'reviewer.md' already exists"));
+
+        // --force replaces the agents and their files
+        let options = AddOptions {
+            force: true,
+            ..AddOptions::default()
+        };
+        run(project_root, &url, &options).await.unwrap();
+        mock.assert_async().await;
+        assert_eq!(AgentsConfig::load(project_root).unwrap().agents.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_add_manifest_only_records_without_fetching() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+        let config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        config.save(project_root).unwrap();
+
+        let options = AddOptions {
+            manifest_only: true,
+            ..AddOptions::default()
+        };
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+        run(project_root, &url, &options).await.unwrap();
+
+        // Without a branch the default branch would have to be looked up
+        let branchless = format!("{}/org/repo/other.md", server.url());
+        let err = run(project_root, &branchless, &options).await.unwrap_err();
+        assert!(err.to_string().contains("/blob/<branch>/"));
+
+        mock.assert_async().await;
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert_eq!(config.agents.len(), 1);
+        let agent = &config.agents[0];
+        assert_eq!(agent.name, "agent.md");
+        assert!(agent.enabled);
+        assert!(agent.sha256.is_none());
+        assert!(matches!(&agent.source, AgentSource::GitHub(stored) if *stored == url));
+        assert!(!project_root.join(".ccagents").exists());
+        assert!(!project_root.join(".claude/agents/agent.md").exists());
+    }
+
+    #[tokio::test]
+    async fn test_add_idempotent_re_add() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join("agents/other")).unwrap();
+        fs::write(project_root.join("agents/review.md"), "# Review").unwrap();
+        fs::write(project_root.join("agents/other/review.md"), "# Other").unwrap();
+
+        let options = AddOptions {
+            idempotent: true,
+            ..AddOptions::default()
+        };
+        run(project_root, "agents/review.md", &options)
+            .await
+            .unwrap();
+
+        // Identical re-add: a no-op, while a plain re-add still errors
+        run(project_root, "./agents/review.md", &options)
+            .await
+            .unwrap();
+        assert!(
+            run(project_root, "agents/review.md", &AddOptions::default())
+                .await
+                .is_err()
+        );
+        assert_eq!(AgentsConfig::load(project_root).unwrap().agents.len(), 1);
+
+        // Conflicting re-add: same name, different source
+        let result = run(project_root, "agents/other/review.md", &options).await;
+        assert!(result.unwrap_err().to_string().contains("--force"));
+
+        let options = AddOptions {
+            force: true,
+            ..AddOptions::default()
+        };
+        run(project_root, "agents/other/review.md", &options)
+            .await
+            .unwrap();
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert_eq!(config.agents.len(), 1);
+        assert_eq!(
+            fs::read_to_string(project_root.join(".claude/agents/review.md")).unwrap(),
+            "# Other"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_honors_default_enabled_false() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join("agents")).unwrap();
+        fs::write(project_root.join("agents/review.md"), "# Review").unwrap();
+
+        let config = AgentsConfig {
+            default_enabled: false,
+            ..AgentsConfig::default()
+        };
+        config.save(project_root).unwrap();
+
+        run(project_root, "agents/review.md", &AddOptions::default())
+            .await
+            .unwrap();
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(!config.agents[0].enabled);
+        assert!(!project_root.join(".claude/agents/review.md").is_symlink());
+
+        // An explicit --enabled wins over the config default
+        fs::write(project_root.join("agents/build.md"), "# Build").unwrap();
+        let options = AddOptions {
+            enabled: Some(true),
+            ..AddOptions::default()
+        };
+        run(project_root, "agents/build.md", &options)
+            .await
+            .unwrap();
+        assert!(project_root.join(".claude/agents/build.md").is_symlink());
+    }
+
+    #[tokio::test]
+    async fn test_add_strict_rejects_png_github_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = "https://github.com/user/repo/blob/main/diagram.png";
+
+        let options = AddOptions {
+            strict: true,
+            ..AddOptions::default()
+        };
+        let result = run(temp_dir.path(), url, &options).await;
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("'.png'"));
+        assert!(!temp_dir.path().join(".agents.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_add_github_filename_collision_requires_name() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/org/first/raw/main/agent.md")
+            .with_body("# First")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/org/second/raw/main/agent.md")
+            .with_body("# Second")
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+        let config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        config.save(project_root).unwrap();
+
+        let first = format!("{}/org/first/blob/main/agent.md", server.url());
+        let second = format!("{}/org/second/blob/main/agent.md", server.url());
+
+        run(project_root, &first, &AddOptions::default())
+            .await
+            .unwrap();
+        let result = run(project_root, &second, &AddOptions::default()).await;
+        assert!(result.unwrap_err().to_string().contains("--name"));
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/agent.md")).unwrap(),
+            "# First"
+        );
+
+        let options = AddOptions {
+            name: Some("second-agent.md".to_string()),
+            ..AddOptions::default()
+        };
+        run(project_root, &second, &options).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(project_root.join(".claude/agents/second-agent.md")).unwrap(),
+            "# Second"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_preserve_path_keeps_folders_apart() {
+        let mut server = mockito::Server::new_async().await;
+        for folder in ["universal", "python"] {
+            server
+                .mock(
+                    "GET",
+                    format!("/org/repo/raw/main/agents/{}/backend.md", folder).as_str(),
+                )
+                .with_body(format!("# {}", folder))
+                .create_async()
+                .await;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+        let config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        config.save(project_root).unwrap();
+
+        let options = AddOptions {
+            preserve_path: true,
+            ..AddOptions::default()
+        };
+        for folder in ["universal", "python"] {
+            let url = format!(
+                "{}/org/repo/blob/main/agents/{}/backend.md",
+                server.url(),
+                folder
+            );
+            run(project_root, &url, &options).await.unwrap();
+        }
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        let names: Vec<&str> = config.agents.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["agents-universal-backend.md", "agents-python-backend.md"]
+        );
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/agents-python-backend.md")).unwrap(),
+            "# python"
+        );
+    }
+
+    #[test]
+    fn test_check_extension_warns_without_strict() {
+        assert!(check_extension("diagram.png", &[], false).is_ok());
+        assert!(check_extension("diagram.png", &[], true).is_err());
+        assert!(check_extension("agent.md", &[], true).is_ok());
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        let home = Path::new("/home/user");
+
+        assert_eq!(
+            expand_tilde("~/agents/foo.md", Some(home)),
+            PathBuf::from("/home/user/agents/foo.md")
+        );
+        assert_eq!(expand_tilde("~", Some(home)), PathBuf::from("/home/user"));
+        assert_eq!(
+            expand_tilde("agents/foo.md", Some(home)),
+            PathBuf::from("agents/foo.md")
+        );
+        assert_eq!(
+            expand_tilde("~other/foo.md", Some(home)),
+            PathBuf::from("~other/foo.md")
+        );
+        assert_eq!(expand_tilde("~/foo.md", None), PathBuf::from("~/foo.md"));
+    }
+
+    #[test]
+    fn test_resolve_local_path_expands_home() {
+        let project_root = Path::new("/project");
+        let resolved = resolve_local_path("~/agents/foo.md", project_root);
+
+        if let Some(home) = dirs::home_dir() {
+            // Home-relative paths resolve outside the project, so add copies them
+            assert_eq!(resolved, home.join("agents/foo.md"));
+            assert!(!resolved.starts_with(project_root));
+        }
+
+        assert_eq!(
+            resolve_local_path("agents/foo.md", project_root),
+            PathBuf::from("/project/agents/foo.md")
+        );
+    }
+}
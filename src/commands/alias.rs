@@ -0,0 +1,118 @@
+use crate::config::{get_project_root, AgentsConfig};
+use crate::error::CcagentsError;
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+pub fn execute(name: &str, add: Option<&str>, remove: Option<&str>) -> Result<()> {
+    let project_root = get_project_root()?;
+    let (resolved, aliases) = run(&project_root, name, add, remove)?;
+
+    if let Some(alias) = add {
+        println!(
+            "{} '{}' is now an alias of '{}'",
+            "✓".green().bold(),
+            alias,
+            resolved
+        );
+    } else if let Some(alias) = remove {
+        println!(
+            "{} Removed alias '{}' from '{}'",
+            "✓".green().bold(),
+            alias,
+            resolved
+        );
+    } else if aliases.is_empty() {
+        println!("{} Agent '{}' has no aliases", "ℹ".blue(), resolved);
+    } else {
+        println!("{}: {}", resolved.bold(), aliases.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Adds or removes an alias of the agent `name` (itself resolved like any
+/// other agent name) and saves the config. Returns the agent's name and its
+/// aliases afterwards.
+fn run(
+    project_root: &Path,
+    name: &str,
+    add: Option<&str>,
+    remove: Option<&str>,
+) -> Result<(String, Vec<String>)> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    let resolved = config.require_agent(name)?.name.clone();
+
+    if let Some(alias) = add {
+        config.add_alias(&resolved, alias)?;
+    }
+    if let Some(alias) = remove {
+        let agent = config
+            .get_agent_mut(&resolved)
+            .ok_or_else(|| CcagentsError::AgentNotFound(resolved.clone()))?;
+        if !agent.aliases.iter().any(|existing| existing == alias) {
+            return Err(anyhow::anyhow!(
+                "'{}' is not an alias of agent '{}'",
+                alias,
+                resolved
+            ));
+        }
+        agent.aliases.retain(|existing| existing != alias);
+    }
+    if add.is_some() || remove.is_some() {
+        config.save(project_root)?;
+    }
+
+    let aliases = config
+        .get_agent(&resolved)
+        .map(|agent| agent.aliases.clone())
+        .unwrap_or_default();
+
+    Ok((resolved, aliases))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSource};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_alias_add_resolve_and_remove() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+
+        let mut config = AgentsConfig::default();
+        for name in ["backend-developer.md", "frontend.md"] {
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(PathBuf::from(name)),
+                ))
+                .unwrap();
+        }
+        config.save(project_root).unwrap();
+
+        let (resolved, aliases) =
+            run(project_root, "backend-developer.md", Some("be"), None).unwrap();
+        assert_eq!(resolved, "backend-developer.md");
+        assert_eq!(aliases, vec!["be"]);
+
+        // The alias is persisted and accepted wherever a name is expected
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert_eq!(
+            config.require_agent("be").unwrap().name,
+            "backend-developer.md"
+        );
+        assert!(run(project_root, "frontend.md", Some("be"), None).is_err());
+
+        let (_, aliases) = run(project_root, "be", None, Some("be")).unwrap();
+        assert!(aliases.is_empty());
+        assert!(AgentsConfig::load(project_root)
+            .unwrap()
+            .lookup_agent("be")
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::config::{get_project_root, AgentsConfig};
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+pub fn execute(name: &str, description: Option<&str>) -> Result<()> {
+    let project_root = get_project_root()?;
+    let resolved = run(&project_root, name, description)?;
+
+    match description {
+        Some(description) => println!(
+            "{} Set description of '{}': {}",
+            "✓".green().bold(),
+            resolved,
+            description
+        ),
+        None => println!(
+            "{} Cleared description of '{}'",
+            "✓".green().bold(),
+            resolved
+        ),
+    }
+
+    Ok(())
+}
+
+/// Sets (or with `None`, clears) the configured description of the agent
+/// `name` and saves the config. Returns the agent's name.
+fn run(project_root: &Path, name: &str, description: Option<&str>) -> Result<String> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    let resolved = config.require_agent(name)?.name.clone();
+
+    let description = description
+        .map(str::trim)
+        .filter(|description| !description.is_empty())
+        .map(str::to_string);
+    if let Some(agent) = config.get_agent_mut(&resolved) {
+        agent.description = description;
+    }
+    config.save(project_root)?;
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSource};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_annotate_sets_and_clears_description() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        run(project_root, "agent.md", Some(" Reviews PRs ")).unwrap();
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert_eq!(config.agents[0].description.as_deref(), Some("Reviews PRs"));
+
+        run(project_root, "agent.md", None).unwrap();
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert_eq!(config.agents[0].description, None);
+
+        assert!(run(project_root, "missing.md", None).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use crate::cache::DownloadCache;
+use anyhow::Result;
+use colored::*;
+use std::time::Duration;
+
+pub fn list() -> Result<()> {
+    for line in list_lines(&default_cache()?)? {
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Deletes cached downloads: all of them, or with `older_than` only those
+/// last validated longer ago than that.
+pub fn clear(older_than: Option<Duration>) -> Result<()> {
+    let cache = default_cache()?;
+    let removed = clear_cache(&cache, older_than)?;
+
+    println!(
+        "{} Removed {} cached download{} from {}",
+        "✓".green().bold(),
+        removed,
+        if removed == 1 { "" } else { "s" },
+        cache.root().display()
+    );
+
+    Ok(())
+}
+
+fn default_cache() -> Result<DownloadCache> {
+    DownloadCache::default_dir()
+        .map(DownloadCache::new)
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the cache directory"))
+}
+
+fn clear_cache(cache: &DownloadCache, older_than: Option<Duration>) -> Result<usize> {
+    match older_than {
+        Some(age) => cache.evict(age, u64::MAX),
+        None => cache.clear(),
+    }
+}
+
+/// One line per cached download (newest first) followed by the total size.
+fn list_lines(cache: &DownloadCache) -> Result<Vec<String>> {
+    let mut entries = cache.entries()?;
+    if entries.is_empty() {
+        return Ok(vec![format!(
+            "No cached downloads in {}",
+            cache.root().display()
+        )]);
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.fetched_at));
+
+    let mut lines = vec![format!(
+        "{} {}",
+        "Cached downloads in".cyan().bold(),
+        cache.root().display()
+    )];
+    for entry in &entries {
+        lines.push(format!(
+            "  {:>9}  {:>5}  {}",
+            format_size(entry.size),
+            format_age(entry.age()),
+            entry.url
+        ));
+    }
+
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    lines.push(format!(
+        "\n{} {} in {} download{}",
+        "Total:".bold(),
+        format_size(total),
+        entries.len(),
+        if entries.len() == 1 { "" } else { "s" }
+    ));
+
+    Ok(lines)
+}
+
+/// Parses a duration such as `90s`, `30m`, `12h`, `7d` or `2w`.
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid duration '{}', expected e.g. 7d", value))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Invalid duration unit in '{}', expected one of: s, m, h, d, w",
+                value
+            ))
+        }
+    };
+
+    Ok(Duration::from_secs(amount * seconds))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheEntry;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Stores `content` for `url` as if it had been fetched `age` ago.
+    fn store_aged(cache: &DownloadCache, url: &str, content: &[u8], age: Duration) {
+        cache.store(url, "", None, content).unwrap();
+        for file in fs::read_dir(cache.root().join("entries")).unwrap() {
+            let path = file.unwrap().path();
+            let mut entry: CacheEntry =
+                serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            if entry.url == url {
+                entry.fetched_at -= age.as_secs();
+                fs::write(&path, serde_json::to_string(&entry).unwrap()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_list_and_clear_older_than() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path().join("cache"));
+        let day = Duration::from_secs(24 * 60 * 60);
+        store_aged(&cache, "https://example.com/new.md", b"new", Duration::ZERO);
+        store_aged(
+            &cache,
+            "https://example.com/old.md",
+            &[b'x'; 2048],
+            10 * day,
+        );
+
+        let lines = list_lines(&cache).unwrap();
+        assert!(lines[1].contains("3 B") && lines[1].contains("new.md"));
+        assert!(lines[2].contains("2.0 KiB") && lines[2].contains("10d"));
+        assert!(lines[3].contains("2.0 KiB in 2 downloads"));
+
+        assert_eq!(clear_cache(&cache, Some(7 * day)).unwrap(), 1);
+        assert!(cache.lookup("https://example.com/old.md", "").is_none());
+        assert!(cache.lookup("https://example.com/new.md", "").is_some());
+
+        assert_eq!(clear_cache(&cache, None).unwrap(), 1);
+        assert!(list_lines(&cache).unwrap()[0].starts_with("No cached downloads"));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(604800));
+        assert_eq!(parse_duration("2w").unwrap(), Duration::from_secs(1209600));
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+    }
+}
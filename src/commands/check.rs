@@ -0,0 +1,76 @@
+use crate::commands::doctor::{self, IssueType};
+use crate::config::{get_project_root, AgentsConfig};
+use crate::linker::LinkMode;
+use anyhow::Result;
+use clap::ValueEnum;
+use std::path::Path;
+
+/// Runs the doctor's checks without printing anything (except the one-line
+/// summary with `summary`) and never fixes. Returns the number of issues.
+pub fn execute(summary: bool, link_mode: Option<LinkMode>) -> Result<usize> {
+    let project_root = get_project_root()?;
+    let (agents, counts) = run(&project_root, link_mode)?;
+    let issues = counts.iter().map(|(_, count)| count).sum();
+
+    if summary {
+        println!("{}", summary_line(agents, &counts));
+    }
+
+    Ok(issues)
+}
+
+/// Returns the number of agents and the issue counts per type. `link_mode`
+/// defaults to the config's `prefer_copy`.
+fn run(
+    project_root: &Path,
+    link_mode: Option<LinkMode>,
+) -> Result<(usize, Vec<(IssueType, usize)>)> {
+    let config = AgentsConfig::load(project_root)?;
+    let link_mode = link_mode.unwrap_or(config.link_mode());
+    let counts = doctor::issue_counts(&config, project_root, link_mode)?;
+    Ok((config.agents.len(), counts))
+}
+
+/// E.g. `ok: 3 agents` or `2 issues (broken-symlink: 1, missing-source: 1): 3 agents`.
+fn summary_line(agents: usize, counts: &[(IssueType, usize)]) -> String {
+    let agents = format!("{} agent{}", agents, if agents == 1 { "" } else { "s" });
+    let issues: usize = counts.iter().map(|(_, count)| count).sum();
+    if issues == 0 {
+        return format!("ok: {}", agents);
+    }
+
+    let per_type: Vec<String> = counts
+        .iter()
+        .map(|(issue_type, count)| {
+            let name = issue_type
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default();
+            format!("{}: {}", name, count)
+        })
+        .collect();
+    format!(
+        "{} issue{} ({}): {}",
+        issues,
+        if issues == 1 { "" } else { "s" },
+        per_type.join(", "),
+        agents
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_line() {
+        assert_eq!(summary_line(1, &[]), "ok: 1 agent");
+        assert_eq!(
+            summary_line(
+                3,
+                &[(IssueType::BrokenSymlink, 2), (IssueType::MissingSource, 1)]
+            ),
+            "3 issues (broken-symlink: 2, missing-source: 1): 3 agents"
+        );
+    }
+}
@@ -1,18 +1,61 @@
-use crate::config::{get_project_root, AgentsConfig};
-use anyhow::Result;
+use crate::claude_settings;
+use crate::config::{get_project_root, normalize_path, read_dir_sorted, AgentsConfig};
+use crate::ignore::IgnoreRules;
+use anyhow::{Context, Result};
 use colored::*;
+use std::fs;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-pub fn execute(force: bool) -> Result<()> {
+pub fn execute(force: bool, prune_files: bool) -> Result<()> {
     let project_root = get_project_root()?;
-    let mut config = AgentsConfig::load(&project_root)?;
 
+    run(&project_root, prune_files, |question| {
+        if force {
+            return Ok(true);
+        }
+
+        println!("\n{}", question.yellow());
+        print!("Confirm [y/N]: ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+    })
+}
+
+/// Removes orphaned agents from the config and, with `prune_files`, deletes
+/// entries of the managed dir that no remaining agent references. `confirm`
+/// is asked before each removal.
+fn run(
+    project_root: &Path,
+    prune_files: bool,
+    mut confirm: impl FnMut(&str) -> Result<bool>,
+) -> Result<()> {
+    let mut config = AgentsConfig::load(project_root)?;
+
+    clean_orphaned_agents(project_root, &mut config, &mut confirm)?;
+
+    if prune_files {
+        prune_unreferenced_files(project_root, &config, &mut confirm)?;
+    }
+
+    Ok(())
+}
+
+fn clean_orphaned_agents(
+    project_root: &Path,
+    config: &mut AgentsConfig,
+    confirm: &mut impl FnMut(&str) -> Result<bool>,
+) -> Result<()> {
     println!("{}", "Checking for orphaned agents...".cyan().bold());
 
     // Find orphaned agents (source doesn't exist)
     let mut orphaned = Vec::new();
     for agent in &config.agents {
-        let local_path = agent.get_local_path(&project_root);
+        let local_path = agent.get_local_path(project_root);
         if !local_path.exists() {
             orphaned.push(agent.clone());
         }
@@ -43,23 +86,7 @@ pub fn execute(force: bool) -> Result<()> {
     }
 
     // Ask for confirmation or use force flag
-    let should_remove = if force {
-        true
-    } else {
-        println!(
-            "\n{}",
-            "Remove these orphaned entries from .agents.json?".yellow()
-        );
-        print!("Confirm [y/N]: ");
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-
-        matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
-    };
-
-    if should_remove {
+    if confirm("Remove these orphaned entries from .agents.json?")? {
         // Remove orphaned agents
         let initial_count = config.agents.len();
         config
@@ -69,7 +96,7 @@ pub fn execute(force: bool) -> Result<()> {
         let removed_count = initial_count - config.agents.len();
 
         // Save the cleaned configuration
-        config.save(&project_root)?;
+        config.save(project_root)?;
 
         println!(
             "\n{} Removed {} orphaned agent{}",
@@ -79,10 +106,10 @@ pub fn execute(force: bool) -> Result<()> {
         );
 
         // Also clean up any orphaned symlinks
-        let claude_agents_dir = project_root.join(".claude").join("agents");
+        let claude_agents_dir = claude_settings::agents_dir(project_root);
         if claude_agents_dir.exists() {
             for agent in &orphaned {
-                let link_path = agent.get_link_path(&project_root);
+                let link_path = agent.get_link_path(project_root);
                 if link_path.exists() || link_path.is_symlink() {
                     std::fs::remove_file(&link_path).ok();
                     println!("  {} Removed orphaned symlink: {}", "→".cyan(), agent.name);
@@ -95,3 +122,139 @@ pub fn execute(force: bool) -> Result<()> {
 
     Ok(())
 }
+
+fn prune_unreferenced_files(
+    project_root: &Path,
+    config: &AgentsConfig,
+    confirm: &mut impl FnMut(&str) -> Result<bool>,
+) -> Result<()> {
+    let managed_dir = config.managed_dir();
+    println!(
+        "\n{}",
+        format!(
+            "Checking {} for unreferenced files...",
+            managed_dir.display()
+        )
+        .cyan()
+        .bold()
+    );
+
+    let unreferenced = unreferenced_files(project_root, config)?;
+    if unreferenced.is_empty() {
+        println!("{} No unreferenced files found.", "✓".green().bold());
+        return Ok(());
+    }
+
+    println!("\n{}", "Found unreferenced files:".yellow().bold());
+    for path in &unreferenced {
+        println!(
+            "  {} {}",
+            "○".red(),
+            path.strip_prefix(project_root).unwrap_or(path).display()
+        );
+    }
+
+    if !confirm("Delete these files?")? {
+        println!("{}", "Pruning cancelled.".yellow());
+        return Ok(());
+    }
+
+    for path in &unreferenced {
+        if path.is_dir() {
+            fs::remove_dir_all(path).with_context(|| format!("Failed to remove {:?}", path))?;
+        } else {
+            fs::remove_file(path).with_context(|| format!("Failed to remove {:?}", path))?;
+        }
+    }
+
+    println!(
+        "\n{} Deleted {} unreferenced file{}",
+        "✓".green().bold(),
+        unreferenced.len(),
+        if unreferenced.len() == 1 { "" } else { "s" }
+    );
+
+    Ok(())
+}
+
+/// Returns the entries of the managed dir that are neither the source of a
+/// configured agent nor contain one. Ignored and hidden files are kept.
+fn unreferenced_files(project_root: &Path, config: &AgentsConfig) -> Result<Vec<PathBuf>> {
+    let dir = project_root.join(config.managed_dir());
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let referenced: Vec<PathBuf> = config
+        .agents
+        .iter()
+        .map(|agent| normalize_path(&agent.get_local_path(project_root)))
+        .collect();
+    let ignore = IgnoreRules::for_config(project_root, config)?;
+
+    let mut unreferenced = Vec::new();
+    for entry in read_dir_sorted(&dir)? {
+        let path = entry.path();
+        if !ignore.is_agent_entry(&path) {
+            continue;
+        }
+
+        let normalized = normalize_path(&path);
+        if !referenced
+            .iter()
+            .any(|source| source.starts_with(&normalized))
+        {
+            unreferenced.push(path);
+        }
+    }
+
+    Ok(unreferenced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSource};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_prune_files_only_with_flag() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = project_root.join(".ccagents");
+        fs::create_dir_all(ccagents_dir.join("bundle")).unwrap();
+        fs::write(ccagents_dir.join("kept.md"), "# Kept").unwrap();
+        fs::write(ccagents_dir.join("bundle/agent.md"), "# Bundled").unwrap();
+        fs::write(ccagents_dir.join("leftover.md"), "# Leftover").unwrap();
+        fs::create_dir_all(ccagents_dir.join("old-dir")).unwrap();
+        fs::write(ccagents_dir.join(".gitkeep"), "").unwrap();
+
+        let mut config = AgentsConfig::default();
+        for (name, path) in [
+            ("kept.md", ".ccagents/kept.md"),
+            ("agent.md", ".ccagents/bundle/agent.md"),
+        ] {
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(PathBuf::from(path)),
+                ))
+                .unwrap();
+        }
+        config.save(project_root).unwrap();
+
+        run(project_root, false, |_| Ok(true)).unwrap();
+        assert!(ccagents_dir.join("leftover.md").exists());
+
+        // Declining keeps everything
+        run(project_root, true, |_| Ok(false)).unwrap();
+        assert!(ccagents_dir.join("leftover.md").exists());
+
+        run(project_root, true, |_| Ok(true)).unwrap();
+        assert!(!ccagents_dir.join("leftover.md").exists());
+        assert!(!ccagents_dir.join("old-dir").exists());
+        assert!(ccagents_dir.join("kept.md").exists());
+        assert!(ccagents_dir.join("bundle/agent.md").exists());
+        assert!(ccagents_dir.join(".gitkeep").exists());
+    }
+}
@@ -1,37 +1,277 @@
+use crate::commands::{read_names, NameSummary};
 use crate::config::{get_project_root, AgentsConfig};
-use crate::linker::remove_symlink;
-use anyhow::Result;
+use crate::error::CcagentsError;
+use crate::linker::unlink_agent;
+use anyhow::{Context, Result};
 use colored::*;
+use std::fs;
+use std::io;
+use std::path::Path;
 
-pub fn execute(name: &str) -> Result<()> {
+pub fn execute(names: &[String], glob: bool, remove_file: bool, stdin: bool) -> Result<()> {
     let project_root = get_project_root()?;
-    let mut config = AgentsConfig::load(&project_root)?;
 
-    // Find the agent
+    if stdin {
+        let names = read_names(io::stdin().lock())?;
+        return run_names(&project_root, &names, remove_file)?.finish("Disabled");
+    }
+
+    match names {
+        [] => return Err(anyhow::anyhow!("Provide an agent name or --stdin")),
+        _ if glob => {}
+        [name] => return run(&project_root, name, remove_file),
+        _ => return run_names(&project_root, names, remove_file)?.finish("Disabled"),
+    }
+
+    // Disable every agent whose name matches one of the patterns
+    for pattern in names {
+        let names = AgentsConfig::load_required(&project_root)?.match_agents(pattern)?;
+        println!(
+            "{} {} agent{} match '{}'",
+            "ℹ".blue(),
+            names.len(),
+            if names.len() == 1 { "" } else { "s" },
+            pattern
+        );
+        for name in &names {
+            run(&project_root, name, remove_file)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Disables each of `names`, continuing past names that fail, and saves the
+/// configuration once at the end.
+fn run_names(project_root: &Path, names: &[String], remove_file: bool) -> Result<NameSummary> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    let mut summary = NameSummary::default();
+    for name in names {
+        let result = disable_agent(&mut config, project_root, name, remove_file);
+        summary.record(name, result);
+    }
+    config.save(project_root)?;
+    Ok(summary)
+}
+
+/// Disables `name` by removing its symlink. The backing file is kept so the
+/// agent can be re-enabled instantly, unless `remove_file` is set.
+fn run(project_root: &Path, name: &str, remove_file: bool) -> Result<()> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    let result = disable_agent(&mut config, project_root, name, remove_file);
+    // Saved even when removing the file failed, since the link is gone
+    config.save(project_root)?;
+    result
+}
+
+/// Removes the link of `name` and marks it disabled in `config`, which the
+/// caller saves.
+fn disable_agent(
+    config: &mut AgentsConfig,
+    project_root: &Path,
+    name: &str,
+    remove_file: bool,
+) -> Result<()> {
+    // Find the agent by name or alias, falling back to a case-insensitive match
+    let agent = config.require_agent(name)?;
+    let resolved = agent.name.clone();
+    if resolved != name && !agent.aliases.iter().any(|alias| alias == name) {
+        println!(
+            "{} Using '{}' (case-insensitive match for '{}')",
+            "ℹ".blue(),
+            resolved,
+            name
+        );
+    }
+    let name = resolved.as_str();
+    let link_mode = config.link_mode();
+    let managed_dir = project_root.join(config.managed_dir());
     let agent = config
         .get_agent_mut(name)
-        .ok_or_else(|| anyhow::anyhow!("Agent '{}' not found in .agents.json", name))?;
+        .ok_or_else(|| CcagentsError::AgentNotFound(name.to_string()))?;
+
+    let local_path = agent.get_local_path(project_root);
 
     if !agent.enabled {
         println!("{} Agent '{}' is already disabled", "ℹ".blue(), name);
+        if remove_file {
+            remove_backing_file(&local_path, &managed_dir)?;
+        }
         return Ok(());
     }
 
     // Disable the agent
     agent.enabled = false;
 
-    // Remove symlink
-    let link_path = agent.get_link_path(&project_root);
+    // Remove the symlink, or the copy with `prefer_copy`
+    let link_path = agent.get_link_path(project_root);
 
     if link_path.exists() || link_path.is_symlink() {
-        remove_symlink(&link_path)?;
-        println!("  {} Removed symlink from .claude/agents/", "→".cyan());
+        let kind = if link_path.is_symlink() {
+            "symlink"
+        } else {
+            "copy"
+        };
+        unlink_agent(&link_path, link_mode)?;
+        println!("  {} Removed {} from .claude/agents/", "→".cyan(), kind);
     }
 
-    // Save config
-    config.save(&project_root)?;
+    if remove_file {
+        remove_backing_file(&local_path, &managed_dir)?;
+    }
 
     println!("{} Agent '{}' has been disabled", "✓".green().bold(), name);
+    if !remove_file && local_path.exists() {
+        println!(
+            "  {} File kept at {}; run 'ccagents enable {}' to re-enable it instantly",
+            "ℹ".blue(),
+            local_path.display(),
+            name
+        );
+    }
 
     Ok(())
 }
+
+/// Deletes the agent's copy in the managed directory. Files elsewhere, such
+/// as the source of a local agent, belong to the user and are kept.
+fn remove_backing_file(local_path: &Path, managed_dir: &Path) -> Result<()> {
+    if !local_path.exists() {
+        return Ok(());
+    }
+
+    if !is_inside(local_path, managed_dir) {
+        println!(
+            "  {} Kept {}: only files in {} are removed",
+            "⚠".yellow(),
+            local_path.display(),
+            managed_dir.display()
+        );
+        return Ok(());
+    }
+
+    if local_path.is_dir() {
+        fs::remove_dir_all(local_path)
+    } else {
+        fs::remove_file(local_path)
+    }
+    .with_context(|| format!("Failed to remove {:?}", local_path))?;
+    println!("  {} Removed {}", "→".cyan(), local_path.display());
+
+    Ok(())
+}
+
+/// Whether `path` is below `dir`. The parent of `path` is resolved rather
+/// than `path` itself, so a symlink in `dir` counts as inside it.
+fn is_inside(path: &Path, dir: &Path) -> bool {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return false;
+    };
+    match (parent.canonicalize(), dir.canonicalize()) {
+        (Ok(parent), Ok(dir)) => parent.join(name).starts_with(dir),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSource};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn setup_enabled_agent(project_root: &Path) {
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+        fs::write(project_root.join(".ccagents/agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_and_link(
+                Agent::new(
+                    "agent.md".to_string(),
+                    AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+                ),
+                project_root,
+                true,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_disable_keeps_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        setup_enabled_agent(project_root);
+
+        run(project_root, "agent.md", false).unwrap();
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(!config.get_agent("agent.md").unwrap().enabled);
+        assert!(!project_root.join(".claude/agents/agent.md").is_symlink());
+        assert!(project_root.join(".ccagents/agent.md").exists());
+    }
+
+    #[test]
+    fn test_disable_remove_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        setup_enabled_agent(project_root);
+
+        run(project_root, "agent.md", true).unwrap();
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(!config.get_agent("agent.md").unwrap().enabled);
+        assert!(!project_root.join(".claude/agents/agent.md").is_symlink());
+        assert!(!project_root.join(".ccagents/agent.md").exists());
+    }
+
+    #[test]
+    fn test_disable_remove_file_keeps_local_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join("src")).unwrap();
+        fs::write(project_root.join("src/b.md"), "# Mine").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_and_link(
+                Agent::new(
+                    "b.md".to_string(),
+                    AgentSource::Local(PathBuf::from("src/b.md")),
+                ),
+                project_root,
+                true,
+            )
+            .unwrap();
+
+        run(project_root, "b.md", true).unwrap();
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(!config.get_agent("b.md").unwrap().enabled);
+        assert!(!project_root.join(".claude/agents/b.md").is_symlink());
+        assert_eq!(
+            fs::read_to_string(project_root.join("src/b.md")).unwrap(),
+            "# Mine"
+        );
+    }
+
+    #[test]
+    fn test_disable_names_continues_past_unknown() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        setup_enabled_agent(project_root);
+
+        let input = "agent.md\n\n  bogus.md  \nagent.md\n";
+        let names = read_names(input.as_bytes()).unwrap();
+        assert_eq!(names, vec!["agent.md", "bogus.md", "agent.md"]);
+
+        let summary = run_names(project_root, &names, false).unwrap();
+        assert_eq!(summary.succeeded, 2);
+        assert_eq!(summary.not_found, vec!["bogus.md"]);
+        assert!(summary.failed.is_empty());
+        assert!(summary.finish("Disabled").is_err());
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(!config.get_agent("agent.md").unwrap().enabled);
+    }
+}
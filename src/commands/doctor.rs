@@ -1,12 +1,30 @@
-use crate::agent::AgentSource;
-use crate::config::{ensure_claude_agents_dir, get_project_root, AgentsConfig};
-use crate::linker::{create_symlink, is_symlink_valid, remove_symlink};
-use anyhow::Result;
+use crate::agent::{expand_env_vars, Agent, AgentSource};
+use crate::claude_settings;
+use crate::commands::print_skipped_names;
+use crate::config::{
+    ensure_claude_agents_dir, get_project_root, managed_dir_problem, scan_link_dir, AgentsConfig,
+};
+use crate::error::CcagentsError;
+use crate::github::{self, GitHubFileUrl};
+use crate::ignore::IgnoreRules;
+use crate::linker::{
+    content_digest, copy_matches_source, create_symlink, link_agent, remove_symlink,
+    resolve_symlink_target, symlink_status, LinkMode, SymlinkStatus,
+};
+use crate::output::{OutputFormat, Presenter, Report, StdoutPresenter};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
 use colored::*;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug)]
+/// File in the managed dir recording the changes of each `doctor --fix` run.
+const HISTORY_FILE: &str = ".doctor-history.json";
+
+#[derive(Debug, Serialize)]
 struct Issue {
     agent_name: String,
     issue_type: IssueType,
@@ -14,61 +32,903 @@ struct Issue {
     fixable: bool,
 }
 
-#[derive(Debug)]
-enum IssueType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueType {
     MissingSource,
     BrokenSymlink,
     MissingSymlink,
     DuplicateAgent,
+    DuplicateSource,
     OrphanedSymlink,
     UnmanagedFile,
+    UpstreamMissing,
+    DriftedCopy,
+    MixedLinkModes,
+    InvalidGithubUrl,
+    ManagedDirNotDirectory,
+    MissingExtension,
 }
 
-pub fn execute(fix: bool) -> Result<()> {
-    let project_root = get_project_root()?;
+impl IssueType {
+    /// What the issue means and how to resolve it by hand, shown by
+    /// `doctor --explain`.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            IssueType::MissingSource => {
+                "The agent's file no longer exists, so there is nothing to link. Restore the file, \
+                 or run `ccagents update <name>` to download a GitHub agent again. `--fix` removes \
+                 the agent from .agents.json instead (`ccagents doctor --undo` restores it)."
+            }
+            IssueType::BrokenSymlink => {
+                "The entry in .claude/agents is dangling, points at another file, or is a regular \
+                 file in the way. Run `ccagents relink` to recreate every link; move a regular \
+                 file aside by hand first."
+            }
+            IssueType::MissingSymlink => {
+                "The agent is enabled but not linked into .claude/agents, so Claude Code does not \
+                 see it. Run `ccagents sync` to link it, or `ccagents disable <name>` if it should \
+                 stay unlinked."
+            }
+            IssueType::DuplicateAgent => {
+                "Several entries in .agents.json share this name and only the first is linked. \
+                 Delete or rename the extra entries in .agents.json; `--fix` removes all but the \
+                 first."
+            }
+            IssueType::DuplicateSource => {
+                "Two agents use the same source file, so Claude Code sees the same agent twice. \
+                 Delete one of the entries in .agents.json, or `ccagents disable <name>` it."
+            }
+            IssueType::OrphanedSymlink => {
+                "A symlink in .claude/agents belongs to no agent in .agents.json, usually one that \
+                 was removed. Delete it with `rm`, or run `ccagents sync`, which removes stale links."
+            }
+            IssueType::UnmanagedFile => {
+                "A file in .claude/agents is not managed by ccagents, so sync never updates it. Run \
+                 `ccagents import <file>` to move it into the managed directory, or add it to \
+                 .ccagentsignore to keep it as is."
+            }
+            IssueType::UpstreamMissing => {
+                "`ccagents update` found the GitHub file deleted upstream and kept the local copy. \
+                 Point the agent at the file's new URL in .agents.json, or run `ccagents update \
+                 <name> --remove-gone` to drop it."
+            }
+            IssueType::DriftedCopy => {
+                "The copy in .claude/agents differs from its source, usually because it was edited \
+                 in place. Copy your edits into the source (`ccagents edit <name>`), then run \
+                 `ccagents sync --copy` to refresh the copy."
+            }
+            IssueType::MixedLinkModes => {
+                "Some agents are symlinked and others copied, e.g. after syncing with and without \
+                 --copy. Run `ccagents sync` or `ccagents sync --copy` to place all agents one way."
+            }
+            IssueType::InvalidGithubUrl => {
+                "The source URL does not name a file, so the agent cannot be downloaded. Replace it \
+                 in .agents.json with a file link such as \
+                 https://github.com/user/repo/blob/main/agent.md."
+            }
+            IssueType::ManagedDirNotDirectory => {
+                "The managed directory is a file or symlink, so downloads and imports cannot write \
+                 into it. Move it aside (e.g. `mv .ccagents .ccagents.bak`), then run `ccagents \
+                 sync` to download GitHub agents again."
+            }
+            IssueType::MissingExtension => {
+                "Claude Code only loads agent files ending in .md, so this agent is ignored even \
+                 when linked. Set \"link_name\" to a name ending in .md in .agents.json and run \
+                 `ccagents sync`."
+            }
+        }
+    }
+}
+
+/// Issues found by the diagnostic checks.
+#[derive(Debug, Serialize)]
+struct DoctorReport {
+    issues: Vec<Issue>,
+    /// With `--explain`, the explanation of each issue type present.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    explanations: BTreeMap<IssueType, &'static str>,
+}
+
+impl DoctorReport {
+    fn new(issues: Vec<Issue>, explain: bool) -> Self {
+        let explanations = if explain {
+            issues
+                .iter()
+                .map(|issue| (issue.issue_type, issue.issue_type.explanation()))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+        Self {
+            issues,
+            explanations,
+        }
+    }
+}
+
+impl Report for DoctorReport {
+    fn table(&self) -> Vec<String> {
+        if self.issues.is_empty() {
+            return vec![format!(
+                "{} All checks passed! No issues found.",
+                "✓".green().bold()
+            )];
+        }
+
+        let mut lines = vec![
+            format!(
+                "{} Found {} issue{}:",
+                "⚠".yellow().bold(),
+                self.issues.len(),
+                if self.issues.len() == 1 { "" } else { "s" }
+            ),
+            String::new(),
+        ];
+
+        for issue in &self.issues {
+            let icon = match issue.issue_type {
+                IssueType::MissingSource => "✗".red(),
+                IssueType::BrokenSymlink | IssueType::MissingSymlink => "⚠".yellow(),
+                IssueType::DuplicateAgent | IssueType::DuplicateSource => "⚠".yellow(),
+                IssueType::OrphanedSymlink => "○".yellow(),
+                IssueType::UnmanagedFile => "◆".blue(),
+                IssueType::UpstreamMissing | IssueType::DriftedCopy | IssueType::MixedLinkModes => {
+                    "⚠".yellow()
+                }
+                IssueType::InvalidGithubUrl | IssueType::ManagedDirNotDirectory => "✗".red(),
+                IssueType::MissingExtension => "ℹ".blue(),
+            };
+
+            lines.push(format!(
+                "  {} {} - {}",
+                icon,
+                issue.agent_name.bold(),
+                issue.description
+            ));
+
+            if issue.fixable {
+                lines.push(format!(
+                    "    {} This issue can be fixed automatically",
+                    "→".green()
+                ));
+            } else {
+                lines.push(format!("    {} Manual intervention required", "→".red()));
+            }
+            if let Some(explanation) = self.explanations.get(&issue.issue_type) {
+                lines.push(format!("    {}", explanation.dimmed()));
+            }
+        }
+
+        lines
+    }
+
+    /// One `CHECK\tAGENT\tFIXABLE\tDESCRIPTION` line per issue, where
+    /// `FIXABLE` is `fixable` or `manual`. With `--explain` an
+    /// `\tEXPLANATION` column follows. This is the `--porcelain` format
+    /// hooks parse: the first three columns keep their order and values, and
+    /// new columns are only ever appended.
+    fn plain(&self) -> Vec<String> {
+        self.issues
+            .iter()
+            .map(|issue| {
+                let mut line = format!(
+                    "{}\t{}\t{}\t{}",
+                    issue
+                        .issue_type
+                        .to_possible_value()
+                        .map(|value| value.get_name().to_string())
+                        .unwrap_or_default(),
+                    issue.agent_name,
+                    if issue.fixable { "fixable" } else { "manual" },
+                    issue.description
+                );
+                if let Some(explanation) = self.explanations.get(&issue.issue_type) {
+                    line.push('\t');
+                    line.push_str(explanation);
+                }
+                line
+            })
+            .collect()
+    }
+}
+
+/// Checks the project for issues. `link_mode` says how agents are expected to
+/// be placed in `.claude/agents` (by default as the config's `prefer_copy`
+/// says); in copy mode copies are validated by content
+/// instead of expecting symlinks. `--fix` is only available with table output,
+/// since its progress lines would mix with the report. `explain` adds what
+/// each issue means and how to resolve it by hand.
+pub fn execute(
+    fix: bool,
+    explain: bool,
+    only: &[IssueType],
+    link_mode: Option<LinkMode>,
+    output: OutputFormat,
+) -> Result<()> {
+    if fix && output != OutputFormat::Table {
+        return Err(anyhow::anyhow!(
+            "--fix cannot be combined with --output json or --output plain"
+        ));
+    }
+
+    run(&get_project_root()?, fix, explain, only, link_mode, output)
+}
+
+fn run(
+    project_root: &Path,
+    fix: bool,
+    explain: bool,
+    only: &[IssueType],
+    link_mode: Option<LinkMode>,
+    output: OutputFormat,
+) -> Result<()> {
+    let project_root = project_root.to_path_buf();
     let mut config = AgentsConfig::load(&project_root)?;
+    let link_mode = link_mode.unwrap_or(config.link_mode());
+
+    if output == OutputFormat::Table {
+        println!("{}", "Running diagnostics...".cyan().bold());
+        println!();
+    }
+
+    let report = DoctorReport::new(
+        collect_issues(&config, &project_root, only, link_mode)?,
+        explain,
+    );
+    StdoutPresenter::new(output).present(&report)?;
+
+    let issues = report.issues;
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+    if output != OutputFormat::Table {
+        return Err(CcagentsError::IssuesFound(issues.len()).into());
+    }
+
+    // Apply fixes if requested
+    if fix {
+        println!();
+        println!("{}", "Applying fixes...".cyan().bold());
+
+        let fixed_count = apply_fixes(&project_root, &mut config, &issues, link_mode)?;
+
+        println!();
+        println!(
+            "{} Fixed {} of {} issue{}",
+            "✓".green().bold(),
+            fixed_count,
+            issues.len(),
+            if issues.len() == 1 { "" } else { "s" }
+        );
+
+        if fixed_count < issues.len() {
+            return Err(CcagentsError::IssuesFound(issues.len() - fixed_count).into());
+        }
+    } else {
+        println!();
+        println!(
+            "Run {} to automatically fix these issues",
+            "ccagents doctor --fix".cyan()
+        );
+
+        return Err(CcagentsError::IssuesFound(issues.len()).into());
+    }
+
+    Ok(())
+}
+
+/// Applies the fix for each fixable issue, saves the config if it changed
+/// and records the changes for `doctor --undo`. Returns the number of issues
+/// fixed.
+fn apply_fixes(
+    project_root: &Path,
+    config: &mut AgentsConfig,
+    issues: &[Issue],
+    link_mode: LinkMode,
+) -> Result<usize> {
+    let claude_agents_dir = claude_settings::agents_dir(project_root);
+    let mut fixed_count = 0;
+    let mut config_modified = false;
+    let mut changes = Vec::new();
+    let relative = |path: &Path| {
+        path.strip_prefix(project_root)
+            .unwrap_or(path)
+            .to_path_buf()
+    };
+
+    for issue in issues {
+        if !issue.fixable {
+            continue;
+        }
+
+        match issue.issue_type {
+            IssueType::MissingSource => {
+                // For GitHub sources, we could re-download, but for now we'll remove
+                while let Some(index) = config
+                    .agents
+                    .iter()
+                    .position(|a| a.name == issue.agent_name)
+                {
+                    let agent = config.agents.remove(index);
+                    changes.push(FixChange::RemovedAgent {
+                        index,
+                        agent: Box::new(agent),
+                    });
+                }
+                config_modified = true;
+                println!(
+                    "  {} Removed agent with missing source: {}",
+                    "✓".green(),
+                    issue.agent_name
+                );
+                fixed_count += 1;
+            }
+            IssueType::BrokenSymlink => {
+                // Remove and recreate the symlink
+                if let Some(agent) = config.agents.iter().find(|a| a.name == issue.agent_name) {
+                    let link_path = agent.get_link_path(project_root);
+                    let local_path = agent.get_local_path(project_root);
+
+                    if let Ok(target) = fs::read_link(&link_path) {
+                        remove_symlink(&link_path)?;
+                        changes.push(FixChange::RemovedSymlink {
+                            path: relative(&link_path),
+                            target,
+                        });
+                    }
+                    if local_path.exists() {
+                        link_agent(&local_path, &link_path, link_mode, config.link_style)?;
+                        changes.push(FixChange::created_link(&link_path, relative(&link_path)));
+                        println!(
+                            "  {} Fixed broken symlink: {}",
+                            "✓".green(),
+                            issue.agent_name
+                        );
+                        fixed_count += 1;
+                    }
+                }
+            }
+            IssueType::MissingSymlink | IssueType::DriftedCopy => {
+                // Create the missing symlink, or refresh the copy
+                if let Some(agent) = config.agents.iter().find(|a| a.name == issue.agent_name) {
+                    let link_path = agent.get_link_path(project_root);
+                    let local_path = agent.get_local_path(project_root);
+
+                    ensure_claude_agents_dir(project_root)?;
+                    link_agent(&local_path, &link_path, link_mode, config.link_style)?;
+                    let path = relative(&link_path);
+                    changes.push(match issue.issue_type {
+                        IssueType::DriftedCopy => FixChange::RefreshedCopy { path },
+                        _ => FixChange::created_link(&link_path, path),
+                    });
+                    println!(
+                        "  {} {}: {}",
+                        "✓".green(),
+                        match (issue.issue_type, link_mode) {
+                            (IssueType::DriftedCopy, _) => "Refreshed drifted copy",
+                            (_, LinkMode::Copy) => "Created missing copy",
+                            (_, LinkMode::Symlink) => "Created missing symlink",
+                        },
+                        issue.agent_name
+                    );
+                    fixed_count += 1;
+                }
+            }
+            IssueType::DuplicateAgent => {
+                // Remove duplicates, keeping only the first occurrence
+                let mut seen = HashSet::new();
+                let mut index = 0;
+                while index < config.agents.len() {
+                    if seen.insert(config.agents[index].name.clone()) {
+                        index += 1;
+                    } else {
+                        let agent = config.agents.remove(index);
+                        changes.push(FixChange::RemovedAgent {
+                            index,
+                            agent: Box::new(agent),
+                        });
+                    }
+                }
+                config_modified = true;
+                println!(
+                    "  {} Removed duplicate agent: {}",
+                    "✓".green(),
+                    issue.agent_name
+                );
+                fixed_count += 1;
+            }
+            IssueType::OrphanedSymlink => {
+                // Remove the orphaned symlink
+                let link_path = claude_agents_dir.join(&issue.agent_name);
+                if let Ok(target) = fs::read_link(&link_path) {
+                    remove_symlink(&link_path)?;
+                    changes.push(FixChange::RemovedSymlink {
+                        path: relative(&link_path),
+                        target,
+                    });
+                }
+                println!(
+                    "  {} Removed orphaned symlink: {}",
+                    "✓".green(),
+                    issue.agent_name
+                );
+                fixed_count += 1;
+            }
+            IssueType::DuplicateSource
+            | IssueType::UpstreamMissing
+            | IssueType::MixedLinkModes
+            | IssueType::InvalidGithubUrl
+            | IssueType::ManagedDirNotDirectory
+            | IssueType::MissingExtension => {
+                // Not fixable: the user has to decide which entry, mode, URL or
+                // directory to keep
+            }
+            IssueType::UnmanagedFile => {
+                // Import the unmanaged file
+                println!("  {} Unmanaged file '{}' detected - run 'ccagents import' to convert to managed agent", "ℹ".blue(), issue.agent_name);
+                // We don't automatically fix this - require explicit import command
+            }
+        }
+    }
+
+    if config_modified {
+        config.save(project_root)?;
+    }
+    if !changes.is_empty() {
+        let path = history_path(project_root, config);
+        let mut history = FixHistory::load(&path)?;
+        history.batches.push(FixBatch {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            changes,
+        });
+        history.save(&path)?;
+    }
+
+    Ok(fixed_count)
+}
+
+/// One change made by `doctor --fix`, recorded so `doctor --undo` can
+/// reverse it. Paths are relative to the project root.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum FixChange {
+    /// `agent` was removed from `.agents.json` at `index`.
+    RemovedAgent { index: usize, agent: Box<Agent> },
+    /// A symlink pointing at `target` was deleted.
+    RemovedSymlink { path: PathBuf, target: PathBuf },
+    /// A link (or copy) was created where there was none. `target` is the
+    /// symlink's target and `digest` the copy's [`content_digest`], so undo
+    /// only removes what is still the created link.
+    CreatedLink {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target: Option<PathBuf>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        digest: Option<String>,
+    },
+    /// A drifted copy was overwritten from its source; the old content was
+    /// not kept.
+    RefreshedCopy { path: PathBuf },
+}
+
+impl FixChange {
+    /// Records the link or copy just created at `link_path`.
+    fn created_link(link_path: &Path, path: PathBuf) -> Self {
+        let (target, digest) = if link_path.is_symlink() {
+            (fs::read_link(link_path).ok(), None)
+        } else {
+            (None, content_digest(link_path).ok())
+        };
+        FixChange::CreatedLink {
+            path,
+            target,
+            digest,
+        }
+    }
+}
+
+/// The fix batches of `doctor --fix` runs, oldest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FixHistory {
+    batches: Vec<FixBatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FixBatch {
+    /// Seconds since the Unix epoch.
+    timestamp: u64,
+    changes: Vec<FixChange>,
+}
+
+impl FixHistory {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {:?}", path))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content).with_context(|| format!("Failed to write {:?}", path))
+    }
+}
+
+fn history_path(project_root: &Path, config: &AgentsConfig) -> PathBuf {
+    project_root.join(config.managed_dir()).join(HISTORY_FILE)
+}
+
+/// Reverses the most recent `doctor --fix` batch.
+pub fn undo() -> Result<()> {
+    let project_root = get_project_root()?;
+    let (restored, failed) = undo_last(&project_root)?;
 
-    println!("{}", "Running diagnostics...".cyan().bold());
-    println!();
+    for line in &restored {
+        println!("  {} {}", "✓".green(), line);
+    }
+    for line in &failed {
+        println!("  {} {}", "✗".red(), line);
+    }
+
+    if failed.is_empty() {
+        println!(
+            "\n{} Undid {} change{}",
+            "✓".green().bold(),
+            restored.len(),
+            if restored.len() == 1 { "" } else { "s" }
+        );
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "{} of {} changes could not be undone",
+            failed.len(),
+            restored.len() + failed.len()
+        ))
+    }
+}
+
+/// Issue types whose fixes only touch links in `.claude/agents`: missing
+/// links are created, broken ones re-pointed and orphaned ones removed.
+/// `repair` applies these and nothing else.
+const LINK_ONLY_FIXES: [IssueType; 3] = [
+    IssueType::MissingSymlink,
+    IssueType::BrokenSymlink,
+    IssueType::OrphanedSymlink,
+];
+
+/// Fixes link problems without touching `.agents.json` or agent files; the
+/// changes can be reversed with `doctor --undo` like those of `doctor --fix`.
+pub fn repair() -> Result<()> {
+    let project_root = get_project_root()?;
+
+    println!("{}", "Repairing links...".cyan().bold());
+
+    let (fixed, failed, remaining) = repair_links(&project_root)?;
+
+    println!(
+        "\n{} Repaired {} link{}",
+        "✓".green().bold(),
+        fixed,
+        if fixed == 1 { "" } else { "s" }
+    );
+    if remaining > 0 {
+        println!(
+            "  {} {} other issue{} left untouched. Run 'ccagents doctor' to review {}.",
+            "ℹ".blue(),
+            remaining,
+            if remaining == 1 { "" } else { "s" },
+            if remaining == 1 { "it" } else { "them" }
+        );
+    }
+
+    if failed > 0 {
+        return Err(CcagentsError::IssuesFound(failed).into());
+    }
+
+    Ok(())
+}
+
+/// Applies the fixes of [`LINK_ONLY_FIXES`]. Returns the number of links
+/// repaired, of link issues that could not be repaired, and of other issues
+/// left for `doctor`.
+fn repair_links(project_root: &Path) -> Result<(usize, usize, usize)> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    let link_mode = config.link_mode();
+
+    let (repairable, remaining): (Vec<Issue>, Vec<Issue>) =
+        collect_issues(&config, project_root, &[], link_mode)?
+            .into_iter()
+            .partition(|issue| issue.fixable && LINK_ONLY_FIXES.contains(&issue.issue_type));
+
+    let fixed = apply_fixes(project_root, &mut config, &repairable, link_mode)?;
+    Ok((fixed, repairable.len() - fixed, remaining.len()))
+}
+
+/// Pops the most recent fix batch from the history and reverses its changes
+/// in reverse order. Returns descriptions of the restored changes and of
+/// those that could not be undone.
+fn undo_last(project_root: &Path) -> Result<(Vec<String>, Vec<String>)> {
+    let mut config = AgentsConfig::load(project_root)?;
+    let path = history_path(project_root, &config);
+    let mut history = FixHistory::load(&path)?;
+    let batch = history
+        .batches
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("Nothing to undo: no 'doctor --fix' changes recorded"))?;
+
+    let mut restored = Vec::new();
+    let mut failed = Vec::new();
+    let mut config_modified = false;
+    for change in batch.changes.into_iter().rev() {
+        match change {
+            FixChange::RemovedAgent { index, agent } => {
+                restored.push(format!("Restored agent '{}' in .agents.json", agent.name));
+                config.agents.insert(index.min(config.agents.len()), *agent);
+                config_modified = true;
+            }
+            FixChange::RemovedSymlink { path, target } => {
+                let link_path = project_root.join(&path);
+                if link_path.exists() || link_path.is_symlink() {
+                    failed.push(format!(
+                        "Cannot restore symlink {}: the path is in use",
+                        path.display()
+                    ));
+                } else {
+                    create_symlink(&target, &link_path)?;
+                    restored.push(format!("Restored symlink {}", path.display()));
+                }
+            }
+            FixChange::CreatedLink {
+                path,
+                target,
+                digest,
+            } => {
+                // Whatever replaced the created link since is left alone
+                let link_path = project_root.join(&path);
+                let unchanged = if link_path.is_symlink() {
+                    target.is_some_and(|target| {
+                        fs::read_link(&link_path).is_ok_and(|current| current == target)
+                    })
+                } else if link_path.exists() {
+                    digest.is_some_and(|digest| {
+                        content_digest(&link_path).is_ok_and(|current| current == digest)
+                    })
+                } else {
+                    true
+                };
+                if !unchanged {
+                    failed.push(format!(
+                        "Cannot remove {}: it changed after doctor --fix created it",
+                        path.display()
+                    ));
+                    continue;
+                }
+
+                if link_path.is_symlink() {
+                    remove_symlink(&link_path)?;
+                } else if link_path.is_dir() {
+                    fs::remove_dir_all(&link_path)?;
+                } else if link_path.exists() {
+                    fs::remove_file(&link_path)?;
+                }
+                restored.push(format!("Removed created link {}", path.display()));
+            }
+            FixChange::RefreshedCopy { path } => failed.push(format!(
+                "Cannot restore the previous content of {}: it was not backed up",
+                path.display()
+            )),
+        }
+    }
+
+    if config_modified {
+        config.save(project_root)?;
+    }
+    history.save(&path)?;
+
+    Ok((restored, failed))
+}
+
+/// Whether the stored GitHub `url` is a file link that can be downloaded,
+/// rather than e.g. a repository root. URLs whose `${VAR}` references cannot
+/// be expanded here are given the benefit of the doubt.
+fn is_downloadable_github_url(url: &str, github_hosts: &[String]) -> bool {
+    match expand_env_vars(url) {
+        Ok(expanded) => GitHubFileUrl::parse(&expanded, github_hosts).is_ok(),
+        Err(_) => true,
+    }
+}
+
+/// Runs every diagnostic check without printing and counts the issues found
+/// per type, in the order the types are first reported.
+pub fn issue_counts(
+    config: &AgentsConfig,
+    project_root: &Path,
+    link_mode: LinkMode,
+) -> Result<Vec<(IssueType, usize)>> {
+    let mut counts: Vec<(IssueType, usize)> = Vec::new();
+    for issue in collect_issues(config, project_root, &[], link_mode)? {
+        match counts.iter_mut().find(|(t, _)| *t == issue.issue_type) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((issue.issue_type, 1)),
+        }
+    }
+    Ok(counts)
+}
+
+/// Whether Claude Code recognizes `name` as an agent file.
+fn has_agent_extension(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
+
+/// Runs the diagnostic checks, restricted to the issue types in `only`
+/// (all checks run when `only` is empty).
+fn collect_issues(
+    config: &AgentsConfig,
+    project_root: &Path,
+    only: &[IssueType],
+    link_mode: LinkMode,
+) -> Result<Vec<Issue>> {
+    let wants = |issue_type: IssueType| only.is_empty() || only.contains(&issue_type);
 
     let mut issues = Vec::new();
     let mut seen_names = HashSet::new();
+    let mut seen_sources: HashMap<PathBuf, &str> = HashMap::new();
+    let github_hosts = github::enterprise_hosts(&config.github_hosts);
+
+    // The managed dir must be a real directory, or downloads and imports fail
+    let managed_dir = project_root.join(config.managed_dir());
+    if wants(IssueType::ManagedDirNotDirectory) {
+        if let Some(problem) = managed_dir_problem(&managed_dir) {
+            issues.push(Issue {
+                agent_name: config.managed_dir().display().to_string(),
+                issue_type: IssueType::ManagedDirNotDirectory,
+                description: format!(
+                    "Managed directory {}; move it aside so ccagents can create a real directory",
+                    problem
+                ),
+                fixable: false,
+            });
+        }
+    }
 
     // Check each agent in config
     for agent in &config.agents {
-        let local_path = agent.get_local_path(&project_root);
-        let link_path = agent.get_link_path(&project_root);
+        let local_path = agent.get_local_path(project_root);
+        let link_path = agent.get_link_path(project_root);
 
         // Check for missing source
         if !local_path.exists() {
-            let fixable = matches!(&agent.source, AgentSource::GitHub(_));
-            issues.push(Issue {
-                agent_name: agent.name.clone(),
-                issue_type: IssueType::MissingSource,
-                description: format!("Source file/directory missing: {:?}", local_path),
-                fixable,
-            });
-        } else if agent.enabled {
-            // Check symlink status for enabled agents
-            if !link_path.exists() && !link_path.is_symlink() {
+            if wants(IssueType::MissingSource) {
+                let fixable = matches!(&agent.source, AgentSource::GitHub(_));
                 issues.push(Issue {
                     agent_name: agent.name.clone(),
-                    issue_type: IssueType::MissingSymlink,
-                    description: "Agent is enabled but symlink is missing".to_string(),
-                    fixable: true,
+                    issue_type: IssueType::MissingSource,
+                    description: format!("Source file/directory missing: {:?}", local_path),
+                    fixable,
                 });
-            } else if !is_symlink_valid(&link_path) {
+            }
+        } else if agent.enabled && agent.condition_met().unwrap_or(true) {
+            // Check symlink status for enabled (and active) agents
+            let status = symlink_status(&link_path);
+            if status == SymlinkStatus::NotExist {
+                if wants(IssueType::MissingSymlink) {
+                    issues.push(Issue {
+                        agent_name: agent.name.clone(),
+                        issue_type: IssueType::MissingSymlink,
+                        description: match link_mode {
+                            LinkMode::Symlink => "Agent is enabled but symlink is missing",
+                            LinkMode::Copy => "Agent is enabled but its copy is missing",
+                        }
+                        .to_string(),
+                        fixable: true,
+                    });
+                }
+            } else if link_mode == LinkMode::Copy && status == SymlinkStatus::NotSymlink {
+                // In copy mode the file is expected; it must match the source
+                if wants(IssueType::DriftedCopy) && !copy_matches_source(&local_path, &link_path) {
+                    issues.push(Issue {
+                        agent_name: agent.name.clone(),
+                        issue_type: IssueType::DriftedCopy,
+                        description: "Copy in .claude/agents differs from its source".to_string(),
+                        fixable: true,
+                    });
+                }
+            } else if wants(IssueType::BrokenSymlink) {
+                // A regular file in the way is never replaced automatically
+                let problem = match status {
+                    SymlinkStatus::NotSymlink => Some((
+                        "A regular file or directory occupies the link path".to_string(),
+                        false,
+                    )),
+                    SymlinkStatus::Dangling => {
+                        Some(("Symlink exists but is broken".to_string(), true))
+                    }
+                    _ => match resolve_symlink_target(&link_path) {
+                        Ok(target) if local_path.canonicalize().ok() != Some(target.clone()) => {
+                            Some((
+                                format!(
+                                    "Symlink points to {:?} instead of {:?}",
+                                    target, local_path
+                                ),
+                                true,
+                            ))
+                        }
+                        _ => None,
+                    },
+                };
+
+                if let Some((description, fixable)) = problem {
+                    issues.push(Issue {
+                        agent_name: agent.name.clone(),
+                        issue_type: IssueType::BrokenSymlink,
+                        description,
+                        fixable,
+                    });
+                }
+            }
+        }
+
+        // Claude Code only picks up `.md` files among the linked agents;
+        // directory sources are exempt
+        if wants(IssueType::MissingExtension)
+            && agent.enabled
+            && !has_agent_extension(agent.link_file_name())
+            && !agent.get_local_path(project_root).is_dir()
+        {
+            issues.push(Issue {
+                agent_name: agent.name.clone(),
+                issue_type: IssueType::MissingExtension,
+                description: format!(
+                    "Link name '{}' has no .md extension, so Claude Code ignores it. Rename the agent or set \"link_name\": \"{}.md\" in .agents.json",
+                    agent.link_file_name(),
+                    agent.link_file_name()
+                ),
+                fixable: false,
+            });
+        }
+
+        // `update` found the GitHub file deleted and kept the local copy
+        if agent.upstream_missing && wants(IssueType::UpstreamMissing) {
+            issues.push(Issue {
+                agent_name: agent.name.clone(),
+                issue_type: IssueType::UpstreamMissing,
+                description: "GitHub file no longer exists upstream; the local copy is kept"
+                    .to_string(),
+                fixable: false,
+            });
+        }
+
+        // Hand-edited or old configs may hold URLs `add` would reject today
+        if let AgentSource::GitHub(url) = &agent.source {
+            if wants(IssueType::InvalidGithubUrl) && !is_downloadable_github_url(url, &github_hosts)
+            {
                 issues.push(Issue {
                     agent_name: agent.name.clone(),
-                    issue_type: IssueType::BrokenSymlink,
-                    description: "Symlink exists but is broken".to_string(),
-                    fixable: true,
+                    issue_type: IssueType::InvalidGithubUrl,
+                    description: format!(
+                        "{} is not a GitHub file URL. Replace it in .agents.json with a file link like https://github.com/user/repo/blob/main/agent.md",
+                        url
+                    ),
+                    fixable: false,
                 });
             }
         }
 
         // Check for duplicate agents
-        if !seen_names.insert(agent.name.clone()) {
+        if !seen_names.insert(agent.name.clone()) && wants(IssueType::DuplicateAgent) {
             issues.push(Issue {
                 agent_name: agent.name.clone(),
                 issue_type: IssueType::DuplicateAgent,
@@ -76,24 +936,75 @@ pub fn execute(fix: bool) -> Result<()> {
                 fixable: true,
             });
         }
+
+        // Check for different agents backed by the same file
+        if wants(IssueType::DuplicateSource) {
+            if let Ok(canonical) = local_path.canonicalize() {
+                match seen_sources.get(&canonical) {
+                    Some(first) if *first != agent.name => issues.push(Issue {
+                        agent_name: agent.name.clone(),
+                        issue_type: IssueType::DuplicateSource,
+                        description: format!(
+                            "Source {:?} is also used by agent '{}'",
+                            canonical, first
+                        ),
+                        fixable: false,
+                    }),
+                    Some(_) => {}
+                    None => {
+                        seen_sources.insert(canonical, &agent.name);
+                    }
+                }
+            }
+        }
     }
 
-    // Check for orphaned symlinks in .claude/agents
-    let claude_agents_dir = project_root.join(".claude").join("agents");
-    if claude_agents_dir.exists() {
-        for entry in fs::read_dir(&claude_agents_dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    // Check that configured agents are either all symlinked or all copied,
+    // e.g. after switching to or from `sync --copy` midway
+    if wants(IssueType::MixedLinkModes) {
+        let (mut symlinked, mut copied) = (0, 0);
+        for agent in &config.agents {
+            match symlink_status(&agent.get_link_path(project_root)) {
+                SymlinkStatus::Valid | SymlinkStatus::Dangling => symlinked += 1,
+                SymlinkStatus::NotSymlink => copied += 1,
+                SymlinkStatus::NotExist => {}
+            }
+        }
+
+        if symlinked > 0 && copied > 0 {
+            issues.push(Issue {
+                agent_name: ".claude/agents".to_string(),
+                issue_type: IssueType::MixedLinkModes,
+                description: format!(
+                    "{} agent{} symlinked and {} copied; run 'ccagents sync' or 'ccagents sync --copy' to use one mode",
+                    symlinked,
+                    if symlinked == 1 { " is" } else { "s are" },
+                    copied
+                ),
+                fixable: false,
+            });
+        }
+    }
 
-            let name = path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("")
-                .to_string();
+    // Check for orphaned symlinks and unmanaged files in .claude/agents
+    let claude_agents_dir = claude_settings::agents_dir(project_root);
+    if claude_agents_dir.exists()
+        && (wants(IssueType::OrphanedSymlink) || wants(IssueType::UnmanagedFile))
+    {
+        let ignore = IgnoreRules::for_config(project_root, config)?;
+        let enabled_names: HashSet<String> = config
+            .enabled_agents()
+            .into_iter()
+            .map(|a| a.link_key())
+            .collect();
 
+        let configured = config.link_names();
+        let scan = scan_link_dir(&claude_agents_dir, &configured)?;
+        print_skipped_names(&scan.skipped);
+        for (name, path) in scan.entries {
             if path.is_symlink() {
                 // Check if this symlink has a corresponding agent in config
-                if !config.agents.iter().any(|a| a.name == name && a.enabled) {
+                if wants(IssueType::OrphanedSymlink) && !enabled_names.contains(name.as_str()) {
                     issues.push(Issue {
                         agent_name: name,
                         issue_type: IssueType::OrphanedSymlink,
@@ -102,7 +1013,13 @@ pub fn execute(fix: bool) -> Result<()> {
                         fixable: true,
                     });
                 }
-            } else if path.is_file() {
+            } else if path.is_file()
+                && wants(IssueType::UnmanagedFile)
+                && ignore.is_agent_entry(&path)
+                // Files at an agent's link path are copies, checked (and in
+                // symlink mode reported) with the agent above
+                && !configured.contains(name.as_str())
+            {
                 // Regular file in .claude/agents - should be managed via symlinks
                 issues.push(Issue {
                     agent_name: name,
@@ -115,152 +1032,623 @@ pub fn execute(fix: bool) -> Result<()> {
         }
     }
 
-    // Report findings
-    if issues.is_empty() {
-        println!("{} All checks passed! No issues found.", "✓".green().bold());
-        return Ok(());
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use crate::config::ensure_ccagents_dir;
+    use crate::linker::{create_symlink, LinkStyle};
+    use crate::output::render;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn project_with_issues() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+
+        // An agent whose source is missing
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "missing.md".to_string(),
+                AgentSource::Local(PathBuf::from(".ccagents/missing.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        // An unmanaged file in .claude/agents
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        fs::write(claude_agents_dir.join("unmanaged.md"), "# Unmanaged").unwrap();
+
+        temp_dir
     }
 
-    println!(
-        "{} Found {} issue{}:",
-        "⚠".yellow().bold(),
-        issues.len(),
-        if issues.len() == 1 { "" } else { "s" }
-    );
-    println!();
-
-    for issue in &issues {
-        let icon = match issue.issue_type {
-            IssueType::MissingSource => "✗".red(),
-            IssueType::BrokenSymlink | IssueType::MissingSymlink => "⚠".yellow(),
-            IssueType::DuplicateAgent => "⚠".yellow(),
-            IssueType::OrphanedSymlink => "○".yellow(),
-            IssueType::UnmanagedFile => "◆".blue(),
+    #[test]
+    fn test_collect_issues_runs_all_checks_by_default() {
+        let temp_dir = project_with_issues();
+        let config = AgentsConfig::load(temp_dir.path()).unwrap();
+
+        let issues = collect_issues(&config, temp_dir.path(), &[], LinkMode::Symlink).unwrap();
+        assert_eq!(issues.len(), 2);
+    }
+
+    #[test]
+    fn test_collect_issues_only_missing_source() {
+        let temp_dir = project_with_issues();
+        let config = AgentsConfig::load(temp_dir.path()).unwrap();
+
+        let issues = collect_issues(
+            &config,
+            temp_dir.path(),
+            &[IssueType::MissingSource],
+            LinkMode::Symlink,
+        )
+        .unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, IssueType::MissingSource);
+        assert_eq!(issues[0].agent_name, "missing.md");
+    }
+
+    #[test]
+    fn test_upstream_missing_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+        fs::write(project_root.join(".ccagents/agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        let mut agent = Agent::new(
+            "agent.md".to_string(),
+            AgentSource::GitHub("https://github.com/user/repo/blob/main/agent.md".to_string()),
+        );
+        agent.enabled = false;
+        agent.upstream_missing = true;
+        config.add_agent(agent).unwrap();
+
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, IssueType::UpstreamMissing);
+        assert!(!issues[0].fixable);
+    }
+
+    #[test]
+    fn test_repo_root_github_url_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+        fs::write(project_root.join(".ccagents/repo"), "").unwrap();
+
+        // Persisted before `add` rejected repository URLs
+        let config: AgentsConfig = serde_json::from_str(
+            r#"{"agents": [{"name": "repo", "source": {"type": "GitHub", "value": "https://github.com/user/repo"}, "enabled": false}]}"#,
+        )
+        .unwrap();
+
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, IssueType::InvalidGithubUrl);
+        assert!(issues[0].description.contains("/blob/main/agent.md"));
+        assert!(!issues[0].fixable);
+    }
+
+    #[test]
+    fn test_hidden_files_are_not_unmanaged() {
+        let temp_dir = TempDir::new().unwrap();
+        let claude_agents_dir = ensure_claude_agents_dir(temp_dir.path()).unwrap();
+        fs::write(claude_agents_dir.join(".DS_Store"), "").unwrap();
+        fs::write(claude_agents_dir.join("agent.md~"), "").unwrap();
+        fs::write(claude_agents_dir.join(".gitkeep"), "").unwrap();
+        // Re-including a placeholder does not make it an agent
+        fs::write(temp_dir.path().join(".ccagentsignore"), "!.gitkeep\n").unwrap();
+
+        let config = AgentsConfig::default();
+        let issues = collect_issues(&config, temp_dir.path(), &[], LinkMode::Symlink).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_config_exclude_is_not_unmanaged() {
+        let temp_dir = TempDir::new().unwrap();
+        let claude_agents_dir = ensure_claude_agents_dir(temp_dir.path()).unwrap();
+        fs::write(claude_agents_dir.join("README.md"), "# Notes").unwrap();
+        fs::write(claude_agents_dir.join("stray.md"), "# Stray").unwrap();
+
+        let config = AgentsConfig {
+            exclude: vec!["README.md".to_string()],
+            ..AgentsConfig::default()
         };
+        let issues = collect_issues(&config, temp_dir.path(), &[], LinkMode::Symlink).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, IssueType::UnmanagedFile);
+        assert_eq!(issues[0].agent_name, "stray.md");
+    }
 
-        println!(
-            "  {} {} - {}",
-            icon,
-            issue.agent_name.bold(),
-            issue.description
+    #[test]
+    fn test_fix_then_undo_restores_orphaned_symlink() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+        fs::write(project_root.join(".ccagents/old.md"), "# Old").unwrap();
+        AgentsConfig::default().save(project_root).unwrap();
+
+        let link_path = project_root.join(".claude/agents/old.md");
+        create_symlink(&project_root.join(".ccagents/old.md"), &link_path).unwrap();
+
+        run(
+            project_root,
+            true,
+            false,
+            &[],
+            Some(LinkMode::Symlink),
+            OutputFormat::Table,
+        )
+        .unwrap();
+        assert!(!link_path.is_symlink());
+        assert!(project_root.join(".ccagents").join(HISTORY_FILE).exists());
+
+        let (restored, failed) = undo_last(project_root).unwrap();
+        assert_eq!(restored, vec!["Restored symlink .claude/agents/old.md"]);
+        assert!(failed.is_empty());
+        assert_eq!(
+            fs::read_link(&link_path).unwrap(),
+            project_root.join(".ccagents/old.md")
         );
 
-        if issue.fixable {
-            println!("    {} This issue can be fixed automatically", "→".green());
-        } else {
-            println!("    {} Manual intervention required", "→".red());
+        // The batch is gone from the history
+        let err = undo_last(project_root).unwrap_err();
+        assert!(err.to_string().contains("Nothing to undo"));
+    }
+
+    #[test]
+    fn test_undo_keeps_link_changed_after_fix() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        ensure_ccagents_dir(project_root).unwrap();
+        fs::write(project_root.join(".ccagents/agent.md"), "# Agent").unwrap();
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+        let link_path = project_root.join(".claude/agents/agent.md");
+
+        // An untouched link is removed
+        repair_links(project_root).unwrap();
+        assert!(link_path.is_symlink());
+        let (restored, failed) = undo_last(project_root).unwrap();
+        assert_eq!(
+            restored,
+            vec!["Removed created link .claude/agents/agent.md"]
+        );
+        assert!(failed.is_empty());
+        assert!(!link_path.is_symlink());
+
+        // A file put in its place afterwards is not
+        repair_links(project_root).unwrap();
+        remove_symlink(&link_path).unwrap();
+        fs::write(&link_path, "# Mine").unwrap();
+        let (restored, failed) = undo_last(project_root).unwrap();
+        assert!(restored.is_empty());
+        assert!(failed[0].contains("Cannot remove .claude/agents/agent.md"));
+        assert_eq!(fs::read_to_string(&link_path).unwrap(), "# Mine");
+    }
+
+    #[test]
+    fn test_repair_fixes_links_but_keeps_missing_source_agent() {
+        let temp_dir = project_with_issues();
+        let project_root = temp_dir.path();
+        ensure_ccagents_dir(project_root).unwrap();
+        fs::write(project_root.join(".ccagents/present.md"), "# Present").unwrap();
+
+        let mut config = AgentsConfig::load(project_root).unwrap();
+        config
+            .add_agent(Agent::new(
+                "present.md".to_string(),
+                AgentSource::Local(PathBuf::from(".ccagents/present.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let (fixed, failed, remaining) = repair_links(project_root).unwrap();
+        assert_eq!((fixed, failed), (1, 0));
+        // The missing source and the unmanaged file are left for doctor
+        assert_eq!(remaining, 2);
+
+        assert_eq!(
+            fs::read_to_string(project_root.join(".claude/agents/present.md")).unwrap(),
+            "# Present"
+        );
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(config.get_agent("missing.md").is_some());
+        assert!(project_root.join(".claude/agents/unmanaged.md").is_file());
+    }
+
+    #[test]
+    fn test_managed_dir_not_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::write(project_root.join(".ccagents"), "").unwrap();
+
+        let issues = collect_issues(
+            &AgentsConfig::default(),
+            project_root,
+            &[],
+            LinkMode::Symlink,
+        )
+        .unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, IssueType::ManagedDirNotDirectory);
+        assert_eq!(issues[0].agent_name, ".ccagents");
+        assert!(issues[0].description.contains("is a file"));
+    }
+
+    #[test]
+    fn test_subdir_links_checked() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+        fs::write(project_root.join(".ccagents/api.md"), "# API").unwrap();
+
+        let mut config = AgentsConfig::default();
+        let mut agent = Agent::new(
+            "api.md".to_string(),
+            AgentSource::Local(PathBuf::from(".ccagents/api.md")),
+        );
+        agent.subdir = Some("backend".to_string());
+        config.add_and_link(agent, project_root, true).unwrap();
+        assert!(project_root
+            .join(".claude/agents/backend/api.md")
+            .is_symlink());
+
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap();
+        assert!(issues.is_empty());
+
+        // A stray link next to it is an orphan, keyed by its subdir path
+        let backend_dir = project_root.join(".claude/agents/backend");
+        create_symlink(
+            &project_root.join(".ccagents/api.md"),
+            &backend_dir.join("old.md"),
+        )
+        .unwrap();
+        fs::remove_file(backend_dir.join("api.md")).unwrap();
+
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap();
+        let mut found: Vec<_> = issues
+            .iter()
+            .map(|i| (i.issue_type, i.agent_name.as_str()))
+            .collect();
+        found.sort_by_key(|(_, name)| *name);
+        assert_eq!(
+            found,
+            vec![
+                (IssueType::MissingSymlink, "api.md"),
+                (IssueType::OrphanedSymlink, "backend/old.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_unmanaged_file_detected() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let nested_dir = ensure_claude_agents_dir(project_root)
+            .unwrap()
+            .join("team/backend");
+        fs::create_dir_all(&nested_dir).unwrap();
+        fs::write(nested_dir.join("deep.md"), "# Deep").unwrap();
+        // Hidden directories are not scanned
+        fs::create_dir_all(project_root.join(".claude/agents/.git")).unwrap();
+        fs::write(project_root.join(".claude/agents/.git/HEAD.md"), "").unwrap();
+
+        let issues = collect_issues(
+            &AgentsConfig::default(),
+            project_root,
+            &[IssueType::UnmanagedFile],
+            LinkMode::Symlink,
+        )
+        .unwrap();
+        let names: Vec<_> = issues.iter().map(|i| i.agent_name.as_str()).collect();
+        assert_eq!(names, vec!["team/backend/deep.md"]);
+    }
+
+    #[test]
+    fn test_duplicate_source_detected() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+        fs::write(project_root.join(".ccagents/shared.md"), "# Shared").unwrap();
+
+        let mut config = AgentsConfig::default();
+        for (name, path) in [
+            ("first.md", ".ccagents/shared.md"),
+            ("second.md", "./.ccagents/../.ccagents/shared.md"),
+        ] {
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(PathBuf::from(path)),
+                ))
+                .unwrap();
         }
+        config.agents.iter_mut().for_each(|a| a.enabled = false);
+
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].issue_type, IssueType::DuplicateSource);
+        assert_eq!(issues[0].agent_name, "second.md");
+        assert!(issues[0].description.contains("first.md"));
+        assert!(!issues[0].fixable);
     }
 
-    // Apply fixes if requested
-    if fix {
-        println!();
-        println!("{}", "Applying fixes...".cyan().bold());
+    #[test]
+    fn test_symlink_target_checks_resolve_relative_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = project_root.join(".ccagents");
+        fs::create_dir_all(&ccagents_dir).unwrap();
+        fs::write(ccagents_dir.join("good.md"), "# Good").unwrap();
+        fs::write(ccagents_dir.join("wrong.md"), "# Wrong").unwrap();
+        fs::write(ccagents_dir.join("other.md"), "# Other").unwrap();
 
-        let mut fixed_count = 0;
-        let mut config_modified = false;
+        let mut config = AgentsConfig::default();
+        for name in ["good.md", "wrong.md"] {
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(PathBuf::from(".ccagents").join(name)),
+                ))
+                .unwrap();
+        }
 
-        for issue in &issues {
-            if !issue.fixable {
-                continue;
-            }
+        // A correct relative link is fine, a link to another file is not
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        std::os::unix::fs::symlink("../../.ccagents/good.md", claude_agents_dir.join("good.md"))
+            .unwrap();
+        std::os::unix::fs::symlink(
+            "../../.ccagents/other.md",
+            claude_agents_dir.join("wrong.md"),
+        )
+        .unwrap();
 
-            match issue.issue_type {
-                IssueType::MissingSource => {
-                    // For GitHub sources, we could re-download, but for now we'll remove
-                    config.agents.retain(|a| a.name != issue.agent_name);
-                    config_modified = true;
-                    println!(
-                        "  {} Removed agent with missing source: {}",
-                        "✓".green(),
-                        issue.agent_name
-                    );
-                    fixed_count += 1;
-                }
-                IssueType::BrokenSymlink => {
-                    // Remove and recreate the symlink
-                    if let Some(agent) = config.agents.iter().find(|a| a.name == issue.agent_name) {
-                        let link_path = agent.get_link_path(&project_root);
-                        let local_path = agent.get_local_path(&project_root);
-
-                        remove_symlink(&link_path).ok();
-                        if local_path.exists() {
-                            create_symlink(&local_path, &link_path)?;
-                            println!(
-                                "  {} Fixed broken symlink: {}",
-                                "✓".green(),
-                                issue.agent_name
-                            );
-                            fixed_count += 1;
-                        }
-                    }
-                }
-                IssueType::MissingSymlink => {
-                    // Create the missing symlink
-                    if let Some(agent) = config.agents.iter().find(|a| a.name == issue.agent_name) {
-                        let link_path = agent.get_link_path(&project_root);
-                        let local_path = agent.get_local_path(&project_root);
-
-                        ensure_claude_agents_dir(&project_root)?;
-                        create_symlink(&local_path, &link_path)?;
-                        println!(
-                            "  {} Created missing symlink: {}",
-                            "✓".green(),
-                            issue.agent_name
-                        );
-                        fixed_count += 1;
-                    }
-                }
-                IssueType::DuplicateAgent => {
-                    // Remove duplicates, keeping only the first occurrence
-                    let mut seen = HashSet::new();
-                    config.agents.retain(|a| seen.insert(a.name.clone()));
-                    config_modified = true;
-                    println!(
-                        "  {} Removed duplicate agent: {}",
-                        "✓".green(),
-                        issue.agent_name
-                    );
-                    fixed_count += 1;
-                }
-                IssueType::OrphanedSymlink => {
-                    // Remove the orphaned symlink
-                    let link_path = claude_agents_dir.join(&issue.agent_name);
-                    remove_symlink(&link_path).ok();
-                    println!(
-                        "  {} Removed orphaned symlink: {}",
-                        "✓".green(),
-                        issue.agent_name
-                    );
-                    fixed_count += 1;
-                }
-                IssueType::UnmanagedFile => {
-                    // Import the unmanaged file
-                    println!("  {} Unmanaged file '{}' detected - run 'ccagents import' to convert to managed agent", "ℹ".blue(), issue.agent_name);
-                    // We don't automatically fix this - require explicit import command
-                }
-            }
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].agent_name, "wrong.md");
+        assert_eq!(issues[0].issue_type, IssueType::BrokenSymlink);
+        assert!(issues[0].description.contains("other.md"));
+    }
+
+    #[test]
+    fn test_extensionless_agent_name_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("README"), "# Readme").unwrap();
+        fs::write(ccagents_dir.join("backend"), "# Backend").unwrap();
+        fs::create_dir(ccagents_dir.join("tools")).unwrap();
+
+        let mut config = AgentsConfig::default();
+        for name in ["README", "backend", "tools"] {
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(PathBuf::from(".ccagents").join(name)),
+                ))
+                .unwrap();
         }
+        config.agents[1].link_name = Some("backend.md".to_string());
+
+        let issues = collect_issues(
+            &config,
+            project_root,
+            &[IssueType::MissingExtension],
+            LinkMode::Symlink,
+        )
+        .unwrap();
+        let names: Vec<_> = issues.iter().map(|i| i.agent_name.as_str()).collect();
+        assert_eq!(names, vec!["README"]);
+        assert!(issues[0]
+            .description
+            .contains("\"link_name\": \"README.md\""));
+    }
+
+    #[test]
+    fn test_explain_adds_explanation_per_issue_type() {
+        let temp_dir = project_with_issues();
+        let config = AgentsConfig::load(temp_dir.path()).unwrap();
+        let issues = || collect_issues(&config, temp_dir.path(), &[], LinkMode::Symlink).unwrap();
 
-        if config_modified {
-            config.save(&project_root)?;
+        let report = DoctorReport::new(issues(), true);
+        let table = render(&report, OutputFormat::Table).unwrap().join("\n");
+        for issue_type in [IssueType::MissingSource, IssueType::UnmanagedFile] {
+            assert!(table.contains(issue_type.explanation()));
         }
+        assert!(!table.contains(IssueType::BrokenSymlink.explanation()));
 
-        println!();
-        println!(
-            "{} Fixed {} of {} issue{}",
-            "✓".green().bold(),
-            fixed_count,
-            issues.len(),
-            if issues.len() == 1 { "" } else { "s" }
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&report, OutputFormat::Json).unwrap()[0]).unwrap();
+        assert_eq!(
+            json["explanations"]["missing-source"],
+            IssueType::MissingSource.explanation()
         );
-    } else {
-        println!();
-        println!(
-            "Run {} to automatically fix these issues",
-            "ccagents doctor --fix".cyan()
+
+        let plain = render(&report, OutputFormat::Plain).unwrap();
+        assert!(plain.iter().all(|line| line.split('\t').count() == 5));
+
+        let report = DoctorReport::new(issues(), false);
+        let table = render(&report, OutputFormat::Table).unwrap().join("\n");
+        assert!(!table.contains(IssueType::MissingSource.explanation()));
+        let json = render(&report, OutputFormat::Json).unwrap()[0].clone();
+        assert!(!json.contains("explanations"));
+    }
+
+    #[test]
+    fn test_porcelain_lines() {
+        let temp_dir = project_with_issues();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("orphan.md"), "# Orphan").unwrap();
+        create_symlink(
+            &ccagents_dir.join("orphan.md"),
+            &project_root.join(".claude/agents/orphan.md"),
+        )
+        .unwrap();
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        let report = DoctorReport::new(
+            collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap(),
+            false,
+        );
+        assert_eq!(
+            render(&report, OutputFormat::Plain).unwrap(),
+            vec![
+                format!(
+                    "missing-source\tmissing.md\tmanual\tSource file/directory missing: {:?}",
+                    project_root.join(".ccagents/missing.md")
+                ),
+                "orphaned-symlink\torphan.md\tfixable\tSymlink exists without corresponding agent in config".to_string(),
+                "unmanaged-file\tunmanaged.md\tfixable\tRegular file in .claude/agents/ should be managed via ccagents".to_string(),
+            ]
         );
     }
 
-    Ok(())
+    #[test]
+    fn test_issue_type_cli_names() {
+        let parsed = IssueType::from_str("broken-symlink", false).unwrap();
+        assert_eq!(parsed, IssueType::BrokenSymlink);
+        assert!(IssueType::from_str("not-a-check", false).is_err());
+    }
+
+    #[test]
+    fn test_copy_mode_validates_copies_by_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = project_root.join(".ccagents");
+        fs::create_dir_all(&ccagents_dir).unwrap();
+
+        let mut config = AgentsConfig::default();
+        for name in ["current.md", "drifted.md", "missing.md"] {
+            fs::write(ccagents_dir.join(name), "# Agent").unwrap();
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(ccagents_dir.join(name)),
+                ))
+                .unwrap();
+        }
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        fs::write(claude_agents_dir.join("current.md"), "# Agent").unwrap();
+        fs::write(claude_agents_dir.join("drifted.md"), "# Edited locally").unwrap();
+
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Copy).unwrap();
+        let found: Vec<(&str, IssueType)> = issues
+            .iter()
+            .map(|i| (i.agent_name.as_str(), i.issue_type))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("drifted.md", IssueType::DriftedCopy),
+                ("missing.md", IssueType::MissingSymlink),
+            ]
+        );
+
+        // The same copies occupy the link paths when symlinks are expected,
+        // reported with their agents rather than as unmanaged files
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap();
+        assert!(issues
+            .iter()
+            .any(|i| i.issue_type == IssueType::BrokenSymlink && i.agent_name == "current.md"));
+        assert!(!issues
+            .iter()
+            .any(|i| i.issue_type == IssueType::UnmanagedFile));
+    }
+
+    #[test]
+    fn test_copy_in_symlink_mode_reported_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = project_root.join(".ccagents");
+        fs::create_dir_all(&ccagents_dir).unwrap();
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("agent.md")),
+            ))
+            .unwrap();
+        // Left behind by `sync --copy`
+        link_agent(
+            &ccagents_dir.join("agent.md"),
+            &claude_agents_dir.join("agent.md"),
+            LinkMode::Copy,
+            LinkStyle::Relative,
+        )
+        .unwrap();
+
+        let issues = collect_issues(&config, project_root, &[], LinkMode::Symlink).unwrap();
+        let types: Vec<_> = issues.iter().map(|i| i.issue_type).collect();
+        assert_eq!(types, vec![IssueType::BrokenSymlink]);
+        assert!(!issues[0].fixable);
+    }
+
+    #[test]
+    fn test_mixed_link_modes_detected() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = project_root.join(".ccagents");
+        fs::create_dir_all(&ccagents_dir).unwrap();
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+
+        let mut config = AgentsConfig::default();
+        for name in ["linked.md", "copied.md"] {
+            fs::write(ccagents_dir.join(name), "# Agent").unwrap();
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(ccagents_dir.join(name)),
+                ))
+                .unwrap();
+        }
+        link_agent(
+            &ccagents_dir.join("linked.md"),
+            &claude_agents_dir.join("linked.md"),
+            LinkMode::Symlink,
+            LinkStyle::Relative,
+        )
+        .unwrap();
+        link_agent(
+            &ccagents_dir.join("copied.md"),
+            &claude_agents_dir.join("copied.md"),
+            LinkMode::Copy,
+            LinkStyle::Relative,
+        )
+        .unwrap();
+
+        let only = [IssueType::MixedLinkModes];
+        let issues = collect_issues(&config, project_root, &only, LinkMode::Copy).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0]
+            .description
+            .contains("1 agent is symlinked and 1 copied"));
+
+        // Converging to one mode clears the issue
+        link_agent(
+            &ccagents_dir.join("linked.md"),
+            &claude_agents_dir.join("linked.md"),
+            LinkMode::Copy,
+            LinkStyle::Relative,
+        )
+        .unwrap();
+        let issues = collect_issues(&config, project_root, &only, LinkMode::Copy).unwrap();
+        assert!(issues.is_empty());
+    }
 }
@@ -0,0 +1,121 @@
+use crate::agent::AgentSource;
+use crate::config::{get_project_root, AgentsConfig};
+use anyhow::{Context, Result};
+use colored::*;
+use std::path::Path;
+use std::process::Command;
+
+/// Editor used when neither `$EDITOR` nor `$VISUAL` is set.
+const DEFAULT_EDITOR: &str = "vi";
+
+pub fn execute(name: &str) -> Result<()> {
+    let project_root = get_project_root()?;
+    run(&project_root, name, &editor_command())
+}
+
+/// Returns `$EDITOR`, else `$VISUAL`, else [`DEFAULT_EDITOR`].
+fn editor_command() -> String {
+    ["EDITOR", "VISUAL"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Opens the backing file of `name` in `editor`, which may include
+/// arguments (e.g. `code --wait`), and waits for it to exit.
+fn run(project_root: &Path, name: &str, editor: &str) -> Result<()> {
+    let config = AgentsConfig::load_required(project_root)?;
+    let agent = config.require_agent(name)?;
+    let local_path = agent.get_local_path(project_root);
+
+    if !local_path.exists() {
+        return Err(anyhow::anyhow!(
+            "Agent source does not exist: {:?}. Run 'ccagents sync' to download missing agents.",
+            local_path
+        ));
+    }
+
+    if let AgentSource::GitHub(_) = &agent.source {
+        println!(
+            "{} '{}' is downloaded from GitHub; 'ccagents update' will overwrite local changes",
+            "⚠".yellow().bold(),
+            agent.name
+        );
+    }
+
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No editor configured"))?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&local_path)
+        .status()
+        .with_context(|| format!("Failed to launch editor '{}'", editor))?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "Editor '{}' exited with {}",
+            editor,
+            status
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_edit_launches_editor_on_local_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+        fs::write(project_root.join(".ccagents/agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+            ))
+            .unwrap();
+        config
+            .add_agent(Agent::new(
+                "remote.md".to_string(),
+                AgentSource::GitHub("https://github.com/org/repo/blob/main/remote.md".to_string()),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        // A stub editor that records its arguments
+        let editor = project_root.join("editor.sh");
+        let log = project_root.join("editor.log");
+        fs::write(
+            &editor,
+            format!("#!/bin/sh\necho \"$@\" > {}\n", log.display()),
+        )
+        .unwrap();
+
+        let command = format!("sh {} --wait", editor.display());
+        run(project_root, "agent.md", &command).unwrap();
+        assert_eq!(
+            fs::read_to_string(&log).unwrap().trim(),
+            format!(
+                "--wait {}",
+                project_root.join(".ccagents/agent.md").display()
+            )
+        );
+
+        // The GitHub agent has not been downloaded yet
+        let err = run(project_root, "remote.md", &command).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+    }
+}
@@ -1,44 +1,264 @@
+use crate::agent::AgentSource;
+use crate::commands::{print_download_warnings, read_names, NameSummary};
 use crate::config::{ensure_claude_agents_dir, get_project_root, AgentsConfig};
-use crate::linker::create_symlink;
+use crate::downloader::{download_agent, DownloadOptions};
+use crate::error::CcagentsError;
+use crate::linker::{link_agent, LinkMode};
 use anyhow::Result;
 use colored::*;
+use std::io;
+use std::path::Path;
 
-pub fn execute(name: &str) -> Result<()> {
+pub async fn execute(names: &[String], download: bool, glob: bool, stdin: bool) -> Result<()> {
     let project_root = get_project_root()?;
-    let mut config = AgentsConfig::load(&project_root)?;
 
-    // Find the agent
+    if stdin {
+        let names = read_names(io::stdin().lock())?;
+        return run_names(&project_root, &names, download)
+            .await?
+            .finish("Enabled");
+    }
+
+    match names {
+        [] => Err(anyhow::anyhow!("Provide an agent name or --stdin")),
+        _ if glob => {
+            for pattern in names {
+                run_matching(&project_root, pattern, download).await?;
+            }
+            Ok(())
+        }
+        [name] => run(&project_root, name, download).await,
+        _ => run_names(&project_root, names, download)
+            .await?
+            .finish("Enabled"),
+    }
+}
+
+/// Enables each of `names`, continuing past names that fail, and saves the
+/// configuration once at the end.
+async fn run_names(project_root: &Path, names: &[String], download: bool) -> Result<NameSummary> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    let mut summary = NameSummary::default();
+    for name in names {
+        let result = enable_agent(&mut config, project_root, name, download).await;
+        summary.record(name, result);
+    }
+    config.save(project_root)?;
+    Ok(summary)
+}
+
+/// Enables every agent whose name matches the glob `pattern`. Returns the
+/// number of matched agents.
+async fn run_matching(project_root: &Path, pattern: &str, download: bool) -> Result<usize> {
+    let names = AgentsConfig::load_required(project_root)?.match_agents(pattern)?;
+    println!(
+        "{} {} agent{} match '{}'",
+        "ℹ".blue(),
+        names.len(),
+        if names.len() == 1 { "" } else { "s" },
+        pattern
+    );
+
+    for name in &names {
+        run(project_root, name, download).await?;
+    }
+
+    Ok(names.len())
+}
+
+/// Enables `name`. With `download`, a missing GitHub source is fetched first.
+async fn run(project_root: &Path, name: &str, download: bool) -> Result<()> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    enable_agent(&mut config, project_root, name, download).await?;
+    config.save(project_root)
+}
+
+/// Links `name` and marks it enabled in `config`, which the caller saves.
+/// On failure the agent is left disabled.
+async fn enable_agent(
+    config: &mut AgentsConfig,
+    project_root: &Path,
+    name: &str,
+    download: bool,
+) -> Result<()> {
+    let options = DownloadOptions {
+        progress: true,
+        ..DownloadOptions::from_config(config)
+    };
+
+    // Find the agent by name or alias, falling back to a case-insensitive match
+    let agent = config.require_agent(name)?;
+    let resolved = agent.name.clone();
+    if resolved != name && !agent.aliases.iter().any(|alias| alias == name) {
+        println!(
+            "{} Using '{}' (case-insensitive match for '{}')",
+            "ℹ".blue(),
+            resolved,
+            name
+        );
+    }
+    let name = resolved.as_str();
     let agent = config
-        .get_agent_mut(name)
-        .ok_or_else(|| anyhow::anyhow!("Agent '{}' not found in .agents.json", name))?;
+        .get_agent(name)
+        .cloned()
+        .ok_or_else(|| CcagentsError::AgentNotFound(name.to_string()))?;
 
     if agent.enabled {
         println!("{} Agent '{}' is already enabled", "ℹ".blue(), name);
         return Ok(());
     }
 
-    // Enable the agent
-    agent.enabled = true;
-
     // Create symlink
-    let _claude_agents_dir = ensure_claude_agents_dir(&project_root)?;
-    let local_path = agent.get_local_path(&project_root);
-    let link_path = agent.get_link_path(&project_root);
+    let _claude_agents_dir = ensure_claude_agents_dir(project_root)?;
+    let local_path = agent.get_local_path(project_root);
+    let link_path = agent.get_link_path(project_root);
 
     if !local_path.exists() {
-        return Err(anyhow::anyhow!(
-            "Agent source does not exist: {:?}. Run 'ccagents sync' to download missing agents.",
-            local_path
-        ));
+        match &agent.source {
+            AgentSource::GitHub(_) if download => {
+                println!("  {} from GitHub...", "Downloading".yellow());
+                print_download_warnings(&download_agent(&agent, project_root, &options).await?);
+            }
+            AgentSource::GitHub(_) => {
+                return Err(anyhow::anyhow!(
+                    "Agent source does not exist: {:?}. Run 'ccagents enable --download {}' or 'ccagents sync' to download it.",
+                    local_path,
+                    name
+                ));
+            }
+            AgentSource::Local(_) => {
+                return Err(anyhow::anyhow!(
+                    "Agent source does not exist: {:?}. Run 'ccagents sync' to download missing agents.",
+                    local_path
+                ));
+            }
+        }
     }
 
-    create_symlink(&local_path, &link_path)?;
+    link_agent(
+        &local_path,
+        &link_path,
+        config.link_mode(),
+        config.link_style,
+    )?;
 
-    // Save config
-    config.save(&project_root)?;
+    // Enable the agent once it is linked
+    if let Some(agent) = config.get_agent_mut(name) {
+        agent.enabled = true;
+    }
 
     println!("{} Agent '{}' has been enabled", "✓".green().bold(), name);
-    println!("  {} Created symlink in .claude/agents/", "→".cyan());
+    match config.link_mode() {
+        LinkMode::Symlink => println!("  {} Created symlink in .claude/agents/", "→".cyan()),
+        LinkMode::Copy => println!("  {} Copied into .claude/agents/", "→".cyan()),
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_enable_download_fetches_missing_github_source() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(200)
+            .with_body("# Agent")
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+
+        let mut config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        let mut agent = Agent::new("agent.md".to_string(), AgentSource::GitHub(url));
+        agent.enabled = false;
+        config.add_agent(agent).unwrap();
+        config.save(project_root).unwrap();
+
+        // Without --download the missing source is an error
+        assert!(run(project_root, "agent.md", false).await.is_err());
+
+        run(project_root, "agent.md", true).await.unwrap();
+
+        mock.assert_async().await;
+        let link_path = project_root.join(".claude/agents/agent.md");
+        assert_eq!(fs::read_to_string(link_path).unwrap(), "# Agent");
+        assert!(AgentsConfig::load(project_root).unwrap().agents[0].enabled);
+    }
+
+    #[tokio::test]
+    async fn test_enable_several_names_continues_past_unknown() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+
+        let mut config = AgentsConfig::default();
+        for name in ["a.md", "b.md"] {
+            fs::write(project_root.join(".ccagents").join(name), "# Agent").unwrap();
+            let mut agent = Agent::new(
+                name.to_string(),
+                AgentSource::Local(project_root.join(".ccagents").join(name)),
+            );
+            agent.enabled = false;
+            config.add_agent(agent).unwrap();
+        }
+        config.save(project_root).unwrap();
+
+        let names = ["a.md", "bogus.md", "b.md"].map(String::from);
+        let summary = run_names(project_root, &names, false).await.unwrap();
+
+        assert_eq!(summary.succeeded, 2);
+        assert_eq!(summary.not_found, vec!["bogus.md"]);
+        assert!(summary.finish("Enabled").is_err());
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(config.agents.iter().all(|agent| agent.enabled));
+        assert!(project_root.join(".claude/agents/a.md").is_symlink());
+        assert!(project_root.join(".claude/agents/b.md").is_symlink());
+    }
+
+    #[tokio::test]
+    async fn test_enable_glob_matches_several_agents() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+
+        let mut config = AgentsConfig::default();
+        for name in ["test-unit.md", "test-e2e.md", "backend.md"] {
+            fs::write(project_root.join(".ccagents").join(name), "# Agent").unwrap();
+            let mut agent = Agent::new(
+                name.to_string(),
+                AgentSource::Local(project_root.join(".ccagents").join(name)),
+            );
+            agent.enabled = false;
+            config.add_agent(agent).unwrap();
+        }
+        config.save(project_root).unwrap();
+
+        let matched = run_matching(project_root, "test-*", false).await.unwrap();
+
+        assert_eq!(matched, 2);
+        let config = AgentsConfig::load(project_root).unwrap();
+        let enabled: Vec<&str> = config
+            .enabled_agents()
+            .into_iter()
+            .map(|a| a.name.as_str())
+            .collect();
+        assert_eq!(enabled, vec!["test-unit.md", "test-e2e.md"]);
+        assert!(project_root.join(".claude/agents/test-e2e.md").is_symlink());
+        assert!(!project_root.join(".claude/agents/backend.md").exists());
+    }
+}
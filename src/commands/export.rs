@@ -0,0 +1,95 @@
+use crate::config::{get_project_root, AgentsConfig};
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+/// Writes the agents of `.agents.json` as a manifest to `output`, or to
+/// stdout. All agents are exported with their enabled flags unless
+/// `enabled_only` is set.
+pub fn execute(output: Option<&Path>, enabled_only: bool) -> Result<()> {
+    let project_root = get_project_root()?;
+    let manifest = manifest(&project_root, enabled_only)?;
+
+    match output {
+        Some(path) => {
+            fs::write(path, &manifest).with_context(|| format!("Failed to write {:?}", path))?;
+            eprintln!(
+                "{} Exported {} to {}",
+                "✓".green().bold(),
+                if enabled_only {
+                    "enabled agents"
+                } else {
+                    "all agents"
+                },
+                path.display()
+            );
+        }
+        None => println!("{}", manifest),
+    }
+
+    Ok(())
+}
+
+/// The manifest JSON: `.agents.json` with paths normalized like a save, and
+/// with `enabled_only` restricted to enabled agents. It can be loaded as
+/// `$CCAGENTS_MANIFEST` or used as another project's `.agents.json`.
+fn manifest(project_root: &Path, enabled_only: bool) -> Result<String> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    if enabled_only {
+        config.agents.retain(|agent| agent.enabled);
+    }
+    config.normalize(project_root);
+
+    serde_json::to_string_pretty(&config).context("Failed to serialize manifest")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSource};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_includes_disabled_unless_enabled_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+
+        let mut config = AgentsConfig::default();
+        for name in ["on.md", "off.md"] {
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(PathBuf::from(".ccagents").join(name)),
+                ))
+                .unwrap();
+        }
+        config.agents[1].enabled = false;
+        config.save(project_root).unwrap();
+
+        let exported = |enabled_only| {
+            AgentsConfig::parse_manifest(
+                &manifest(project_root, enabled_only).unwrap(),
+                project_root,
+            )
+            .unwrap()
+        };
+
+        let all = exported(false);
+        let flags: Vec<_> = all
+            .agents
+            .iter()
+            .map(|a| (a.name.as_str(), a.enabled))
+            .collect();
+        assert_eq!(flags, vec![("on.md", true), ("off.md", false)]);
+
+        let enabled = exported(true);
+        let names: Vec<_> = enabled.agents.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["on.md"]);
+        assert!(matches!(
+            &enabled.agents[0].source,
+            AgentSource::Local(path) if path == Path::new(".ccagents/on.md")
+        ));
+    }
+}
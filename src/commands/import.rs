@@ -1,15 +1,27 @@
 use crate::agent::{Agent, AgentSource};
-use crate::config::{ensure_ccagents_dir, get_project_root, AgentsConfig};
-use crate::linker::create_symlink;
-use anyhow::Result;
+use crate::claude_settings;
+use crate::commands::print_skipped_names;
+use crate::config::{ensure_managed_dir, get_project_root, scan_link_dir, AgentsConfig};
+use crate::ignore::IgnoreRules;
+use crate::linker::{copy_file, create_agent_symlink, LinkMode, LinkStyle};
+use anyhow::{Context, Result};
 use colored::*;
 use std::fs;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-pub fn execute(specific_name: Option<String>, all: bool) -> Result<()> {
+/// Imports unmanaged files from `.claude/agents`. With `dry_run` the planned
+/// moves, links and config entries are printed and nothing is changed.
+pub fn execute(
+    specific_name: Option<String>,
+    all: bool,
+    enabled: Option<bool>,
+    dry_run: bool,
+    on_conflict: ConflictStrategy,
+) -> Result<()> {
     let project_root = get_project_root()?;
     let mut config = AgentsConfig::load(&project_root)?;
-    let claude_agents_dir = project_root.join(".claude").join("agents");
+    let claude_agents_dir = claude_settings::agents_dir(&project_root);
 
     if !claude_agents_dir.exists() {
         println!("{}", "No .claude/agents directory found.".yellow());
@@ -17,23 +29,22 @@ pub fn execute(specific_name: Option<String>, all: bool) -> Result<()> {
     }
 
     // Find unmanaged files
+    let configured = config.agent_names();
+    let linked = config.link_names();
+    let ignore = IgnoreRules::for_config(&project_root, &config)?;
     let mut unmanaged_files = Vec::new();
+    let mut skipped_files = Vec::new();
 
-    for entry in fs::read_dir(&claude_agents_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
+    // Files in subdirectories are keyed by their relative path, e.g.
+    // `team/backend/agent.md`
+    let scan = scan_link_dir(&claude_agents_dir, &linked)?;
+    print_skipped_names(&scan.skipped);
+    for (name, path) in scan.entries {
         // Skip directories and symlinks
         if !path.is_file() || path.is_symlink() {
             continue;
         }
 
-        let name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_string();
-
         // Check if specific name was requested
         if let Some(ref specific) = specific_name {
             if name != *specific {
@@ -41,12 +52,26 @@ pub fn execute(specific_name: Option<String>, all: bool) -> Result<()> {
             }
         }
 
+        // Skip hidden, backup and ignored files
+        if !ignore.is_agent_entry(&path) {
+            skipped_files.push(name);
+            continue;
+        }
+
         // Check if already managed
-        if !config.agents.iter().any(|a| a.name == name) {
+        if !configured.contains(name.as_str()) && !linked.contains(name.as_str()) {
             unmanaged_files.push((name, path));
         }
     }
 
+    for name in &skipped_files {
+        println!(
+            "  {} Skipping ignored file: {}",
+            "⚠".yellow(),
+            name.dimmed()
+        );
+    }
+
     if unmanaged_files.is_empty() {
         if specific_name.is_some() {
             println!("{} No unmanaged file found with that name.", "ℹ".blue());
@@ -71,6 +96,25 @@ pub fn execute(specific_name: Option<String>, all: bool) -> Result<()> {
         println!("  {} {}", "◆".blue(), name);
     }
 
+    let enabled = enabled.unwrap_or(config.default_enabled);
+
+    if dry_run {
+        println!(
+            "\n{}",
+            "Dry run, nothing was changed. Import would:".yellow()
+        );
+        for line in plan_lines(
+            &unmanaged_files,
+            &project_root,
+            config.managed_dir(),
+            enabled,
+            on_conflict,
+        ) {
+            println!("  {}", line);
+        }
+        return Ok(());
+    }
+
     // Ask for confirmation if not using --all
     let should_import = if all {
         true
@@ -90,60 +134,644 @@ pub fn execute(specific_name: Option<String>, all: bool) -> Result<()> {
         return Ok(());
     }
 
-    // Import each file
-    let ccagents_dir = ensure_ccagents_dir(&project_root)?;
-    let mut imported_count = 0;
+    let managed_dir = config.managed_dir().to_path_buf();
+    let report = import_files(
+        &mut config,
+        &project_root,
+        unmanaged_files,
+        enabled,
+        on_conflict,
+    )?;
 
-    for (name, source_path) in unmanaged_files {
-        println!("\n{} {}", "Importing:".cyan(), name);
+    println!();
+    for line in report_lines(&report, &managed_dir, enabled) {
+        println!("{}", line);
+    }
 
-        // Copy to .ccagents
-        let target_path = ccagents_dir.join(&name);
+    if let Some(error) = report.error {
+        return Err(anyhow::anyhow!(
+            "Import failed; previously imported files were restored: {}",
+            error
+        ));
+    }
 
-        // Handle existing file in .ccagents
-        if target_path.exists() {
-            println!(
-                "  {} File already exists in .ccagents/, using existing",
-                "⚠".yellow()
-            );
+    let imported = report.imported();
+    println!(
+        "\n{} Successfully imported {} agent{}",
+        "✓".green().bold(),
+        imported,
+        if imported == 1 { "" } else { "s" }
+    );
+    if report.skipped() > 0 {
+        println!(
+            "  {} {} skipped because {} already has a file of that name",
+            "ℹ".blue(),
+            report.skipped(),
+            managed_dir.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// One line per file of `report`.
+fn report_lines(report: &ImportReport, managed_dir: &Path, enabled: bool) -> Vec<String> {
+    report
+        .files
+        .iter()
+        .map(|(name, outcome)| match outcome {
+            ImportOutcome::Imported if enabled => format!(
+                "  {} {}: copied to {} and replaced with a symlink",
+                "→".cyan(),
+                name,
+                managed_dir.display()
+            ),
+            ImportOutcome::Imported => format!(
+                "  {} {}: moved to {} (disabled)",
+                "→".cyan(),
+                name,
+                managed_dir.display()
+            ),
+            ImportOutcome::UsedExisting => format!(
+                "  {} {}: already in {}, using existing",
+                "⚠".yellow(),
+                name,
+                managed_dir.display()
+            ),
+            ImportOutcome::Skipped => format!(
+                "  {} {}: already in {}, skipped",
+                "○".yellow(),
+                name,
+                managed_dir.display()
+            ),
+            ImportOutcome::Failed(error) => format!("  {} {}: {}", "✗".red(), name, error),
+            ImportOutcome::RolledBack => format!("  {} Restored {}", "↺".yellow(), name),
+            ImportOutcome::RestoreFailed(error) => format!(
+                "  {} Failed to restore {} (copy kept in {}): {}",
+                "✗".red(),
+                name,
+                managed_dir.display(),
+                error
+            ),
+        })
+        .collect()
+}
+
+/// Describes what importing `files` into `managed_dir` would do, one step per
+/// line, mirroring [`import_files`].
+fn plan_lines(
+    files: &[(String, PathBuf)],
+    project_root: &Path,
+    managed_dir: &Path,
+    enabled: bool,
+    on_conflict: ConflictStrategy,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (name, path) in files {
+        let original = path.strip_prefix(project_root).unwrap_or(path).display();
+        let target = managed_dir.join(name);
+        let conflict = project_root.join(&target).exists();
+
+        if conflict && on_conflict == ConflictStrategy::Skip {
+            lines.push(format!(
+                "{} skip {}, {} already exists",
+                "→".cyan(),
+                original,
+                target.display()
+            ));
+            continue;
+        }
+        if conflict && on_conflict == ConflictStrategy::Fail {
+            lines.push(format!(
+                "{} fail: {} already exists",
+                "✗".red(),
+                target.display()
+            ));
+            break;
+        }
+
+        if conflict {
+            lines.push(format!(
+                "{} use existing {} for {}",
+                "→".cyan(),
+                target.display(),
+                original
+            ));
         } else {
-            fs::copy(&source_path, &target_path)
-                .map_err(|e| anyhow::anyhow!("Failed to copy {}: {}", name, e))?;
-            println!("  {} Copied to .ccagents/", "→".cyan());
+            lines.push(format!(
+                "{} move {} to {}",
+                "→".cyan(),
+                original,
+                target.display()
+            ));
+        }
+        if enabled {
+            lines.push(format!(
+                "{} replace {} with a symlink",
+                "→".cyan(),
+                original
+            ));
         }
+        lines.push(format!(
+            "{} add '{}' to .agents.json{}",
+            "→".cyan(),
+            name,
+            if enabled { "" } else { " (disabled)" }
+        ));
+    }
+    lines
+}
 
-        // Remove original file
-        fs::remove_file(&source_path)
-            .map_err(|e| anyhow::anyhow!("Failed to remove original {}: {}", name, e))?;
-        println!("  {} Removed original file", "→".cyan());
+/// How [`import_files`] treats a file whose name already exists in the
+/// managed dir.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictStrategy {
+    /// Link to the existing managed file; the unmanaged copy is dropped
+    #[default]
+    UseExisting,
+    /// Leave the unmanaged file where it is
+    Skip,
+    /// Abort the import, restoring files imported so far
+    Fail,
+}
 
-        // Create symlink
-        create_symlink(&target_path, &source_path)?;
-        println!("  {} Created symlink", "→".cyan());
+/// What happened to one file in an import batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+    /// Copied into the managed dir and added to the config.
+    Imported,
+    /// The managed dir already had the file; it was used instead.
+    UsedExisting,
+    /// Left untouched because of a conflict ([`ConflictStrategy::Skip`]).
+    Skipped,
+    /// Importing this file failed, so the batch was rolled back.
+    Failed(String),
+    /// Imported, then restored because the batch failed.
+    RolledBack,
+    /// Imported, but restoring it after the batch failed did not work; the
+    /// managed copy was kept.
+    RestoreFailed(String),
+}
 
-        // Add to config
-        let relative_target = target_path
-            .strip_prefix(&project_root)
-            .unwrap_or(&target_path)
-            .to_path_buf();
+/// Result of [`import_files`]: the outcome for every file, in order.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub files: Vec<(String, ImportOutcome)>,
+    /// Why the batch was rolled back, if it was.
+    pub error: Option<String>,
+}
+
+impl ImportReport {
+    /// Number of files that ended up as managed agents.
+    pub fn imported(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|(_, outcome)| {
+                matches!(
+                    outcome,
+                    ImportOutcome::Imported | ImportOutcome::UsedExisting
+                )
+            })
+            .count()
+    }
 
-        let agent = Agent::new(name.clone(), AgentSource::Local(relative_target));
+    pub fn skipped(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|(_, outcome)| *outcome == ImportOutcome::Skipped)
+            .count()
+    }
+}
+
+/// State needed to undo a single imported file.
+struct ImportedFile {
+    name: String,
+    original: PathBuf,
+    target: PathBuf,
+    copied: bool,
+}
 
-        config.add_agent(agent)?;
-        println!("  {} Added to .agents.json", "→".cyan());
+/// Imports `files` (name and path in `.claude/agents`) into the managed dir
+/// and saves the configuration. If any step fails, every file imported so
+/// far is restored and the config is not saved. Disabled imports are moved
+/// out of `.claude/agents` instead of linked. Prints nothing.
+pub fn import_files(
+    config: &mut AgentsConfig,
+    project_root: &Path,
+    files: Vec<(String, PathBuf)>,
+    enabled: bool,
+    on_conflict: ConflictStrategy,
+) -> Result<ImportReport> {
+    let ccagents_dir = ensure_managed_dir(project_root, config.managed_dir())?;
+    let mut report = ImportReport::default();
+    let mut imported: Vec<ImportedFile> = Vec::new();
+    let mut failure = None;
 
-        imported_count += 1;
+    for (name, source_path) in files {
+        let conflict = ccagents_dir.join(&name).exists();
+        match (conflict, on_conflict) {
+            (true, ConflictStrategy::Skip) => {
+                report.files.push((name, ImportOutcome::Skipped));
+                continue;
+            }
+            (true, ConflictStrategy::Fail) => {
+                failure = Some((
+                    name.clone(),
+                    format!("{} already exists in {}", name, ccagents_dir.display()),
+                ));
+                break;
+            }
+            _ => {}
+        }
+
+        // A nested file keeps its subpath, both in the managed dir and as the
+        // agent's subdir, so its link stays where it was
+        let (subdir, file_name) = match name.rsplit_once('/') {
+            Some((subdir, file_name)) => (Some(subdir.to_string()), file_name.to_string()),
+            None => (None, name.clone()),
+        };
+        let result = import_file(
+            &name,
+            &source_path,
+            &ccagents_dir,
+            enabled,
+            config.link_mode(),
+            config.link_style,
+        )
+        .and_then(|file| {
+            let mut agent = Agent::new(file_name, AgentSource::Local(file.target.clone()));
+            agent.enabled = enabled;
+            agent.subdir = subdir;
+            imported.push(file);
+            config.add_agent(agent)
+        });
+
+        if let Err(e) = result {
+            failure = Some((name, format!("{:#}", e)));
+            break;
+        }
     }
 
-    // Save config
-    config.save(&project_root)?;
+    let failure = match failure {
+        Some((name, message)) => {
+            report
+                .files
+                .push((name, ImportOutcome::Failed(message.clone())));
+            Some(message)
+        }
+        None => config.save(project_root).err().map(|e| format!("{:#}", e)),
+    };
 
-    println!(
-        "\n{} Successfully imported {} agent{}",
-        "✓".green().bold(),
-        imported_count,
-        if imported_count == 1 { "" } else { "s" }
-    );
+    match failure {
+        Some(message) => {
+            report.files.extend(rollback(&imported));
+            report.error = Some(message);
+        }
+        None => report.files.extend(imported.into_iter().map(|file| {
+            let outcome = if file.copied {
+                ImportOutcome::Imported
+            } else {
+                ImportOutcome::UsedExisting
+            };
+            (file.name, outcome)
+        })),
+    }
 
-    Ok(())
+    Ok(report)
+}
+
+/// Copies `source_path` into `.ccagents` and replaces it with a symlink. The
+/// link is staged under a temporary name and renamed over the original, so
+/// the original file is only replaced once the symlink exists. In copy mode
+/// the original stays as the agent's copy. Without `link` the original is
+/// removed after copying. An existing file in `.ccagents` is used as is.
+fn import_file(
+    name: &str,
+    source_path: &Path,
+    ccagents_dir: &Path,
+    link: bool,
+    link_mode: LinkMode,
+    link_style: LinkStyle,
+) -> Result<ImportedFile> {
+    let target_path = ccagents_dir.join(name);
+
+    let copied = if target_path.exists() {
+        false
+    } else {
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        copy_file(source_path, &target_path).with_context(|| format!("Failed to copy {}", name))?;
+        true
+    };
+
+    let imported = ImportedFile {
+        name: name.to_string(),
+        original: source_path.to_path_buf(),
+        target: target_path,
+        copied,
+    };
+
+    if !link {
+        if let Err(e) = fs::remove_file(source_path) {
+            if copied {
+                fs::remove_file(&imported.target).ok();
+            }
+            return Err(e).with_context(|| format!("Failed to remove original {}", name));
+        }
+        return Ok(imported);
+    }
+
+    if link_mode == LinkMode::Copy {
+        // The copy must match the source in `.ccagents`, which is kept when it
+        // already existed
+        if !copied {
+            copy_file(&imported.target, source_path)
+                .with_context(|| format!("Failed to replace original {}", name))?;
+        }
+        return Ok(imported);
+    }
+
+    let staged_link = staging_link_path(source_path);
+    let linked = create_agent_symlink(&imported.target, &staged_link, link_style).and_then(|_| {
+        fs::rename(&staged_link, source_path)
+            .with_context(|| format!("Failed to replace original {}", name))
+    });
+
+    if let Err(e) = linked {
+        fs::remove_file(&staged_link).ok();
+        if copied {
+            fs::remove_file(&imported.target).ok();
+        }
+        return Err(e);
+    }
+
+    Ok(imported)
+}
+
+/// Restores imported files in reverse order, putting the original content
+/// back in place of the symlink (or where it was removed). Returns the
+/// outcome for each file, in import order.
+fn rollback(imported: &[ImportedFile]) -> Vec<(String, ImportOutcome)> {
+    let mut outcomes: Vec<(String, ImportOutcome)> = imported
+        .iter()
+        .rev()
+        .map(|file| {
+            let unlinked = if file.original.is_symlink() {
+                fs::remove_file(&file.original)
+            } else {
+                Ok(())
+            };
+            let restored = unlinked
+                .map_err(anyhow::Error::from)
+                .and_then(|_| copy_file(&file.target, &file.original));
+
+            let outcome = match restored {
+                Ok(_) => {
+                    if file.copied {
+                        fs::remove_file(&file.target).ok();
+                    }
+                    ImportOutcome::RolledBack
+                }
+                Err(e) => ImportOutcome::RestoreFailed(format!("{:#}", e)),
+            };
+            (file.name.clone(), outcome)
+        })
+        .collect();
+    outcomes.reverse();
+    outcomes
+}
+
+/// Temporary (hidden) path used to stage a symlink next to `original`.
+fn staging_link_path(original: &Path) -> PathBuf {
+    let name = original
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    original.with_file_name(format!(".{}.ccagents-tmp", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ensure_claude_agents_dir;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_import_files_links_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        let original = claude_agents_dir.join("agent.md");
+        fs::write(&original, "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        let report = import_files(
+            &mut config,
+            project_root,
+            vec![("agent.md".to_string(), original.clone())],
+            true,
+            ConflictStrategy::UseExisting,
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.files,
+            vec![("agent.md".to_string(), ImportOutcome::Imported)]
+        );
+        assert!(report.error.is_none());
+        assert!(original.is_symlink());
+        assert_eq!(fs::read_to_string(&original).unwrap(), "# Agent");
+        assert!(!staging_link_path(&original).exists());
+        assert_eq!(AgentsConfig::load(project_root).unwrap().agents.len(), 1);
+    }
+
+    #[test]
+    fn test_import_files_keeps_nested_subpath() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let nested_dir = ensure_claude_agents_dir(project_root)
+            .unwrap()
+            .join("team/backend");
+        fs::create_dir_all(&nested_dir).unwrap();
+        let original = nested_dir.join("deep.md");
+        fs::write(&original, "# Deep").unwrap();
+
+        let mut config = AgentsConfig::default();
+        let report = import_files(
+            &mut config,
+            project_root,
+            vec![("team/backend/deep.md".to_string(), original.clone())],
+            true,
+            ConflictStrategy::UseExisting,
+        )
+        .unwrap();
+
+        assert!(report.error.is_none());
+        assert!(original.is_symlink());
+        assert_eq!(fs::read_to_string(&original).unwrap(), "# Deep");
+        assert!(project_root
+            .join(".ccagents/team/backend/deep.md")
+            .is_file());
+
+        let config = AgentsConfig::load(project_root).unwrap();
+        let agent = config.get_agent("deep.md").unwrap();
+        assert_eq!(agent.subdir.as_deref(), Some("team/backend"));
+        assert_eq!(agent.get_link_path(project_root), original);
+    }
+
+    #[test]
+    fn test_import_files_disabled_moves_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        let original = claude_agents_dir.join("agent.md");
+        fs::write(&original, "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        import_files(
+            &mut config,
+            project_root,
+            vec![("agent.md".to_string(), original.clone())],
+            false,
+            ConflictStrategy::UseExisting,
+        )
+        .unwrap();
+
+        assert!(!original.exists() && !original.is_symlink());
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/agent.md")).unwrap(),
+            "# Agent"
+        );
+        assert!(!AgentsConfig::load(project_root).unwrap().agents[0].enabled);
+    }
+
+    #[test]
+    fn test_import_files_rolls_back_on_symlink_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+
+        let first = claude_agents_dir.join("first.md");
+        let second = claude_agents_dir.join("second.md");
+        fs::write(&first, "# First").unwrap();
+        fs::write(&second, "# Second").unwrap();
+
+        // Block the staged symlink for the second file so linking fails
+        let blocker = staging_link_path(&second);
+        fs::create_dir(&blocker).unwrap();
+        fs::write(blocker.join("keep"), "").unwrap();
+
+        let mut config = AgentsConfig::default();
+        let report = import_files(
+            &mut config,
+            project_root,
+            vec![
+                ("first.md".to_string(), first.clone()),
+                ("second.md".to_string(), second.clone()),
+            ],
+            true,
+            ConflictStrategy::UseExisting,
+        )
+        .unwrap();
+        assert!(report.error.is_some());
+        assert_eq!(report.imported(), 0);
+        assert_eq!(report.files[0].0, "second.md");
+        assert!(matches!(report.files[0].1, ImportOutcome::Failed(_)));
+        assert_eq!(
+            report.files[1],
+            ("first.md".to_string(), ImportOutcome::RolledBack)
+        );
+
+        // Both originals survive as regular files with their content
+        assert!(!first.is_symlink());
+        assert_eq!(fs::read_to_string(&first).unwrap(), "# First");
+        assert!(!second.is_symlink());
+        assert_eq!(fs::read_to_string(&second).unwrap(), "# Second");
+
+        // Copies made by the failed batch are removed and nothing is saved
+        assert!(!project_root.join(".ccagents/first.md").exists());
+        assert!(!project_root.join(".ccagents/second.md").exists());
+        assert!(!project_root.join(".agents.json").exists());
+    }
+
+    #[test]
+    fn test_import_files_conflict_strategies() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        let ccagents_dir = project_root.join(".ccagents");
+        fs::create_dir_all(&ccagents_dir).unwrap();
+        fs::write(ccagents_dir.join("taken.md"), "# Managed").unwrap();
+
+        let taken = claude_agents_dir.join("taken.md");
+        let fresh = claude_agents_dir.join("fresh.md");
+        let files = || {
+            vec![
+                ("fresh.md".to_string(), fresh.clone()),
+                ("taken.md".to_string(), taken.clone()),
+            ]
+        };
+        let reset = || {
+            for (path, content) in [(&fresh, "# Fresh"), (&taken, "# Unmanaged")] {
+                fs::remove_file(path).ok();
+                fs::write(path, content).unwrap();
+            }
+            fs::remove_file(ccagents_dir.join("fresh.md")).ok();
+            fs::remove_file(project_root.join(".agents.json")).ok();
+        };
+
+        // Fail aborts the batch and restores the file imported before it
+        reset();
+        let mut config = AgentsConfig::default();
+        let report = import_files(
+            &mut config,
+            project_root,
+            files(),
+            true,
+            ConflictStrategy::Fail,
+        )
+        .unwrap();
+        assert!(report.error.unwrap().contains("already exists"));
+        assert_eq!(
+            report.files[1],
+            ("fresh.md".to_string(), ImportOutcome::RolledBack)
+        );
+        assert!(!fresh.is_symlink() && !taken.is_symlink());
+
+        // Skip leaves the conflicting file alone
+        reset();
+        let mut config = AgentsConfig::default();
+        let report = import_files(
+            &mut config,
+            project_root,
+            files(),
+            true,
+            ConflictStrategy::Skip,
+        )
+        .unwrap();
+        assert_eq!((report.imported(), report.skipped()), (1, 1));
+        assert_eq!(fs::read_to_string(&taken).unwrap(), "# Unmanaged");
+        assert!(!taken.is_symlink());
+
+        // UseExisting links the conflicting file to the managed copy
+        reset();
+        let mut config = AgentsConfig::default();
+        let report = import_files(
+            &mut config,
+            project_root,
+            files(),
+            true,
+            ConflictStrategy::UseExisting,
+        )
+        .unwrap();
+        assert_eq!(
+            report.files,
+            vec![
+                ("fresh.md".to_string(), ImportOutcome::Imported),
+                ("taken.md".to_string(), ImportOutcome::UsedExisting),
+            ]
+        );
+        assert_eq!(fs::read_to_string(&taken).unwrap(), "# Managed");
+    }
 }
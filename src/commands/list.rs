@@ -1,124 +1,531 @@
-use crate::config::{get_project_root, AgentsConfig};
-use crate::linker::is_symlink_valid;
+use crate::agent::{Agent, AgentSource};
+use crate::claude_settings;
+use crate::commands::print_skipped_names;
+use crate::config::{get_project_root, normalize_path, read_dir_sorted, AgentsConfig};
+use crate::error::CcagentsError;
+use crate::ignore::{scanned_file_name, IgnoreRules};
+use crate::linker::{symlink_status, SymlinkStatus};
+use crate::output::{OutputFormat, Presenter, Report, StdoutPresenter};
 use anyhow::Result;
 use colored::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
 
-pub fn execute() -> Result<()> {
+pub fn execute(output: OutputFormat, require_agents: bool) -> Result<()> {
     let project_root = get_project_root()?;
     let config = AgentsConfig::load(&project_root)?;
+    if require_agents && config.agents.is_empty() {
+        return Err(CcagentsError::NoAgentsConfigured.into());
+    }
 
-    println!("{}", "Agents Status:".cyan().bold());
-    println!();
-
-    // List enabled agents
-    let enabled = config.enabled_agents();
-    if !enabled.is_empty() {
-        println!("{}", "Enabled agents:".green().bold());
-        for agent in &enabled {
-            let link_path = agent.get_link_path(&project_root);
-            let local_path = agent.get_local_path(&project_root);
-
-            // Determine detailed status
-            let status = if !local_path.exists() {
-                "⚠ source missing".red().to_string()
-            } else if !link_path.exists() && !link_path.is_symlink() {
-                "⚠ not linked".yellow().to_string()
-            } else if !is_symlink_valid(&link_path) {
-                "⚠ link broken".yellow().to_string()
-            } else {
-                "✓ linked".green().to_string()
-            };
+    let report = build_report(&config, &project_root)?;
+    StdoutPresenter::new(output).present(&report)
+}
 
-            println!("  {} {} - {}", "●".green(), agent.name, status);
+/// Configured agents with their status, plus directories in the managed dir
+/// that are not configured yet.
+#[derive(Debug, Serialize)]
+struct ListReport {
+    agents: Vec<AgentEntry>,
+    available: Vec<String>,
+    #[serde(skip)]
+    managed_dir: PathBuf,
+}
 
-            // Show source
-            match &agent.source {
-                crate::agent::AgentSource::Local(path) => {
-                    println!("    {} {}", "source:".dimmed(), path.display());
-                }
-                crate::agent::AgentSource::GitHub(url) => {
-                    println!("    {} {}", "source:".dimmed(), url);
-                }
-            }
-        }
-    } else {
-        println!("{}", "No enabled agents".dimmed());
-    }
+#[derive(Debug, Serialize)]
+struct AgentEntry {
+    name: String,
+    source: String,
+    enabled: bool,
+    status: AgentStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
 
-    println!();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum AgentStatus {
+    Linked,
+    NotLinked,
+    LinkBroken,
+    FileInTheWay,
+    SourceMissing,
+    Disabled,
+}
 
-    // List disabled agents from config
-    let disabled = config.disabled_agents();
-    if !disabled.is_empty() {
-        println!("{}", "Disabled agents (in .agents.json):".yellow().bold());
-        for agent in &disabled {
-            println!(
-                "  {} {} - {}",
-                "○".yellow(),
-                agent.name,
-                "disabled".dimmed()
-            );
+fn build_report(config: &AgentsConfig, project_root: &Path) -> Result<ListReport> {
+    let agents = config
+        .agents
+        .iter()
+        .map(|agent| AgentEntry {
+            name: agent.name.clone(),
+            source: source_display(&agent.source),
+            enabled: agent.enabled,
+            status: agent_status(agent, project_root),
+            description: agent.description(project_root),
+        })
+        .collect();
 
-            // Show source
-            match &agent.source {
-                crate::agent::AgentSource::Local(path) => {
-                    println!("    {} {}", "source:".dimmed(), path.display());
-                }
-                crate::agent::AgentSource::GitHub(url) => {
-                    println!("    {} {}", "source:".dimmed(), url);
-                }
-            }
+    // Directories in the managed dir that are not in config
+    let managed_dir = config.managed_dir();
+    let ccagents_dir = project_root.join(managed_dir);
+    let available = if ccagents_dir.exists() {
+        let ignore = IgnoreRules::for_config(project_root, config)?;
+        find_available_agents(&ccagents_dir, &config.agent_names(), &ignore)?
+    } else {
+        Vec::new()
+    };
+
+    Ok(ListReport {
+        agents,
+        available,
+        managed_dir: managed_dir.to_path_buf(),
+    })
+}
+
+fn agent_status(agent: &Agent, project_root: &Path) -> AgentStatus {
+    if !agent.get_local_path(project_root).exists() {
+        AgentStatus::SourceMissing
+    } else if !agent.enabled {
+        AgentStatus::Disabled
+    } else {
+        match symlink_status(&agent.get_link_path(project_root)) {
+            SymlinkStatus::NotExist => AgentStatus::NotLinked,
+            SymlinkStatus::NotSymlink => AgentStatus::FileInTheWay,
+            SymlinkStatus::Dangling => AgentStatus::LinkBroken,
+            SymlinkStatus::Valid => AgentStatus::Linked,
         }
     }
+}
 
-    println!();
+impl Report for ListReport {
+    fn table(&self) -> Vec<String> {
+        let mut lines = vec!["Agents Status:".cyan().bold().to_string(), String::new()];
 
-    // List available agents in .ccagents that are not in config
-    let ccagents_dir = project_root.join(".ccagents");
-    if ccagents_dir.exists() {
-        let mut available_agents = Vec::new();
+        // List enabled agents
+        let enabled: Vec<&AgentEntry> = self.agents.iter().filter(|a| a.enabled).collect();
+        if !enabled.is_empty() {
+            lines.push("Enabled agents:".green().bold().to_string());
+            for agent in &enabled {
+                let status = match agent.status {
+                    AgentStatus::SourceMissing => "⚠ source missing".red(),
+                    AgentStatus::NotLinked => "⚠ not linked".yellow(),
+                    AgentStatus::FileInTheWay => "⚠ file in the way of the link".yellow(),
+                    AgentStatus::LinkBroken => "⚠ link broken".yellow(),
+                    AgentStatus::Linked | AgentStatus::Disabled => "✓ linked".green(),
+                };
 
-        for entry in fs::read_dir(&ccagents_dir)? {
-            let entry = entry?;
-            let path = entry.path();
+                lines.push(format!("  {} {} - {}", "●".green(), agent.name, status));
+                lines.push(format!("    {} {}", "source:".dimmed(), agent.source));
+                if let Some(description) = &agent.description {
+                    lines.push(format!("    {} {}", "description:".dimmed(), description));
+                }
+            }
+        } else {
+            lines.push("No enabled agents".dimmed().to_string());
+        }
 
-            if path.is_dir() {
-                let name = path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
+        lines.push(String::new());
 
-                // Check if this agent is not already in config
-                if !config.agents.iter().any(|a| a.name == name) {
-                    available_agents.push(name);
+        // List disabled agents from config
+        let disabled: Vec<&AgentEntry> = self.agents.iter().filter(|a| !a.enabled).collect();
+        if !disabled.is_empty() {
+            lines.push(
+                "Disabled agents (in .agents.json):"
+                    .yellow()
+                    .bold()
+                    .to_string(),
+            );
+            for agent in &disabled {
+                lines.push(format!(
+                    "  {} {} - {}",
+                    "○".yellow(),
+                    agent.name,
+                    "disabled".dimmed()
+                ));
+                lines.push(format!("    {} {}", "source:".dimmed(), agent.source));
+                if let Some(description) = &agent.description {
+                    lines.push(format!("    {} {}", "description:".dimmed(), description));
                 }
             }
         }
 
-        if !available_agents.is_empty() {
-            println!(
-                "{}",
-                "Available agents (in .ccagents but not configured):"
-                    .blue()
-                    .bold()
+        lines.push(String::new());
+
+        // List available agents in the managed dir that are not in config
+        if !self.available.is_empty() {
+            lines.push(
+                format!(
+                    "Available agents (in {} but not configured):",
+                    self.managed_dir.display()
+                )
+                .blue()
+                .bold()
+                .to_string(),
             );
-            for name in available_agents {
-                println!("  {} {} - {}", "◇".blue(), name, "not configured".dimmed());
-                println!("    {} ccagents add .ccagents/{}", "hint:".dimmed(), name);
+            for name in &self.available {
+                lines.push(format!(
+                    "  {} {} - {}",
+                    "◇".blue(),
+                    name,
+                    "not configured".dimmed()
+                ));
+                lines.push(format!(
+                    "    {} ccagents add {}",
+                    "hint:".dimmed(),
+                    self.managed_dir.join(name).display()
+                ));
             }
         }
+
+        // Summary
+        lines.push(String::new());
+        lines.push(format!(
+            "{}: {} enabled, {} disabled",
+            "Total".bold(),
+            enabled.len(),
+            disabled.len()
+        ));
+
+        lines
+    }
+
+    /// One `STATUS\tNAME\tSOURCE` line per configured agent. This format is
+    /// stable across versions. Status codes:
+    ///
+    /// * `E` - enabled and linked
+    /// * `D` - disabled
+    /// * `B` - enabled but the link is missing or broken
+    /// * `M` - source missing
+    fn plain(&self) -> Vec<String> {
+        self.agents
+            .iter()
+            .map(|agent| {
+                let code = match agent.status {
+                    AgentStatus::SourceMissing => 'M',
+                    AgentStatus::Disabled => 'D',
+                    AgentStatus::Linked => 'E',
+                    AgentStatus::NotLinked
+                    | AgentStatus::LinkBroken
+                    | AgentStatus::FileInTheWay => 'B',
+                };
+                format!("{}\t{}\t{}", code, agent.name, agent.source)
+            })
+            .collect()
+    }
+}
+
+/// Prints the number of configured agents, only enabled or disabled ones
+/// when `enabled` is given, with no other output.
+pub fn count(enabled: Option<bool>, require_agents: bool) -> Result<()> {
+    let project_root = get_project_root()?;
+    let config = AgentsConfig::load(&project_root)?;
+    if require_agents && config.agents.is_empty() {
+        return Err(CcagentsError::NoAgentsConfigured.into());
+    }
+
+    println!("{}", count_agents(&config, enabled));
+
+    Ok(())
+}
+
+/// Prints the managed dir and `.claude/agents` as indented trees, marking
+/// configured entries with their status and links with their targets.
+pub fn tree(require_agents: bool) -> Result<()> {
+    let project_root = get_project_root()?;
+    let config = AgentsConfig::load(&project_root)?;
+    if require_agents && config.agents.is_empty() {
+        return Err(CcagentsError::NoAgentsConfigured.into());
     }
 
-    // Summary
-    println!();
-    println!(
-        "{}: {} enabled, {} disabled",
-        "Total".bold(),
-        enabled.len(),
-        disabled.len()
+    for line in tree_lines(&config, &project_root)? {
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+fn tree_lines(config: &AgentsConfig, project_root: &Path) -> Result<Vec<String>> {
+    let ignore = IgnoreRules::for_config(project_root, config)?;
+    let agents: HashMap<PathBuf, &Agent> = config
+        .agents
+        .iter()
+        .map(|agent| (normalize_path(&agent.get_local_path(project_root)), agent))
+        .collect();
+
+    let mut lines = Vec::new();
+    let managed_dir = config.managed_dir();
+    lines.push(managed_dir.display().to_string().cyan().bold().to_string());
+    push_tree(
+        &project_root.join(managed_dir),
+        "",
+        &ignore,
+        &mut lines,
+        &|path| {
+            agents
+                .get(&normalize_path(path))
+                .map(|agent| format!("[{}]", status_label(agent_status(agent, project_root))))
+        },
+    )?;
+
+    let claude_agents_dir = claude_settings::agents_dir(project_root);
+    lines.push(String::new());
+    lines.push(
+        claude_agents_dir
+            .strip_prefix(project_root)
+            .unwrap_or(&claude_agents_dir)
+            .display()
+            .to_string()
+            .cyan()
+            .bold()
+            .to_string(),
     );
+    push_tree(&claude_agents_dir, "", &ignore, &mut lines, &|path| {
+        fs::read_link(path).ok().map(|target| {
+            let target = normalize_path(&path.parent().unwrap_or(path).join(target));
+            format!(
+                "-> {}",
+                target
+                    .strip_prefix(project_root)
+                    .unwrap_or(&target)
+                    .display()
+            )
+        })
+    })?;
+
+    Ok(lines)
+}
+
+/// Appends one line per entry below `dir` (sorted, recursing into real
+/// directories) to `lines`, followed by the `label` of the entry if any.
+fn push_tree(
+    dir: &Path,
+    indent: &str,
+    ignore: &IgnoreRules,
+    lines: &mut Vec<String>,
+    label: &dyn Fn(&Path) -> Option<String>,
+) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    let mut entries = Vec::new();
+    for entry in read_dir_sorted(dir)? {
+        let path = entry.path();
+        if ignore.is_agent_entry(&path) {
+            entries.push(path);
+        }
+    }
+
+    for (i, path) in entries.iter().enumerate() {
+        let last = i == entries.len() - 1;
+        let is_dir = path.is_dir() && !path.is_symlink();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let mut line = format!(
+            "{}{}{}{}",
+            indent,
+            if last { "└── " } else { "├── " },
+            name,
+            if is_dir { "/" } else { "" }
+        );
+        if let Some(label) = label(path) {
+            line.push_str("  ");
+            line.push_str(&label);
+        }
+        lines.push(line);
+
+        if is_dir {
+            let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+            push_tree(path, &indent, ignore, lines, label)?;
+        }
+    }
 
     Ok(())
 }
+
+fn status_label(status: AgentStatus) -> &'static str {
+    match status {
+        AgentStatus::Linked => "enabled, linked",
+        AgentStatus::NotLinked => "enabled, not linked",
+        AgentStatus::LinkBroken => "enabled, link broken",
+        AgentStatus::FileInTheWay => "enabled, file in the way",
+        AgentStatus::Disabled => "disabled",
+        AgentStatus::SourceMissing => "source missing",
+    }
+}
+
+fn count_agents(config: &AgentsConfig, enabled: Option<bool>) -> usize {
+    config
+        .agents
+        .iter()
+        .filter(|agent| enabled.is_none_or(|enabled| agent.enabled == enabled))
+        .count()
+}
+
+fn source_display(source: &AgentSource) -> String {
+    match source {
+        AgentSource::Local(path) => path.display().to_string(),
+        AgentSource::GitHub(url) => url.clone(),
+    }
+}
+
+/// Returns the directories in `ccagents_dir` whose names are not configured,
+/// skipping entries matched by the ignore rules.
+fn find_available_agents(
+    ccagents_dir: &Path,
+    configured: &HashSet<&str>,
+    ignore: &IgnoreRules,
+) -> Result<Vec<String>> {
+    let mut available_agents = Vec::new();
+
+    for entry in read_dir_sorted(ccagents_dir)? {
+        let path = entry.path();
+
+        if path.is_dir() {
+            let Some(name) = scanned_file_name(&path) else {
+                print_skipped_names(&[path]);
+                continue;
+            };
+
+            // Check if this agent is not ignored or already in config
+            if ignore.is_agent_entry(&path) && !configured.contains(name.as_str()) {
+                available_agents.push(name);
+            }
+        }
+    }
+
+    Ok(available_agents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linker::create_symlink;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_porcelain_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = project_root.join(".ccagents");
+        fs::create_dir_all(&ccagents_dir).unwrap();
+        for name in ["linked.md", "unlinked.md", "disabled.md"] {
+            fs::write(ccagents_dir.join(name), "# Agent").unwrap();
+        }
+
+        let mut config = AgentsConfig::default();
+        for name in ["linked.md", "unlinked.md", "disabled.md", "missing.md"] {
+            config.agents.push(Agent::new(
+                name.to_string(),
+                AgentSource::Local(PathBuf::from(".ccagents").join(name)),
+            ));
+        }
+        config.agents[2].enabled = false;
+        config.agents.push(Agent::new(
+            "remote.md".to_string(),
+            AgentSource::GitHub("https://github.com/u/r/blob/main/remote.md".to_string()),
+        ));
+
+        let linked = &config.agents[0];
+        create_symlink(
+            &linked.get_local_path(project_root),
+            &linked.get_link_path(project_root),
+        )
+        .unwrap();
+
+        let report = build_report(&config, project_root).unwrap();
+        assert_eq!(
+            report.plain(),
+            vec![
+                "E\tlinked.md\t.ccagents/linked.md",
+                "B\tunlinked.md\t.ccagents/unlinked.md",
+                "D\tdisabled.md\t.ccagents/disabled.md",
+                "M\tmissing.md\t.ccagents/missing.md",
+                "M\tremote.md\thttps://github.com/u/r/blob/main/remote.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tree_lines_for_nested_managed_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = project_root.join(".ccagents");
+        fs::create_dir_all(ccagents_dir.join("backend/db")).unwrap();
+        fs::write(ccagents_dir.join("backend/api.md"), "# API").unwrap();
+        fs::write(ccagents_dir.join("backend/db/schema.md"), "# Schema").unwrap();
+        fs::write(ccagents_dir.join("reviewer.md"), "# Reviewer").unwrap();
+        fs::write(ccagents_dir.join("scratch.md"), "# Scratch").unwrap();
+
+        let mut config = AgentsConfig::default();
+        for (name, path) in [
+            ("backend", ".ccagents/backend"),
+            ("reviewer.md", ".ccagents/reviewer.md"),
+        ] {
+            config.agents.push(Agent::new(
+                name.to_string(),
+                AgentSource::Local(PathBuf::from(path)),
+            ));
+        }
+        config.agents[1].enabled = false;
+        let backend = &config.agents[0];
+        create_symlink(
+            &backend.get_local_path(project_root),
+            &backend.get_link_path(project_root),
+        )
+        .unwrap();
+
+        let lines = tree_lines(&config, project_root).unwrap();
+        assert_eq!(
+            lines[1..7],
+            [
+                "├── backend/  [enabled, linked]",
+                "│   ├── api.md",
+                "│   └── db/",
+                "│       └── schema.md",
+                "├── reviewer.md  [disabled]",
+                "└── scratch.md",
+            ]
+        );
+        assert!(lines[8].contains(".claude/agents"));
+        assert_eq!(lines[9..], ["└── backend  -> .ccagents/backend"]);
+    }
+
+    #[test]
+    fn test_find_available_agents_with_many_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let ccagents_dir = temp_dir.path().join(".ccagents");
+
+        let mut config = AgentsConfig::default();
+        for i in 0..400 {
+            let name = format!("agent-{:03}", i);
+            fs::create_dir_all(ccagents_dir.join(&name)).unwrap();
+
+            // Configure every other agent
+            if i % 2 == 0 {
+                config.agents.push(Agent::new(
+                    name.clone(),
+                    AgentSource::Local(PathBuf::from(".ccagents").join(&name)),
+                ));
+            }
+        }
+
+        let mut available = find_available_agents(
+            &ccagents_dir,
+            &config.agent_names(),
+            &IgnoreRules::default(),
+        )
+        .unwrap();
+        available.sort();
+
+        assert_eq!(available.len(), 200);
+        assert_eq!(available[0], "agent-001");
+        assert!(available
+            .iter()
+            .all(|name| !config.agent_names().contains(name.as_str())));
+    }
+}
@@ -1,8 +1,123 @@
 pub mod add;
+pub mod alias;
+pub mod annotate;
+pub mod cache;
+pub mod check;
 pub mod clean;
 pub mod disable;
 pub mod doctor;
+pub mod edit;
 pub mod enable;
+pub mod export;
 pub mod import;
 pub mod list;
+pub mod relink;
 pub mod sync;
+pub mod update;
+pub mod verify;
+
+use crate::error::CcagentsError;
+use crate::ignore::SKIPPED_NAME_WARNING;
+use anyhow::Result;
+use colored::*;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+/// Prints the entries a scan skipped because their name is not valid UTF-8.
+pub fn print_skipped_names(skipped: &[PathBuf]) {
+    for path in skipped {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        println!(
+            "  {} {} - {}",
+            "⚠".yellow(),
+            name.dimmed(),
+            SKIPPED_NAME_WARNING
+        );
+    }
+}
+
+/// Prints the warnings a download returned, e.g. about its `Content-Type`.
+pub fn print_download_warnings(warnings: &[String]) {
+    for warning in warnings {
+        println!("  {} {}", "⚠".yellow(), warning);
+    }
+}
+
+/// Reads newline-delimited agent names, skipping blank lines.
+pub fn read_names(input: impl BufRead) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for line in input.lines() {
+        let line = line?;
+        let name = line.trim();
+        if !name.is_empty() {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// Outcome of running `enable` or `disable` over a list of names.
+#[derive(Debug, Default)]
+pub struct NameSummary {
+    pub succeeded: usize,
+    pub not_found: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl NameSummary {
+    /// Records the result for `name`, printing failures as they happen.
+    pub fn record(&mut self, name: &str, result: Result<()>) {
+        let Err(e) = result else {
+            self.succeeded += 1;
+            return;
+        };
+
+        println!("  {} {}: {}", "✗".red(), name, e);
+        let not_found = e
+            .chain()
+            .any(|e| matches!(e.downcast_ref(), Some(CcagentsError::AgentNotFound(_))));
+        if not_found {
+            self.not_found.push(name.to_string());
+        } else {
+            self.failed.push(name.to_string());
+        }
+    }
+
+    /// Prints the summary line and fails when any name was not processed.
+    /// `verb` is the past tense of the action, e.g. "Disabled".
+    pub fn finish(&self, verb: &str) -> Result<()> {
+        let total = self.succeeded + self.not_found.len() + self.failed.len();
+        let ok = self.not_found.is_empty() && self.failed.is_empty();
+        println!(
+            "\n{} {} {} of {} agent{} ({} not found, {} failed)",
+            if ok {
+                "✓".green().bold()
+            } else {
+                "⚠".yellow().bold()
+            },
+            verb,
+            self.succeeded,
+            total,
+            if total == 1 { "" } else { "s" },
+            self.not_found.len(),
+            self.failed.len()
+        );
+
+        if !ok {
+            let skipped: Vec<&str> = self
+                .not_found
+                .iter()
+                .chain(&self.failed)
+                .map(String::as_str)
+                .collect();
+            return Err(anyhow::anyhow!(
+                "{} agent{} not processed: {}",
+                skipped.len(),
+                if skipped.len() == 1 { " was" } else { "s were" },
+                skipped.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+}
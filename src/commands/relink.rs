@@ -0,0 +1,163 @@
+use crate::commands::print_skipped_names;
+use crate::config::{ensure_claude_agents_dir, get_project_root, scan_link_dir, AgentsConfig};
+use crate::linker::{link_agent, remove_symlink};
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+pub fn execute() -> Result<()> {
+    let project_root = get_project_root()?;
+
+    println!("{}", "Relinking agents...".cyan().bold());
+
+    let (linked, skipped) = run(&project_root)?;
+
+    println!(
+        "\n{} Relinked {} agent{}",
+        "✓".green().bold(),
+        linked,
+        if linked == 1 { "" } else { "s" }
+    );
+    if skipped > 0 {
+        println!(
+            "  {} {} agent{} skipped (source missing). Run 'ccagents sync' to download missing agents.",
+            "⚠".yellow(),
+            skipped,
+            if skipped == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(())
+}
+
+/// Removes the symlinks of configured agents from `.claude/agents` and
+/// recreates links (copies with `prefer_copy`) for enabled agents from the
+/// current project root. Symlinks ccagents does not manage are left alone.
+/// Never downloads anything.
+/// Returns the number of agents linked and skipped.
+fn run(project_root: &Path) -> Result<(usize, usize)> {
+    let config = AgentsConfig::load_required(project_root)?;
+    let claude_agents_dir = ensure_claude_agents_dir(project_root)?;
+
+    let link_names = config.link_names();
+    let scan = scan_link_dir(&claude_agents_dir, &link_names)?;
+    print_skipped_names(&scan.skipped);
+    for (key, path) in scan.entries {
+        if link_names.contains(&key) && path.is_symlink() {
+            remove_symlink(&path)?;
+        }
+    }
+
+    let mut linked = 0;
+    let mut skipped = 0;
+    for agent in config.enabled_agents() {
+        if !agent.condition_met().unwrap_or(false) {
+            continue;
+        }
+
+        let local_path = agent.get_local_path(project_root);
+        if !local_path.exists() {
+            println!(
+                "  {} {} - {}",
+                "✗".red(),
+                agent.name,
+                "source missing".red()
+            );
+            skipped += 1;
+            continue;
+        }
+
+        link_agent(
+            &local_path,
+            &agent.get_link_path(project_root),
+            config.link_mode(),
+            config.link_style,
+        )?;
+        println!("  {} {}", "→".cyan(), agent.name);
+        linked += 1;
+    }
+
+    Ok((linked, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSource};
+    use crate::linker::{create_symlink, is_symlink_valid, LinkStyle};
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_relink_after_project_move() {
+        let temp_dir = TempDir::new().unwrap();
+        let old_root = temp_dir.path().join("old");
+        let new_root = temp_dir.path().join("new");
+
+        fs::create_dir_all(old_root.join(".ccagents")).unwrap();
+        fs::write(old_root.join(".ccagents/agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig {
+            link_style: LinkStyle::Absolute,
+            ..Default::default()
+        };
+        config
+            .add_and_link(
+                Agent::new(
+                    "agent.md".to_string(),
+                    AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+                ),
+                &old_root,
+                true,
+            )
+            .unwrap();
+        assert!(is_symlink_valid(&old_root.join(".claude/agents/agent.md")));
+
+        // Moving the project leaves the absolute symlink dangling
+        fs::rename(&old_root, &new_root).unwrap();
+        let link_path = new_root.join(".claude/agents/agent.md");
+        assert!(!is_symlink_valid(&link_path));
+
+        let (linked, skipped) = run(&new_root).unwrap();
+
+        assert_eq!((linked, skipped), (1, 0));
+        assert!(is_symlink_valid(&link_path));
+        assert_eq!(
+            fs::read_link(&link_path).unwrap(),
+            new_root.join(".ccagents/agent.md")
+        );
+    }
+
+    #[test]
+    fn test_relink_keeps_unmanaged_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::create_dir_all(project_root.join(".ccagents")).unwrap();
+        fs::write(project_root.join(".ccagents/agent.md"), "# Agent").unwrap();
+        fs::write(project_root.join("mine.md"), "# Mine").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_and_link(
+                Agent::new(
+                    "agent.md".to_string(),
+                    AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+                ),
+                project_root,
+                true,
+            )
+            .unwrap();
+
+        let unmanaged = project_root.join(".claude/agents/mine.md");
+        create_symlink(&project_root.join("mine.md"), &unmanaged).unwrap();
+
+        let (linked, skipped) = run(project_root).unwrap();
+
+        assert_eq!((linked, skipped), (1, 0));
+        assert!(is_symlink_valid(
+            &project_root.join(".claude/agents/agent.md")
+        ));
+        assert!(is_symlink_valid(&unmanaged));
+    }
+}
@@ -1,142 +1,1251 @@
-use crate::agent::AgentSource;
-use crate::config::{
-    ensure_ccagents_dir, ensure_claude_agents_dir, get_project_root, AgentsConfig,
-};
-use crate::downloader::download_from_github;
-use crate::linker::{create_symlink, remove_symlink};
-use anyhow::Result;
+use crate::config::{ensure_managed_dir, get_project_root, AgentsConfig, MANIFEST_ENV};
+use crate::error::CcagentsError;
+use crate::reconcile::{self, AgentOutcome, SyncChanges};
+use anyhow::{Context, Result};
 use colored::*;
-use std::fs;
+use notify::{RecursiveMode, Watcher};
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
 
-pub fn execute(prune: bool) -> Result<()> {
-    let project_root = get_project_root()?;
-    let mut config = AgentsConfig::load(&project_root)?;
-
-    if config.agents.is_empty() {
-        println!("{}", "No agents configured in .agents.json".yellow());
-        println!("Use 'ccagents add <source>' to add agents");
-        return Ok(());
-    }
-
-    let claude_agents_dir = ensure_claude_agents_dir(&project_root)?;
-    let ccagents_dir = ensure_ccagents_dir(&project_root)?;
-
-    // Handle pruning if requested
-    if prune {
-        let mut orphaned_count = 0;
-
-        config.agents.retain(|agent| {
-            let local_path = agent.get_local_path(&project_root);
-            if !local_path.exists() {
-                orphaned_count += 1;
-                println!("  {} Pruning orphaned agent: {}", "✗".red(), agent.name);
-                // Also remove orphaned symlink if it exists
-                let link_path = agent.get_link_path(&project_root);
-                if link_path.exists() || link_path.is_symlink() {
-                    remove_symlink(&link_path).ok();
+/// How long `sync --watch` waits for changes to settle before syncing.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The human-readable change summary, one entry per line.
+fn change_lines(changes: &SyncChanges) -> Vec<String> {
+    let mut lines = vec![format!("\n{}", "Changes:".cyan().bold())];
+    if changes.is_empty() {
+        lines.push(format!("  {}", "nothing changed".dimmed()));
+    }
+
+    for name in &changes.downloaded {
+        lines.push(format!("  {} downloaded {}", "↓".cyan(), name));
+    }
+    for name in &changes.created {
+        lines.push(format!("  {} linked {}", "+".green(), name));
+    }
+    for name in &changes.repaired {
+        lines.push(format!("  {} repaired {}", "~".yellow(), name));
+    }
+    for name in &changes.removed {
+        lines.push(format!("  {} removed {}", "-".red(), name));
+    }
+    if !changes.unchanged.is_empty() {
+        lines.push(format!(
+            "  {}",
+            format!(
+                "{} agent{} unchanged",
+                changes.unchanged.len(),
+                if changes.unchanged.len() == 1 {
+                    ""
+                } else {
+                    "s"
                 }
-                false
-            } else {
-                true
+            )
+            .dimmed()
+        ));
+    }
+    lines
+}
+
+/// Flags of the `sync` command: those of the sync itself plus how its
+/// report is printed.
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// The sync itself; download progress follows `quiet_success`.
+    pub sync: reconcile::SyncOptions,
+    /// Print the change summary as JSON.
+    pub json: bool,
+    /// Print only warnings, failures and a one-line summary; the full
+    /// per-agent detail is printed only when something fails.
+    pub quiet_success: bool,
+}
+
+/// Routes sync output. With `quiet_success`, lines that only describe things
+/// going well are held back and printed only if an agent fails, so a clean
+/// run prints little more than its summary. With `json`, every line goes to
+/// stderr so stdout holds nothing but the JSON document.
+#[derive(Debug, Default)]
+struct SyncOutput {
+    quiet_success: bool,
+    json: bool,
+    held: Vec<String>,
+    /// Every line printed so far, in order.
+    #[cfg(test)]
+    printed: Vec<String>,
+    /// The part of `printed` that went to stdout.
+    #[cfg(test)]
+    stdout: Vec<String>,
+}
+
+impl SyncOutput {
+    fn new(options: &SyncOptions) -> Self {
+        Self {
+            quiet_success: options.quiet_success,
+            json: options.json,
+            ..Default::default()
+        }
+    }
+
+    /// A line describing normal progress.
+    fn detail(&mut self, line: impl Into<String>) {
+        if self.quiet_success {
+            self.held.push(line.into());
+        } else {
+            self.emit(line.into());
+        }
+    }
+
+    /// A warning, always printed.
+    fn warn(&mut self, line: impl Into<String>) {
+        self.emit(line.into());
+    }
+
+    /// A failure, printed after any held-back detail so it has full context.
+    fn fail(&mut self, line: impl Into<String>) {
+        for held in std::mem::take(&mut self.held) {
+            self.emit(held);
+        }
+        self.emit(line.into());
+    }
+
+    /// The JSON document, the only thing printed to stdout with `json`.
+    fn document(&mut self, document: String) {
+        println!("{}", document);
+        #[cfg(test)]
+        {
+            self.stdout.push(document.clone());
+            self.printed.push(document);
+        }
+    }
+
+    fn emit(&mut self, line: String) {
+        if self.json {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+            #[cfg(test)]
+            self.stdout.push(line.clone());
+        }
+        #[cfg(test)]
+        self.printed.push(line);
+    }
+}
+
+pub async fn execute(options: &SyncOptions) -> Result<()> {
+    let project_root = get_project_root()?;
+    let mut out = SyncOutput::new(options);
+    run(&project_root, options, &mut out).await?;
+    Ok(())
+}
+
+/// Syncs, then syncs again whenever `.agents.json` or a file in the managed
+/// directory changes, until Ctrl-C.
+pub async fn watch(options: &SyncOptions) -> Result<()> {
+    let project_root = get_project_root()?;
+    watch_until(&project_root, options, async {
+        tokio::signal::ctrl_c().await.ok();
+    })
+    .await?;
+
+    println!("\n{} Stopped watching", "✓".green().bold());
+    Ok(())
+}
+
+/// Runs the watch loop until `shutdown` completes and returns how many syncs
+/// ran. A failed sync is reported and watching continues.
+async fn watch_until(
+    project_root: &Path,
+    options: &SyncOptions,
+    shutdown: impl Future<Output = ()>,
+) -> Result<usize> {
+    let config = AgentsConfig::load(project_root)?;
+    let managed_dir = ensure_managed_dir(project_root, config.managed_dir())?.canonicalize()?;
+    let config_path = project_root.canonicalize()?.join(".agents.json");
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            tx.send(event).ok();
+        }
+    })
+    .context("Failed to start the file watcher")?;
+    watcher
+        .watch(&managed_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {:?}", managed_dir))?;
+    // The config is watched through its directory, since editors often
+    // replace the file rather than write to it. Links in .claude/agents are
+    // below that directory and therefore not seen
+    watcher
+        .watch(project_root, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {:?}", project_root))?;
+
+    let relevant = |event: &notify::Event| {
+        !event.kind.is_access()
+            && event
+                .paths
+                .iter()
+                .any(|path| path.starts_with(&managed_dir) || *path == config_path)
+    };
+
+    tokio::pin!(shutdown);
+    let mut syncs = 0;
+    loop {
+        let mut out = SyncOutput::new(options);
+        if let Err(e) = run(project_root, options, &mut out).await {
+            out.fail(format!("{} Sync failed: {}", "✗".red().bold(), e));
+        }
+        syncs += 1;
+
+        // Drop the events caused by the sync itself (downloads, a pruned config)
+        while rx.try_recv().is_ok() {}
+        out.warn(format!(
+            "\n{} Watching .agents.json and {} for changes (Ctrl-C to stop)",
+            "ℹ".blue(),
+            config.managed_dir().display()
+        ));
+
+        // Wait for a relevant change, then until changes have settled
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => return Ok(syncs),
+                event = rx.recv() => match event {
+                    Some(event) if relevant(&event) => break,
+                    Some(_) => {}
+                    None => return Ok(syncs),
+                },
             }
-        });
-
-        if orphaned_count > 0 {
-            config.save(&project_root)?;
-            println!(
-                "{} Pruned {} orphaned agent{}\n",
-                "→".yellow(),
-                orphaned_count,
-                if orphaned_count == 1 { "" } else { "s" }
-            );
         }
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => return Ok(syncs),
+                event = tokio::time::timeout(WATCH_DEBOUNCE, rx.recv()) => match event {
+                    Err(_) => break,
+                    Ok(Some(_)) => {}
+                    Ok(None) => return Ok(syncs),
+                },
+            }
+        }
+    }
+}
+
+/// Syncs through [`AgentsConfig::sync`] and prints its report.
+async fn run(
+    project_root: &Path,
+    sync_options: &SyncOptions,
+    out: &mut SyncOutput,
+) -> Result<SyncChanges> {
+    let options = reconcile::SyncOptions {
+        // Progress is printed to stdout, which is reserved for the JSON
+        progress: !sync_options.quiet_success && !sync_options.json,
+        ..sync_options.sync.clone()
+    };
+    let report = match AgentsConfig::sync(project_root, &options).await {
+        Ok(report) => report,
+        Err(e) => {
+            if let Some(CcagentsError::UnmanagedFiles(names)) = e.downcast_ref::<CcagentsError>() {
+                warn_unmanaged(out, names);
+            }
+            return Err(e);
+        }
+    };
+
+    if report.from_manifest {
+        out.detail(format!(
+            "{} Using agents from ${} ({:?}); .agents.json is not modified",
+            "ℹ".blue(),
+            MANIFEST_ENV,
+            options.manifest_merge
+        ));
+    }
+
+    if report.no_agents {
+        out.warn("No agents configured in .agents.json".yellow().to_string());
+        out.warn("Use 'ccagents add <source>' to add agents");
+        if sync_options.json {
+            out.document(serde_json::to_string_pretty(&report.changes)?);
+        }
+        return Ok(report.changes);
+    }
+
+    for name in &report.pruned {
+        out.detail(format!("  {} Pruning orphaned agent: {}", "✗".red(), name));
+    }
+    if !report.pruned.is_empty() && !report.from_manifest {
+        out.detail(format!(
+            "{} Pruned {} orphaned agent{}\n",
+            "→".yellow(),
+            report.pruned.len(),
+            if report.pruned.len() == 1 { "" } else { "s" }
+        ));
     }
 
-    println!("{}", "Syncing agents...".cyan().bold());
+    out.detail("Syncing agents...".cyan().bold().to_string());
 
-    // First, check for unmanaged files and remove symlinks
-    let mut unmanaged_files = Vec::new();
-    if claude_agents_dir.exists() {
-        for entry in fs::read_dir(&claude_agents_dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    if !report.unmanaged.is_empty() {
+        warn_unmanaged(out, &report.unmanaged);
+    }
 
-            if path.is_symlink() {
-                remove_symlink(&path).ok();
-            } else if path.is_file() {
-                // Regular file - not managed by ccagents
-                let name = path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
-                unmanaged_files.push(name);
+    let mut failures = report.failed.into_iter();
+    let mut collisions = Vec::new();
+    let mut missing = 0;
+    for (name, outcome) in &report.outcomes {
+        let prefix = format!("  {} {}", "→".cyan(), name);
+        match outcome {
+            AgentOutcome::Unchanged => out.detail(format!("{} - {}", prefix, "unchanged".dimmed())),
+            AgentOutcome::Linked => out.detail(format!("{} - {}", prefix, "enabled".green())),
+            AgentOutcome::ConditionNotMet => out.detail(format!(
+                "{} - {}",
+                prefix,
+                "condition not met, skipping".dimmed()
+            )),
+            AgentOutcome::InvalidCondition(e) => out.warn(format!(
+                "{} - {} ({})",
+                prefix,
+                "invalid condition, skipping".red(),
+                e
+            )),
+            AgentOutcome::Collision(owner) => {
+                out.warn(format!(
+                    "{} - {} (link collides with '{}', skipping)",
+                    prefix,
+                    "name collision".red(),
+                    owner
+                ));
+                collisions.push((owner, name));
+            }
+            AgentOutcome::SourceMissing => {
+                missing += 1;
+                out.warn(format!(
+                    "{} - {}",
+                    prefix,
+                    "source not found, skipping".red()
+                ))
             }
+            AgentOutcome::Failed(e) => {
+                out.fail(format!("{} - {} {}", prefix, "✗".red(), e));
+                // Without --keep-going the sync stopped here
+                if !options.keep_going {
+                    if let Some((_, e)) = failures.next() {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+    let failures: Vec<_> = failures.collect();
+
+    for (name, warning) in &report.warnings {
+        out.warn(format!("  {} {} - {}", "⚠".yellow(), name, warning));
+    }
+
+    if !report.disabled.is_empty() {
+        out.detail(format!("\n{}", "Disabled agents:".yellow()));
+        for name in &report.disabled {
+            out.detail(format!(
+                "  {} {} - {}",
+                "○".yellow(),
+                name,
+                "disabled".dimmed()
+            ));
         }
     }
 
-    // Warn about unmanaged files
-    if !unmanaged_files.is_empty() {
-        println!(
-            "\n{} Found {} unmanaged file{} in .claude/agents/:",
+    if !collisions.is_empty() {
+        out.warn(format!(
+            "\n{} {} agent{} skipped because of link name collisions:",
             "⚠".yellow().bold(),
-            unmanaged_files.len(),
-            if unmanaged_files.len() == 1 { "" } else { "s" }
+            collisions.len(),
+            if collisions.len() == 1 { "" } else { "s" }
+        ));
+        for (owner, skipped) in &collisions {
+            out.warn(format!(
+                "  {} '{}' collides with '{}' (linked)",
+                "◆".yellow(),
+                skipped,
+                owner
+            ));
+        }
+        out.warn(format!(
+            "  {} Rename one of the agents in .agents.json to link both",
+            "→".cyan()
+        ));
+    }
+
+    if options.link_only && missing > 0 {
+        out.warn(format!(
+            "  {} --link-only downloads nothing; run 'ccagents update --only-missing' to fetch missing GitHub agents",
+            "→".cyan()
+        ));
+    }
+
+    let changes = report.changes;
+    if sync_options.json {
+        out.document(serde_json::to_string_pretty(&changes)?);
+    } else if !sync_options.quiet_success || !failures.is_empty() {
+        for line in change_lines(&changes) {
+            out.detail(line);
+        }
+    }
+
+    if !failures.is_empty() {
+        out.fail(format!(
+            "\n{} {} agent{} failed to sync:",
+            "✗".red().bold(),
+            failures.len(),
+            if failures.len() == 1 { "" } else { "s" }
+        ));
+        for (name, e) in &failures {
+            out.fail(format!("  {} {} - {}", "✗".red(), name, e));
+        }
+        return Err(anyhow::anyhow!(
+            "Sync finished with {} failed agent{}",
+            failures.len(),
+            if failures.len() == 1 { "" } else { "s" }
+        ));
+    }
+
+    if sync_options.quiet_success {
+        out.warn(format!(
+            "{} Sync complete: {} created, {} repaired, {} removed, {} downloaded, {} unchanged",
+            "✓".green().bold(),
+            changes.created.len(),
+            changes.repaired.len(),
+            changes.removed.len(),
+            changes.downloaded.len(),
+            changes.unchanged.len()
+        ));
+    } else {
+        out.detail(format!("\n{} Sync complete!", "✓".green().bold()));
+    }
+
+    Ok(changes)
+}
+
+/// Warns about regular files in `.claude/agents` that no agent accounts for.
+fn warn_unmanaged(out: &mut SyncOutput, names: &[String]) {
+    out.warn(format!(
+        "\n{} Found {} unmanaged file{} in .claude/agents/:",
+        "⚠".yellow().bold(),
+        names.len(),
+        if names.len() == 1 { "" } else { "s" }
+    ));
+
+    for name in names {
+        out.warn(format!("  {} {}", "◆".blue(), name));
+    }
+
+    out.warn(format!(
+        "\n  {} Run 'ccagents import' to convert these to managed agents\n",
+        "→".cyan()
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentSource};
+    use crate::config::{ensure_ccagents_dir, ensure_claude_agents_dir};
+    use crate::linker::{create_agent_symlink, create_symlink, LinkMode, LinkStyle};
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// A project whose `broken.md` download fails with a 404 from the mock
+    /// server, next to an already downloaded `present.md`.
+    async fn project_with_failing_download(server: &mut mockito::Server) -> TempDir {
+        server
+            .mock("GET", "/user/repo/raw/main/broken.md")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+
+        let mut config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        config
+            .add_agent(Agent::new(
+                "broken.md".to_string(),
+                AgentSource::GitHub(format!("{}/user/repo/blob/main/broken.md", server.url())),
+            ))
+            .unwrap();
+        config
+            .add_agent(Agent::new(
+                "present.md".to_string(),
+                AgentSource::GitHub(format!("{}/user/repo/blob/main/present.md", server.url())),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("present.md"), "# Present").unwrap();
+
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn test_sync_fails_fast_by_default() {
+        let mut server = mockito::Server::new_async().await;
+        let temp_dir = project_with_failing_download(&mut server).await;
+        let project_root = temp_dir.path();
+
+        let err = run(
+            project_root,
+            &SyncOptions::default(),
+            &mut SyncOutput::default(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            crate::error::exit_code_for(&err),
+            crate::error::exit_code::NETWORK
+        );
+
+        let link_path = project_root.join(".claude/agents/present.md");
+        assert!(!link_path.is_symlink());
+    }
+
+    #[tokio::test]
+    async fn test_sync_keep_going_continues_after_failure() {
+        let mut server = mockito::Server::new_async().await;
+        let temp_dir = project_with_failing_download(&mut server).await;
+        let project_root = temp_dir.path();
+
+        let result = run(
+            project_root,
+            &SyncOptions {
+                sync: reconcile::SyncOptions {
+                    keep_going: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            &mut SyncOutput::default(),
+        )
+        .await;
+        assert!(result.unwrap_err().to_string().contains("1 failed agent"));
+
+        let link_path = project_root.join(".claude/agents/present.md");
+        assert!(link_path.is_symlink());
+        assert!(!project_root.join(".claude/agents/broken.md").exists());
+    }
+
+    #[tokio::test]
+    async fn test_sync_skips_colliding_link_names() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+
+        let mut config = AgentsConfig::default();
+        for (name, file) in [("Review.md", "first.md"), ("review.md", "second.md")] {
+            fs::write(ccagents_dir.join(file), "# Agent").unwrap();
+            config.agents.push(Agent::new(
+                name.to_string(),
+                AgentSource::Local(ccagents_dir.join(file)),
+            ));
+        }
+        config.save(project_root).unwrap();
+
+        let changes = run(
+            project_root,
+            &SyncOptions::default(),
+            &mut SyncOutput::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(changes.created, vec!["Review.md"]);
+        let link_path = project_root.join(".claude/agents/Review.md");
+        assert_eq!(
+            fs::read_link(link_path).unwrap(),
+            Path::new("../../.ccagents/first.md")
         );
+        assert!(!project_root.join(".claude/agents/review.md").is_symlink());
+    }
+
+    #[tokio::test]
+    async fn test_sync_reports_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
 
-        for name in &unmanaged_files {
-            println!("  {} {}", "◆".blue(), name);
+        let mut config = AgentsConfig::default();
+        for name in ["new.md", "kept.md"] {
+            fs::write(ccagents_dir.join(name), "# Agent").unwrap();
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(ccagents_dir.join(name)),
+                ))
+                .unwrap();
         }
+        config.save(project_root).unwrap();
 
-        println!(
-            "\n  {} Run 'ccagents import' to convert these to managed agents",
-            "→".cyan()
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        create_agent_symlink(
+            &ccagents_dir.join("kept.md"),
+            &claude_agents_dir.join("kept.md"),
+            LinkStyle::Relative,
+        )
+        .unwrap();
+        create_symlink(
+            &ccagents_dir.join("gone.md"),
+            &claude_agents_dir.join("stale.md"),
+        )
+        .unwrap();
+
+        let changes = run(
+            project_root,
+            &SyncOptions::default(),
+            &mut SyncOutput::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(changes.created, vec!["new.md"]);
+        assert!(changes.repaired.is_empty());
+        assert_eq!(changes.removed, vec!["stale.md"]);
+        assert!(changes.downloaded.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_link_only_never_downloads() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/remote.md")
+            .with_body("# Remote")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("local.md"), "# Local").unwrap();
+
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+
+        let mut config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        config
+            .add_agent(Agent::new(
+                "remote.md".to_string(),
+                AgentSource::GitHub(format!("{}/org/repo/blob/main/remote.md", server.url())),
+            ))
+            .unwrap();
+        config
+            .add_agent(Agent::new(
+                "local.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("local.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let options = SyncOptions {
+            sync: reconcile::SyncOptions {
+                link_only: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut out = SyncOutput::default();
+        let changes = run(project_root, &options, &mut out).await.unwrap();
+
+        mock.assert_async().await;
+        assert!(changes.downloaded.is_empty());
+        assert_eq!(changes.created, vec!["local.md"]);
+        assert!(!ccagents_dir.join("remote.md").exists());
+        assert!(out
+            .printed
+            .iter()
+            .any(|line| line.contains("remote.md") && line.contains("skipping")));
+    }
+
+    #[tokio::test]
+    async fn test_sync_only_affects_selected_agents() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+
+        let mut config = AgentsConfig::default();
+        for name in ["foo.md", "bar.md", "baz.md"] {
+            fs::write(ccagents_dir.join(name), "# Agent").unwrap();
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(ccagents_dir.join(name)),
+                ))
+                .unwrap();
+        }
+        config.agents[2].tags = vec!["review".to_string()];
+        config.save(project_root).unwrap();
+
+        // A stale link of bar.md and an orphaned link outside the selection
+        create_symlink(
+            &ccagents_dir.join("gone.md"),
+            &claude_agents_dir.join("bar.md"),
+        )
+        .unwrap();
+        create_symlink(
+            &ccagents_dir.join("gone.md"),
+            &claude_agents_dir.join("orphan.md"),
+        )
+        .unwrap();
+
+        let sync = |only: &[&str], tags: &[&str]| {
+            let options = SyncOptions {
+                sync: reconcile::SyncOptions {
+                    only: only.iter().map(|s| s.to_string()).collect(),
+                    tags: tags.iter().map(|s| s.to_string()).collect(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            async move { run(project_root, &options, &mut SyncOutput::default()).await }
+        };
+
+        let changes = sync(&["foo.md"], &[]).await.unwrap();
+        assert_eq!(changes.created, vec!["foo.md"]);
+        assert!(changes.removed.is_empty());
+        assert!(claude_agents_dir.join("foo.md").exists());
+        assert_eq!(
+            fs::read_link(claude_agents_dir.join("bar.md")).unwrap(),
+            ccagents_dir.join("gone.md")
         );
-        println!();
+        assert!(claude_agents_dir.join("orphan.md").is_symlink());
+        assert!(!claude_agents_dir.join("baz.md").exists());
+
+        let changes = sync(&[], &["review"]).await.unwrap();
+        assert_eq!(changes.created, vec!["baz.md"]);
+        assert!(claude_agents_dir.join("orphan.md").is_symlink());
+
+        assert!(sync(&["missing.md"], &[]).await.is_err());
+        assert!(sync(&[], &["nothing"]).await.is_err());
     }
 
-    // Sync enabled agents
-    for agent in config.enabled_agents() {
-        print!("  {} {}", "→".cyan(), agent.name);
+    #[tokio::test]
+    async fn test_sync_links_under_link_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
 
-        let local_path = agent.get_local_path(&project_root);
-        let link_path = agent.get_link_path(&project_root);
+        let mut config = AgentsConfig::default();
+        for name in ["backend-developer.md", "frontend.md"] {
+            fs::write(ccagents_dir.join(name), "# Agent").unwrap();
+            config
+                .add_agent(Agent::new(
+                    name.to_string(),
+                    AgentSource::Local(ccagents_dir.join(name)),
+                ))
+                .unwrap();
+        }
+        config.agents[0].link_name = Some("backend.md".to_string());
+        config.save(project_root).unwrap();
 
-        // Ensure the source exists
-        if !local_path.exists() {
-            match &agent.source {
-                AgentSource::GitHub(url) => {
-                    println!(" - {}", "downloading from GitHub...".yellow());
-                    tokio::runtime::Runtime::new()?
-                        .block_on(async { download_from_github(url, &ccagents_dir).await })?;
-                }
-                AgentSource::Local(_) => {
-                    println!(" - {}", "source not found, skipping".red());
-                    continue;
+        let sync = || async {
+            run(
+                project_root,
+                &SyncOptions::default(),
+                &mut SyncOutput::default(),
+            )
+            .await
+            .unwrap()
+        };
+
+        let changes = sync().await;
+        assert_eq!(changes.created, vec!["backend-developer.md", "frontend.md"]);
+        let claude_agents_dir = project_root.join(".claude/agents");
+        assert_eq!(
+            fs::read_link(claude_agents_dir.join("backend.md")).unwrap(),
+            Path::new("../../.ccagents/backend-developer.md")
+        );
+        assert!(!claude_agents_dir.join("backend-developer.md").exists());
+        assert!(claude_agents_dir.join("frontend.md").is_symlink());
+
+        // The renamed link is recognized as current on the next run
+        let changes = sync().await;
+        assert!(changes.created.is_empty());
+        assert!(changes.repaired.is_empty());
+        assert!(changes.removed.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_second_sync_performs_no_mutations() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("agent.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let claude_agents_dir = project_root.join(".claude/agents");
+        let link_path = claude_agents_dir.join("agent.md");
+        // Inode and change time of the link and of its directory; recreating
+        // or rewriting the link changes at least one of them
+        let snapshot = || {
+            let link = fs::symlink_metadata(&link_path).unwrap();
+            let dir = fs::metadata(&claude_agents_dir).unwrap();
+            (
+                link.ino(),
+                link.ctime(),
+                link.ctime_nsec(),
+                dir.mtime(),
+                dir.mtime_nsec(),
+            )
+        };
+
+        for link_mode in [LinkMode::Symlink, LinkMode::Copy] {
+            let options = SyncOptions {
+                sync: reconcile::SyncOptions {
+                    link_mode: Some(link_mode),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            run(project_root, &options, &mut SyncOutput::default())
+                .await
+                .unwrap();
+            let before = snapshot();
+
+            let changes = run(project_root, &options, &mut SyncOutput::default())
+                .await
+                .unwrap();
+            assert!(changes.is_empty());
+            assert_eq!(changes.unchanged, vec!["agent.md"]);
+            assert_eq!(snapshot(), before, "{:?} sync touched the link", link_mode);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_rewrites_links_when_link_style_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("agent.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let sync = || async {
+            run(
+                project_root,
+                &SyncOptions::default(),
+                &mut SyncOutput::default(),
+            )
+            .await
+            .unwrap()
+        };
+        let link_path = project_root.join(".claude/agents/agent.md");
+
+        let changes = sync().await;
+        assert_eq!(changes.created, vec!["agent.md"]);
+        assert_eq!(
+            fs::read_link(&link_path).unwrap(),
+            Path::new("../../.ccagents/agent.md")
+        );
+
+        config.link_style = LinkStyle::Absolute;
+        config.save(project_root).unwrap();
+        let changes = sync().await;
+        assert_eq!(changes.repaired, vec!["agent.md"]);
+        assert_eq!(
+            fs::read_link(&link_path).unwrap(),
+            ccagents_dir.join("agent.md")
+        );
+
+        let changes = sync().await;
+        assert_eq!(changes.unchanged, vec!["agent.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_watch_relinks_on_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().canonicalize().unwrap();
+        let ccagents_dir = ensure_ccagents_dir(&project_root).unwrap();
+        fs::write(ccagents_dir.join("first.md"), "# First").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "first.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("first.md")),
+            ))
+            .unwrap();
+        config.save(&project_root).unwrap();
+
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let link_path = project_root.join(".claude/agents/second.md");
+        let edit = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            fs::write(ccagents_dir.join("second.md"), "# Second").unwrap();
+            config
+                .add_agent(Agent::new(
+                    "second.md".to_string(),
+                    AgentSource::Local(ccagents_dir.join("second.md")),
+                ))
+                .unwrap();
+            config.save(&project_root).unwrap();
+
+            for _ in 0..50 {
+                if link_path.is_symlink() {
+                    break;
                 }
+                tokio::time::sleep(Duration::from_millis(100)).await;
             }
+            stop.send(()).unwrap();
+        };
+
+        let options = SyncOptions::default();
+        let shutdown = async {
+            stopped.await.ok();
+        };
+        let (syncs, ()) = tokio::join!(watch_until(&project_root, &options, shutdown), edit);
+
+        assert!(project_root.join(".claude/agents/first.md").is_symlink());
+        assert_eq!(
+            fs::read_link(&link_path).unwrap(),
+            Path::new("../../.ccagents/second.md")
+        );
+        assert!(syncs.unwrap() >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_sync_links_into_subdir() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("api.md"), "# API").unwrap();
+
+        let mut config = AgentsConfig::default();
+        let mut agent = Agent::new(
+            "api.md".to_string(),
+            AgentSource::Local(ccagents_dir.join("api.md")),
+        );
+        agent.subdir = Some("backend".to_string());
+        config.add_agent(agent).unwrap();
+        config.save(project_root).unwrap();
+
+        let sync = || async {
+            run(
+                project_root,
+                &SyncOptions::default(),
+                &mut SyncOutput::default(),
+            )
+            .await
+            .unwrap()
+        };
+
+        let changes = sync().await;
+        assert_eq!(changes.created, vec!["api.md"]);
+        let link_path = project_root.join(".claude/agents/backend/api.md");
+        assert_eq!(
+            fs::read_link(&link_path).unwrap(),
+            Path::new("../../../.ccagents/api.md")
+        );
+        assert!(!project_root.join(".claude/agents/api.md").exists());
+
+        // The link in the subdir is found on the next run; disabling removes it
+        let changes = sync().await;
+        assert!(changes.created.is_empty() && changes.repaired.is_empty());
+
+        config.agents[0].enabled = false;
+        config.save(project_root).unwrap();
+        let changes = sync().await;
+        assert_eq!(changes.removed, vec!["backend/api.md"]);
+        assert!(!link_path.is_symlink());
+    }
+
+    #[tokio::test]
+    async fn test_sync_fail_on_unmanaged() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("agent.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+        fs::write(claude_agents_dir.join("rogue.md"), "# Rogue").unwrap();
+
+        // By default the unmanaged file is only a warning
+        run(
+            project_root,
+            &SyncOptions::default(),
+            &mut SyncOutput::default(),
+        )
+        .await
+        .unwrap();
+        let link_path = claude_agents_dir.join("agent.md");
+        assert!(link_path.is_symlink());
+
+        let options = SyncOptions {
+            sync: reconcile::SyncOptions {
+                fail_on_unmanaged: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let err = run(project_root, &options, &mut SyncOutput::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("rogue.md"));
+        assert_eq!(
+            crate::error::exit_code_for(&err),
+            crate::error::exit_code::ISSUES_FOUND
+        );
+        // Existing links are left alone
+        assert!(link_path.is_symlink());
+    }
+
+    fn quiet_success() -> SyncOptions {
+        SyncOptions {
+            sync: reconcile::SyncOptions {
+                keep_going: true,
+                ..Default::default()
+            },
+            quiet_success: true,
+            ..Default::default()
         }
+    }
+
+    #[tokio::test]
+    async fn test_sync_quiet_success_prints_only_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
 
-        // Create symlink
-        create_symlink(&local_path, &link_path)?;
-        println!(" - {}", "enabled".green());
+        fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("agent.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let mut out = SyncOutput::new(&quiet_success());
+        run(project_root, &quiet_success(), &mut out).await.unwrap();
+
+        assert_eq!(out.printed.len(), 1);
+        assert!(out.printed[0].contains("Sync complete: 1 created"));
+        assert!(project_root.join(".claude/agents/agent.md").is_symlink());
     }
 
-    // Report disabled agents
-    let disabled = config.disabled_agents();
-    if !disabled.is_empty() {
-        println!("\n{}", "Disabled agents:".yellow());
-        for agent in disabled {
-            println!(
-                "  {} {} - {}",
-                "○".yellow(),
-                agent.name,
-                "disabled".dimmed()
+    #[tokio::test]
+    async fn test_sync_json_keeps_stdout_parseable() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("agent.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let options = SyncOptions {
+            json: true,
+            ..Default::default()
+        };
+        let mut out = SyncOutput::new(&options);
+        run(project_root, &options, &mut out).await.unwrap();
+
+        let stdout: serde_json::Value = serde_json::from_str(&out.stdout.join("\n")).unwrap();
+        assert_eq!(stdout["created"], serde_json::json!(["agent.md"]));
+        // The human-readable lines went to stderr
+        assert!(out
+            .printed
+            .iter()
+            .any(|line| line.contains("Syncing agents...")));
+    }
+
+    #[tokio::test]
+    async fn test_sync_quiet_success_prints_detail_on_failure() {
+        let mut server = mockito::Server::new_async().await;
+        let temp_dir = project_with_failing_download(&mut server).await;
+        let project_root = temp_dir.path();
+
+        let mut out = SyncOutput::new(&quiet_success());
+        assert!(run(project_root, &quiet_success(), &mut out).await.is_err());
+
+        let output = out.printed.join("\n");
+        assert!(output.contains("Syncing agents..."));
+        assert!(output.contains("broken.md"));
+        assert!(output.contains("present.md - "));
+        assert!(output.contains("failed to sync"));
+        assert!(!output.contains("Sync complete"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_copy_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::Local(ccagents_dir.join("agent.md")),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let options = SyncOptions {
+            sync: reconcile::SyncOptions {
+                link_mode: Some(LinkMode::Copy),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let copy_path = project_root.join(".claude/agents/agent.md");
+
+        let changes = run(project_root, &options, &mut SyncOutput::default())
+            .await
+            .unwrap();
+        assert_eq!(changes.created, vec!["agent.md"]);
+        assert!(copy_path.is_file() && !copy_path.is_symlink());
+
+        // An up-to-date copy is left alone, a drifted one is refreshed
+        let changes = run(project_root, &options, &mut SyncOutput::default())
+            .await
+            .unwrap();
+        assert!(changes.is_empty());
+        fs::write(&copy_path, "# Edited").unwrap();
+        let changes = run(project_root, &options, &mut SyncOutput::default())
+            .await
+            .unwrap();
+        assert_eq!(changes.repaired, vec!["agent.md"]);
+        assert_eq!(fs::read_to_string(&copy_path).unwrap(), "# Agent");
+
+        // Disabled agents lose their copy
+        config.agents[0].enabled = false;
+        config.save(project_root).unwrap();
+        let changes = run(project_root, &options, &mut SyncOutput::default())
+            .await
+            .unwrap();
+        assert_eq!(changes.removed, vec!["agent.md"]);
+        assert!(!copy_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_sync_copy_mode_keeps_copy_of_collision_winner() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+
+        // The loser of a collision and a disabled agent both share the
+        // winner's link path
+        let mut config = AgentsConfig::default();
+        for name in ["winner.md", "loser.md", "disabled.md"] {
+            fs::write(ccagents_dir.join(name), format!("# {}", name)).unwrap();
+            let mut agent = Agent::new(
+                name.to_string(),
+                AgentSource::Local(ccagents_dir.join(name)),
             );
+            agent.link_name = Some("shared.md".to_string());
+            agent.enabled = name != "disabled.md";
+            config.agents.push(agent);
+        }
+        config.save(project_root).unwrap();
+
+        let options = SyncOptions {
+            sync: reconcile::SyncOptions {
+                link_mode: Some(LinkMode::Copy),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let copy_path = project_root.join(".claude/agents/shared.md");
+        for _ in 0..2 {
+            let changes = run(project_root, &options, &mut SyncOutput::default())
+                .await
+                .unwrap();
+            assert!(changes.removed.is_empty());
+            assert_eq!(fs::read_to_string(&copy_path).unwrap(), "# winner.md");
         }
     }
 
-    println!("\n{} Sync complete!", "✓".green().bold());
+    #[tokio::test]
+    async fn test_sync_drops_files_removed_from_directory_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        let source = ccagents_dir.join("team");
+        fs::create_dir_all(&source).unwrap();
+        for name in ["a.md", "b.md", "c.md"] {
+            fs::write(source.join(name), format!("# {}", name)).unwrap();
+        }
 
-    Ok(())
+        let mut config = AgentsConfig::default();
+        config
+            .add_agent(Agent::new(
+                "team".to_string(),
+                AgentSource::Local(source.clone()),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let linked = project_root.join(".claude/agents/team");
+        for link_mode in [LinkMode::Symlink, LinkMode::Copy] {
+            let options = SyncOptions {
+                sync: reconcile::SyncOptions {
+                    link_mode: Some(link_mode),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            fs::write(source.join("b.md"), "# b.md").unwrap();
+            run(project_root, &options, &mut SyncOutput::default())
+                .await
+                .unwrap();
+            assert!(linked.join("b.md").exists());
+
+            fs::remove_file(source.join("b.md")).unwrap();
+            run(project_root, &options, &mut SyncOutput::default())
+                .await
+                .unwrap();
+            assert!(!linked.join("b.md").exists(), "{:?}", link_mode);
+            assert!(linked.join("a.md").exists() && linked.join("c.md").exists());
+        }
+    }
 }
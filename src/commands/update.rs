@@ -0,0 +1,532 @@
+use crate::agent::{Agent, AgentSource};
+use crate::commands::print_download_warnings;
+use crate::config::{get_project_root, AgentsConfig};
+use crate::downloader::{check_github_file, download_agent, DownloadOptions, UpstreamStatus};
+use crate::error::CcagentsError;
+use crate::linker::{content_digest, remove_symlink};
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// What an update run did, by agent name.
+#[derive(Debug, Default)]
+struct UpdateReport {
+    updated: Vec<String>,
+    /// Agents whose upstream file is gone; the local copy was kept.
+    kept: Vec<String>,
+    /// Agents whose upstream file is gone and that were removed.
+    removed: Vec<String>,
+    /// Agents skipped by `only_missing` because their file is on disk.
+    present: Vec<String>,
+}
+
+pub async fn execute(
+    name: Option<String>,
+    remove_gone: bool,
+    check: bool,
+    only_missing: bool,
+    all: bool,
+) -> Result<()> {
+    let project_root = get_project_root()?;
+
+    if check {
+        return check_only(&project_root, name.as_deref()).await;
+    }
+
+    let confirm_removal = |agent: &Agent| {
+        if remove_gone {
+            return Ok(true);
+        }
+
+        print!("    Remove the local copy of '{}'? [y/N]: ", agent.name);
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+    };
+
+    let report = if all {
+        println!("{}", "Checking GitHub agents for updates...".cyan().bold());
+        run_stale(&project_root, confirm_removal).await?
+    } else {
+        println!("{}", "Updating GitHub agents...".cyan().bold());
+        run(
+            &project_root,
+            name.as_deref(),
+            only_missing,
+            confirm_removal,
+        )
+        .await?
+    };
+
+    println!(
+        "\n{} Updated {} agent{}",
+        "✓".green().bold(),
+        report.updated.len(),
+        if report.updated.len() == 1 { "" } else { "s" }
+    );
+    if !report.present.is_empty() {
+        println!(
+            "  {} Already present, not downloaded: {}",
+            "ℹ".blue(),
+            report.present.len()
+        );
+    }
+    if !report.kept.is_empty() {
+        println!(
+            "  {} Upstream gone, local copy kept: {}",
+            "⚠".yellow(),
+            report.kept.join(", ")
+        );
+    }
+    if !report.removed.is_empty() {
+        println!(
+            "  {} Upstream gone, removed: {}",
+            "→".cyan(),
+            report.removed.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints which GitHub agents have upstream changes without downloading.
+async fn check_only(project_root: &Path, name: Option<&str>) -> Result<()> {
+    println!("{}", "Checking GitHub agents for updates...".cyan().bold());
+
+    let statuses = check(project_root, name).await?;
+    for (name, status) in &statuses {
+        match status {
+            UpstreamStatus::Current => {
+                println!("  {} {} - {}", "✓".green(), name, "up to date".dimmed())
+            }
+            UpstreamStatus::Changed => {
+                println!("  {} {} - {}", "↑".cyan(), name, "update available".cyan())
+            }
+            UpstreamStatus::Gone => println!(
+                "  {} {} - {}",
+                "⚠".yellow(),
+                name,
+                "upstream file no longer exists".yellow()
+            ),
+        }
+    }
+
+    let available = statuses
+        .iter()
+        .filter(|(_, status)| *status == UpstreamStatus::Changed)
+        .count();
+    if available == 0 {
+        println!("\n{} All agents are up to date", "✓".green().bold());
+    } else {
+        println!(
+            "\n{} {} agent{} {} updates available. Run 'ccagents update --all' to apply them",
+            "→".cyan().bold(),
+            available,
+            if available == 1 { "" } else { "s" },
+            if available == 1 { "has" } else { "have" }
+        );
+    }
+
+    Ok(())
+}
+
+/// Compares the GitHub agent `name`, or every GitHub agent, with its
+/// upstream file. Nothing is downloaded to disk.
+async fn check(project_root: &Path, name: Option<&str>) -> Result<Vec<(String, UpstreamStatus)>> {
+    let config = AgentsConfig::load_required(project_root)?;
+    let options = DownloadOptions::from_config(&config);
+
+    let mut statuses = Vec::new();
+    for name in agent_names(&config, name)? {
+        let Some(agent) = config.get_agent(&name) else {
+            continue;
+        };
+        let AgentSource::GitHub(url) = &agent.source else {
+            println!(
+                "  {} {} - {}",
+                "ℹ".blue(),
+                name,
+                "local source, nothing to update".dimmed()
+            );
+            continue;
+        };
+
+        let options = DownloadOptions {
+            headers: agent.headers.clone(),
+            ..options.clone()
+        };
+        let status = check_github_file(url, &agent.get_local_path(project_root), &options)
+            .await
+            .with_context(|| format!("Failed to check '{}'", name))?;
+        statuses.push((name, status));
+    }
+
+    Ok(statuses)
+}
+
+/// The agent `name`, or every GitHub agent.
+fn agent_names(config: &AgentsConfig, name: Option<&str>) -> Result<Vec<String>> {
+    Ok(match name {
+        Some(name) => vec![config.require_agent(name)?.name.clone()],
+        None => config
+            .agents
+            .iter()
+            .filter(|a| matches!(a.source, AgentSource::GitHub(_)))
+            .map(|a| a.name.clone())
+            .collect(),
+    })
+}
+
+/// Re-downloads the GitHub agent `name`, or every GitHub agent; with
+/// `only_missing`, only those whose file is not on disk, leaving links
+/// untouched. When a file no longer exists upstream, `remove_gone` decides
+/// whether the agent and its local copy are removed; otherwise the copy is
+/// kept and the agent flagged for `doctor`. Network errors abort the run.
+async fn run(
+    project_root: &Path,
+    name: Option<&str>,
+    only_missing: bool,
+    remove_gone: impl FnMut(&Agent) -> Result<bool>,
+) -> Result<UpdateReport> {
+    let names = agent_names(&AgentsConfig::load_required(project_root)?, name)?;
+    update_names(project_root, names, only_missing, remove_gone).await
+}
+
+/// Applies what [`check`] reports: re-downloads only the GitHub agents whose
+/// upstream file changed or is gone, leaving current ones untouched.
+async fn run_stale(
+    project_root: &Path,
+    remove_gone: impl FnMut(&Agent) -> Result<bool>,
+) -> Result<UpdateReport> {
+    let stale = check(project_root, None)
+        .await?
+        .into_iter()
+        .filter(|(_, status)| *status != UpstreamStatus::Current)
+        .map(|(name, _)| name)
+        .collect();
+    update_names(project_root, stale, false, remove_gone).await
+}
+
+/// Re-downloads the agents `names`; see [`run`].
+async fn update_names(
+    project_root: &Path,
+    names: Vec<String>,
+    only_missing: bool,
+    mut remove_gone: impl FnMut(&Agent) -> Result<bool>,
+) -> Result<UpdateReport> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+
+    // Filling in missing files may use the cache; a full update revalidates
+    let options = DownloadOptions {
+        refresh: !only_missing,
+        progress: true,
+        ..DownloadOptions::from_config(&config)
+    };
+    let mut report = UpdateReport::default();
+
+    for name in names {
+        let Some(agent) = config.get_agent(&name).cloned() else {
+            continue;
+        };
+        if !matches!(agent.source, AgentSource::GitHub(_)) {
+            println!(
+                "  {} {} - {}",
+                "ℹ".blue(),
+                name,
+                "local source, nothing to update".dimmed()
+            );
+            continue;
+        }
+        if only_missing && agent.get_local_path(project_root).exists() {
+            println!(
+                "  {} {} - {}",
+                "✓".green(),
+                name,
+                "already present".dimmed()
+            );
+            report.present.push(name);
+            continue;
+        }
+
+        match download_agent(&agent, project_root, &options).await {
+            Ok(warnings) => {
+                print_download_warnings(&warnings);
+                let sha256 = content_digest(&agent.get_local_path(project_root)).ok();
+                if let Some(agent) = config.get_agent_mut(&name) {
+                    agent.upstream_missing = false;
+                    agent.sha256 = sha256;
+                }
+                println!("  {} {} - {}", "✓".green(), name, "updated".green());
+                report.updated.push(name);
+            }
+            Err(e) if is_upstream_gone(&e) => {
+                println!(
+                    "  {} {} - {}",
+                    "⚠".yellow(),
+                    name,
+                    "upstream file no longer exists".yellow()
+                );
+
+                let local_path = agent.get_local_path(project_root);
+                if local_path.exists() && remove_gone(&agent)? {
+                    fs::remove_file(&local_path)
+                        .with_context(|| format!("Failed to remove {:?}", local_path))?;
+                    let link_path = agent.get_link_path(project_root);
+                    if link_path.is_symlink() {
+                        remove_symlink(&link_path)?;
+                    }
+                    config.remove_agent(&name)?;
+                    report.removed.push(name);
+                } else {
+                    if let Some(agent) = config.get_agent_mut(&name) {
+                        agent.upstream_missing = true;
+                    }
+                    report.kept.push(name);
+                }
+            }
+            Err(e) => {
+                config.save(project_root)?;
+                return Err(e.context(format!("Failed to update '{}'", name)));
+            }
+        }
+    }
+
+    config.save(project_root)?;
+
+    Ok(report)
+}
+
+fn is_upstream_gone(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| {
+        matches!(
+            e.downcast_ref::<CcagentsError>(),
+            Some(CcagentsError::UpstreamNotFound(_))
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ensure_ccagents_dir;
+    use tempfile::TempDir;
+
+    /// A project with a downloaded GitHub agent served by a mock server.
+    fn project_with_github_agent(server: &mockito::Server) -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+
+        let mut config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        config
+            .add_agent(Agent::new(
+                "agent.md".to_string(),
+                AgentSource::GitHub(format!("{}/org/repo/blob/main/agent.md", server.url())),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("agent.md"), "# Old").unwrap();
+
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_stale_and_current_agents() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_body("# New")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/org/repo/raw/main/current.md")
+            .with_body("# Current")
+            .create_async()
+            .await;
+        let temp_dir = project_with_github_agent(&server);
+        let project_root = temp_dir.path();
+
+        let mut config = AgentsConfig::load(project_root).unwrap();
+        config
+            .add_agent(Agent::new(
+                "current.md".to_string(),
+                AgentSource::GitHub(format!("{}/org/repo/blob/main/current.md", server.url())),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+        fs::write(project_root.join(".ccagents/current.md"), "# Current").unwrap();
+
+        let statuses = check(project_root, None).await.unwrap();
+        assert_eq!(
+            statuses,
+            vec![
+                ("agent.md".to_string(), UpstreamStatus::Changed),
+                ("current.md".to_string(), UpstreamStatus::Current),
+            ]
+        );
+        // Nothing was downloaded
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/agent.md")).unwrap(),
+            "# Old"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_all_downloads_only_stale_agents() {
+        let mut server = mockito::Server::new_async().await;
+        let stale = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_body("# New")
+            .expect(2)
+            .create_async()
+            .await;
+        // Fetched by the check only
+        let current = server
+            .mock("GET", "/org/repo/raw/main/current.md")
+            .with_body("# Current")
+            .expect(1)
+            .create_async()
+            .await;
+        let temp_dir = project_with_github_agent(&server);
+        let project_root = temp_dir.path();
+
+        let mut config = AgentsConfig::load(project_root).unwrap();
+        config
+            .add_agent(Agent::new(
+                "current.md".to_string(),
+                AgentSource::GitHub(format!("{}/org/repo/blob/main/current.md", server.url())),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+        fs::write(project_root.join(".ccagents/current.md"), "# Current").unwrap();
+
+        let report = run_stale(project_root, |_| Ok(false)).await.unwrap();
+
+        stale.assert_async().await;
+        current.assert_async().await;
+        assert_eq!(report.updated, vec!["agent.md"]);
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/agent.md")).unwrap(),
+            "# New"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_only_missing_downloads_absent_files() {
+        let mut server = mockito::Server::new_async().await;
+        let present = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_body("# New")
+            .expect(0)
+            .create_async()
+            .await;
+        let missing = server
+            .mock("GET", "/org/repo/raw/main/missing.md")
+            .with_body("# Missing")
+            .create_async()
+            .await;
+        let temp_dir = project_with_github_agent(&server);
+        let project_root = temp_dir.path();
+
+        let mut config = AgentsConfig::load(project_root).unwrap();
+        config
+            .add_agent(Agent::new(
+                "missing.md".to_string(),
+                AgentSource::GitHub(format!("{}/org/repo/blob/main/missing.md", server.url())),
+            ))
+            .unwrap();
+        config.save(project_root).unwrap();
+
+        let report = run(project_root, None, true, |_| Ok(false)).await.unwrap();
+
+        present.assert_async().await;
+        missing.assert_async().await;
+        assert_eq!(report.updated, vec!["missing.md"]);
+        assert_eq!(report.present, vec!["agent.md"]);
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/agent.md")).unwrap(),
+            "# Old"
+        );
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/missing.md")).unwrap(),
+            "# Missing"
+        );
+        assert!(!project_root.join(".claude/agents/missing.md").exists());
+    }
+
+    #[tokio::test]
+    async fn test_update_keeps_local_copy_when_upstream_is_gone() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(404)
+            .create_async()
+            .await;
+        let temp_dir = project_with_github_agent(&server);
+        let project_root = temp_dir.path();
+
+        let report = run(project_root, None, false, |_| Ok(false)).await.unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(report.kept, vec!["agent.md"]);
+        assert!(report.updated.is_empty());
+        assert_eq!(
+            fs::read_to_string(project_root.join(".ccagents/agent.md")).unwrap(),
+            "# Old"
+        );
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(config.get_agent("agent.md").unwrap().upstream_missing);
+    }
+
+    #[tokio::test]
+    async fn test_update_removes_agent_when_upstream_is_gone() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(404)
+            .create_async()
+            .await;
+        let temp_dir = project_with_github_agent(&server);
+        let project_root = temp_dir.path();
+
+        let report = run(project_root, Some("agent.md"), false, |_| Ok(true))
+            .await
+            .unwrap();
+
+        assert_eq!(report.removed, vec!["agent.md"]);
+        assert!(!project_root.join(".ccagents/agent.md").exists());
+        let config = AgentsConfig::load(project_root).unwrap();
+        assert!(config.get_agent("agent.md").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_fails_on_server_errors() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(500)
+            .create_async()
+            .await;
+        let temp_dir = project_with_github_agent(&server);
+        let project_root = temp_dir.path();
+
+        let error = run(project_root, None, false, |_| Ok(true))
+            .await
+            .unwrap_err();
+
+        assert!(!is_upstream_gone(&error));
+        assert!(project_root.join(".ccagents/agent.md").exists());
+    }
+}
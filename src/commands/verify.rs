@@ -0,0 +1,270 @@
+use crate::agent::{Agent, AgentSource};
+use crate::commands::print_download_warnings;
+use crate::config::{get_project_root, AgentsConfig};
+use crate::downloader::{download_agent, DownloadOptions};
+use crate::error::CcagentsError;
+use crate::linker::content_digest;
+use anyhow::Result;
+use colored::*;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// What to do with a GitHub agent whose content no longer matches its
+/// recorded checksum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixChoice {
+    /// Download the file again from its URL to restore the recorded content.
+    Restore,
+    /// Keep the local change and record its checksum.
+    Accept,
+    /// Leave it reported.
+    Skip,
+}
+
+/// What a verify run found, by agent name.
+#[derive(Debug, Default)]
+struct VerifyReport {
+    verified: Vec<String>,
+    /// Agents added before checksums were recorded.
+    unpinned: Vec<String>,
+    /// Agents that still do not match their checksum.
+    mismatched: Vec<String>,
+    restored: Vec<String>,
+    accepted: Vec<String>,
+}
+
+pub async fn execute(fix: bool) -> Result<()> {
+    let project_root = get_project_root()?;
+
+    println!("{}", "Verifying agent checksums...".cyan().bold());
+
+    let report = run(&project_root, fix, |agent| {
+        print!(
+            "    '{}' was modified. [r]e-download, [a]ccept the change, or [s]kip? [r/a/S]: ",
+            agent.name
+        );
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        Ok(match input.trim().to_lowercase().as_str() {
+            "r" | "re-download" => FixChoice::Restore,
+            "a" | "accept" => FixChoice::Accept,
+            _ => FixChoice::Skip,
+        })
+    })
+    .await?;
+
+    println!(
+        "\n{} Verified {} agent{}",
+        "✓".green().bold(),
+        report.verified.len(),
+        if report.verified.len() == 1 { "" } else { "s" }
+    );
+    if !report.restored.is_empty() {
+        println!("  {} Restored: {}", "→".cyan(), report.restored.join(", "));
+    }
+    if !report.accepted.is_empty() {
+        println!(
+            "  {} Checksum updated: {}",
+            "→".cyan(),
+            report.accepted.join(", ")
+        );
+    }
+    if !report.unpinned.is_empty() {
+        println!(
+            "  {} No checksum recorded (run 'ccagents update' for GitHub agents): {}",
+            "ℹ".blue(),
+            report.unpinned.join(", ")
+        );
+    }
+
+    if !report.mismatched.is_empty() {
+        if !fix {
+            println!(
+                "\n{} Run 'ccagents verify --fix' to restore modified GitHub agents",
+                "→".cyan().bold()
+            );
+        }
+        return Err(CcagentsError::IssuesFound(report.mismatched.len()).into());
+    }
+
+    Ok(())
+}
+
+/// Compares every agent's content with the checksum recorded by `add` or
+/// `update`. With `fix`, `choose` decides for each modified GitHub agent
+/// whether it is downloaded again or its new checksum recorded; modified
+/// local agents have no known-good copy and are only reported.
+async fn run(
+    project_root: &Path,
+    fix: bool,
+    mut choose: impl FnMut(&Agent) -> Result<FixChoice>,
+) -> Result<VerifyReport> {
+    let mut config = AgentsConfig::load_required(project_root)?;
+    let options = DownloadOptions {
+        refresh: true,
+        progress: true,
+        ..DownloadOptions::from_config(&config)
+    };
+    let mut report = VerifyReport::default();
+    let mut changed = false;
+
+    for agent in config.agents.clone() {
+        let Some(expected) = &agent.sha256 else {
+            report.unpinned.push(agent.name);
+            continue;
+        };
+
+        let actual = content_digest(&agent.get_local_path(project_root)).ok();
+        if actual.as_ref() == Some(expected) {
+            report.verified.push(agent.name);
+            continue;
+        }
+
+        let problem = if actual.is_some() {
+            "content does not match the recorded checksum"
+        } else {
+            "file is missing"
+        };
+        println!("  {} {} - {}", "✗".red(), agent.name, problem.red());
+
+        let is_github = matches!(agent.source, AgentSource::GitHub(_));
+        let choice = if fix && is_github {
+            choose(&agent)?
+        } else {
+            FixChoice::Skip
+        };
+
+        match choice {
+            FixChoice::Restore => {
+                print_download_warnings(&download_agent(&agent, project_root, &options).await?);
+                let restored = content_digest(&agent.get_local_path(project_root)).ok();
+                if restored.as_ref() == Some(expected) {
+                    println!("  {} {} - {}", "✓".green(), agent.name, "restored".green());
+                    report.restored.push(agent.name);
+                } else {
+                    println!(
+                        "  {} {} - {}",
+                        "⚠".yellow(),
+                        agent.name,
+                        "upstream content has changed too; run 'ccagents update' to accept it"
+                            .yellow()
+                    );
+                    report.mismatched.push(agent.name);
+                }
+            }
+            FixChoice::Accept if actual.is_some() => {
+                if let Some(stored) = config.get_agent_mut(&agent.name) {
+                    stored.sha256 = actual;
+                }
+                changed = true;
+                println!(
+                    "  {} {} - {}",
+                    "✓".green(),
+                    agent.name,
+                    "checksum updated".green()
+                );
+                report.accepted.push(agent.name);
+            }
+            _ => {
+                if fix && !is_github {
+                    println!(
+                        "    {}",
+                        "local source, no known-good copy to restore".dimmed()
+                    );
+                }
+                report.mismatched.push(agent.name);
+            }
+        }
+    }
+
+    if changed {
+        config.save(project_root)?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ensure_ccagents_dir;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_fix_restores_tampered_github_agent() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_body("# Good")
+            .create_async()
+            .await;
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+
+        let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+        fs::write(ccagents_dir.join("agent.md"), "# Good").unwrap();
+        fs::write(ccagents_dir.join("local.md"), "# Local").unwrap();
+
+        crate::github::trust_test_host(&server.host_with_port());
+        crate::cache::DownloadCache::use_test_dir(temp_dir.path());
+
+        let mut config = AgentsConfig {
+            github_hosts: vec![server.host_with_port()],
+            ..AgentsConfig::default()
+        };
+        let mut github = Agent::new(
+            "agent.md".to_string(),
+            AgentSource::GitHub(format!("{}/org/repo/blob/main/agent.md", server.url())),
+        );
+        github.sha256 = Some(content_digest(&ccagents_dir.join("agent.md")).unwrap());
+        let mut local = Agent::new(
+            "local.md".to_string(),
+            AgentSource::Local(PathBuf::from(".ccagents/local.md")),
+        );
+        local.sha256 = Some(content_digest(&ccagents_dir.join("local.md")).unwrap());
+        config.add_agent(github).unwrap();
+        config.add_agent(local).unwrap();
+        config.save(project_root).unwrap();
+
+        // Untouched content verifies without a download
+        let report = run(project_root, true, |_| Ok(FixChoice::Restore))
+            .await
+            .unwrap();
+        assert_eq!(report.verified, vec!["agent.md", "local.md"]);
+
+        fs::write(ccagents_dir.join("agent.md"), "# Tampered").unwrap();
+        fs::write(ccagents_dir.join("local.md"), "# Edited").unwrap();
+
+        // Without --fix both are only reported
+        let report = run(project_root, false, |_| panic!("not asked without --fix"))
+            .await
+            .unwrap();
+        assert_eq!(report.mismatched, vec!["agent.md", "local.md"]);
+
+        let mut asked = Vec::new();
+        let report = run(project_root, true, |agent| {
+            asked.push(agent.name.clone());
+            Ok(FixChoice::Restore)
+        })
+        .await
+        .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(asked, vec!["agent.md"]);
+        assert_eq!(report.restored, vec!["agent.md"]);
+        assert_eq!(report.mismatched, vec!["local.md"]);
+        assert_eq!(
+            fs::read_to_string(ccagents_dir.join("agent.md")).unwrap(),
+            "# Good"
+        );
+        assert_eq!(
+            fs::read_to_string(ccagents_dir.join("local.md")).unwrap(),
+            "# Edited"
+        );
+    }
+}
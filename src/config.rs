@@ -1,12 +1,91 @@
-use crate::agent::Agent;
+use crate::agent::{Agent, AgentSource};
+use crate::claude_settings;
+use crate::error::CcagentsError;
+use crate::ignore::scanned_file_name;
+use crate::linker::{link_agent, LinkMode, LinkStyle};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// Default directory for copied and downloaded agents.
+pub const DEFAULT_MANAGED_DIR: &str = ".ccagents";
+
+/// Environment variable holding a manifest for `sync`: inline JSON or a path
+/// to a file, both in the `.agents.json` format.
+pub const MANIFEST_ENV: &str = "CCAGENTS_MANIFEST";
+
+/// How a manifest from [`MANIFEST_ENV`] combines with `.agents.json`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ManifestMerge {
+    /// Use only the manifest and ignore `.agents.json`.
+    #[default]
+    Override,
+    /// Use both; manifest agents replace configured agents of the same name.
+    Union,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentsConfig {
     pub agents: Vec<Agent>,
+    /// GitHub Enterprise hosts recognized in addition to github.com.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub github_hosts: Vec<String>,
+    /// Narrows the hosts downloads may contact, including those redirected
+    /// to. Hosts not already allowed by default or by the user's environment
+    /// are ignored; empty leaves the list unchanged.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_hosts: Vec<String>,
+    /// Directory for copied and downloaded agents, defaults to `.ccagents`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub managed_dir: Option<PathBuf>,
+    /// File extensions accepted for GitHub agents, defaults to md/markdown/txt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_extensions: Vec<String>,
+    /// Whether `add` and `import` enable new agents unless told otherwise.
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub default_enabled: bool,
+    /// Whether agent symlinks are created relative (the default) or absolute.
+    #[serde(default, skip_serializing_if = "is_relative")]
+    pub link_style: LinkStyle,
+    /// Whether agents are copied into `.claude/agents` instead of symlinked,
+    /// unless a command is told otherwise (e.g. `sync --symlink`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prefer_copy: bool,
+    /// Glob patterns for file names that scans skip, like lines of
+    /// `.ccagentsignore`; a `!` line there cannot re-include them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+impl Default for AgentsConfig {
+    fn default() -> Self {
+        Self {
+            agents: Vec::new(),
+            github_hosts: Vec::new(),
+            allowed_hosts: Vec::new(),
+            managed_dir: None,
+            allowed_extensions: Vec::new(),
+            default_enabled: true,
+            link_style: LinkStyle::default(),
+            prefer_copy: false,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_relative(style: &LinkStyle) -> bool {
+    *style == LinkStyle::Relative
 }
 
 impl AgentsConfig {
@@ -16,17 +95,123 @@ impl AgentsConfig {
         if !config_path.exists() {
             return Ok(Self::default());
         }
+        reject_config_dir(&config_path)?;
+
+        let bytes =
+            fs::read(&config_path).with_context(|| format!("Failed to read {:?}", config_path))?;
+
+        Self::parse_file(&bytes, &config_path)
+    }
+
+    /// Parses the content of the config file at `path`. Invalid UTF-8 or JSON
+    /// is reported with the position and the offending line, since the file
+    /// is often edited by hand.
+    fn parse_file(bytes: &[u8], path: &Path) -> Result<Self> {
+        let content = std::str::from_utf8(bytes).map_err(|e| {
+            anyhow::anyhow!(
+                "{:?} is not valid UTF-8 (invalid byte at offset {}). Save it as UTF-8 and run 'ccagents doctor' to check it.",
+                path,
+                e.valid_up_to()
+            )
+        })?;
+
+        serde_json::from_str(content).map_err(|e| {
+            let snippet = content
+                .lines()
+                .nth(e.line().saturating_sub(1))
+                .map(|line| {
+                    format!(
+                        "\n{:>5} | {}\n      | {}^",
+                        e.line(),
+                        line,
+                        " ".repeat(e.column().saturating_sub(1))
+                    )
+                })
+                .unwrap_or_default();
+
+            anyhow::anyhow!(
+                "Failed to parse {:?}: {}{}\nFix the file, then run 'ccagents doctor' to check the configuration.",
+                path,
+                e,
+                snippet
+            )
+        })
+    }
+
+    /// Loads `.agents.json` and applies the manifest from [`MANIFEST_ENV`] if
+    /// it is set. Returns whether a manifest was applied; the result must not
+    /// be saved in that case.
+    pub fn load_with_manifest(project_root: &Path, merge: ManifestMerge) -> Result<(Self, bool)> {
+        let config = Self::load(project_root)?;
+
+        match std::env::var(MANIFEST_ENV) {
+            Ok(value) if !value.trim().is_empty() => {
+                let manifest = Self::parse_manifest(&value, project_root)
+                    .with_context(|| format!("Invalid manifest in ${}", MANIFEST_ENV))?;
+                Ok((config.merge_manifest(manifest, merge), true))
+            }
+            _ => Ok((config, false)),
+        }
+    }
 
-        let content = fs::read_to_string(&config_path)
-            .with_context(|| format!("Failed to read {:?}", config_path))?;
+    /// Parses a manifest given as inline JSON (starting with `{`) or as a path
+    /// to a JSON file, relative to `project_root`.
+    pub fn parse_manifest(value: &str, project_root: &Path) -> Result<Self> {
+        let value = value.trim();
+        if value.starts_with('{') {
+            return serde_json::from_str(value).context("Failed to parse inline JSON");
+        }
 
-        serde_json::from_str(&content).with_context(|| format!("Failed to parse {:?}", config_path))
+        let path = project_root.join(value);
+        let bytes = fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::parse_file(&bytes, &path)
+    }
+
+    /// Combines this config with `manifest` according to `merge`.
+    pub fn merge_manifest(mut self, manifest: Self, merge: ManifestMerge) -> Self {
+        match merge {
+            ManifestMerge::Override => manifest,
+            ManifestMerge::Union => {
+                for agent in manifest.agents {
+                    match self.agents.iter_mut().find(|a| a.name == agent.name) {
+                        Some(existing) => *existing = agent,
+                        None => self.agents.push(agent),
+                    }
+                }
+                for host in manifest.github_hosts {
+                    if !self.github_hosts.contains(&host) {
+                        self.github_hosts.push(host);
+                    }
+                }
+                for pattern in manifest.exclude {
+                    if !self.exclude.contains(&pattern) {
+                        self.exclude.push(pattern);
+                    }
+                }
+                self
+            }
+        }
+    }
+
+    /// Like [`AgentsConfig::load`], but fails with
+    /// [`CcagentsError::ConfigNotFound`] when `.agents.json` does not exist.
+    pub fn load_required(project_root: &Path) -> Result<Self> {
+        let config_path = project_root.join(".agents.json");
+        if !config_path.exists() {
+            return Err(CcagentsError::ConfigNotFound(config_path).into());
+        }
+
+        Self::load(project_root)
     }
 
     pub fn save(&self, project_root: &Path) -> Result<()> {
         let config_path = project_root.join(".agents.json");
-        let content =
-            serde_json::to_string_pretty(self).context("Failed to serialize agents config")?;
+        reject_config_dir(&config_path)?;
+
+        let mut normalized = self.clone();
+        normalized.normalize(project_root);
+        let content = serde_json::to_string_pretty(&normalized)
+            .context("Failed to serialize agents config")?;
 
         fs::write(&config_path, content)
             .with_context(|| format!("Failed to write {:?}", config_path))?;
@@ -34,16 +219,89 @@ impl AgentsConfig {
         Ok(())
     }
 
+    /// Normalizes local agent paths so the serialized form does not depend on
+    /// how an `Agent` was constructed: paths inside the project are stored
+    /// relative to `project_root`, paths outside it are stored absolute.
+    pub fn normalize(&mut self, project_root: &Path) {
+        let root = normalize_path(project_root);
+
+        let relativize = |path: &Path| {
+            let absolute = normalize_path(&root.join(path));
+            match absolute.strip_prefix(&root) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => absolute,
+            }
+        };
+
+        for agent in &mut self.agents {
+            if let AgentSource::Local(path) = &mut agent.source {
+                *path = relativize(path);
+            }
+            if let Some(dir) = &mut agent.managed_dir {
+                *dir = relativize(dir);
+            }
+        }
+        if let Some(dir) = &mut self.managed_dir {
+            *dir = relativize(dir);
+        }
+    }
+
+    /// How agents are placed in `.claude/agents` by default: copies with
+    /// `prefer_copy`, else symlinks.
+    pub fn link_mode(&self) -> LinkMode {
+        if self.prefer_copy {
+            LinkMode::Copy
+        } else {
+            LinkMode::Symlink
+        }
+    }
+
+    /// Returns the directory for copied and downloaded agents.
+    pub fn managed_dir(&self) -> &Path {
+        self.managed_dir
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_MANAGED_DIR))
+    }
+
     pub fn add_agent(&mut self, agent: Agent) -> Result<()> {
         // Check for duplicates
         if self.agents.iter().any(|a| a.name == agent.name) {
             return Err(anyhow::anyhow!("Agent '{}' already exists", agent.name));
         }
+        if let Some(owner) = self.alias_owner(&agent.name) {
+            return Err(anyhow::anyhow!(
+                "'{}' is already an alias of agent '{}'",
+                agent.name,
+                owner.name
+            ));
+        }
 
         self.agents.push(agent);
         Ok(())
     }
 
+    /// Adds an agent, saves the configuration and, when `link` is set and the
+    /// agent is enabled, links (or with `prefer_copy`, copies) it into
+    /// `.claude/agents`.
+    ///
+    /// Unlike the `add` command this performs no terminal I/O, so it can be
+    /// reused by library consumers.
+    pub fn add_and_link(&mut self, agent: Agent, project_root: &Path, link: bool) -> Result<()> {
+        let local_path = agent.get_local_path(project_root);
+        let link_path = agent.get_link_path(project_root);
+        let enabled = agent.enabled;
+
+        self.add_agent(agent)?;
+        self.save(project_root)?;
+
+        if link && enabled {
+            ensure_claude_agents_dir(project_root)?;
+            link_agent(&local_path, &link_path, self.link_mode(), self.link_style)?;
+        }
+
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn remove_agent(&mut self, name: &str) -> Result<()> {
         let initial_len = self.agents.len();
@@ -65,6 +323,112 @@ impl AgentsConfig {
         self.agents.iter_mut().find(|a| a.name == name)
     }
 
+    /// Finds an agent by name ignoring case. Errors when the name is
+    /// ambiguous, i.e. several agents differ from it only by case.
+    pub fn get_agent_ci(&self, name: &str) -> Result<Option<&Agent>> {
+        let lowered = name.to_lowercase();
+        let mut matches = self
+            .agents
+            .iter()
+            .filter(|a| a.name.to_lowercase() == lowered);
+
+        match (matches.next(), matches.next()) {
+            (Some(_), Some(_)) => Err(anyhow::anyhow!(
+                "Agent name '{}' is ambiguous: several agents differ only by case",
+                name
+            )),
+            (first, _) => Ok(first),
+        }
+    }
+
+    /// Resolves a user-supplied agent name, trying an exact match first, then
+    /// an alias, and falling back to a case-insensitive match. Callers can
+    /// detect the fallback by comparing the returned agent's name with `name`.
+    pub fn lookup_agent(&self, name: &str) -> Result<Option<&Agent>> {
+        match self.agents.iter().find(|a| a.name == name) {
+            Some(agent) => Ok(Some(agent)),
+            None => match self.alias_owner(name) {
+                Some(agent) => Ok(Some(agent)),
+                None => self.get_agent_ci(name),
+            },
+        }
+    }
+
+    /// Returns the agent that has `alias` among its aliases.
+    pub fn alias_owner(&self, alias: &str) -> Option<&Agent> {
+        self.agents
+            .iter()
+            .find(|a| a.aliases.iter().any(|existing| existing == alias))
+    }
+
+    /// Adds `alias` to the agent named `name`. Errors when the alias is empty
+    /// or already used as an agent name or alias.
+    pub fn add_alias(&mut self, name: &str, alias: &str) -> Result<()> {
+        if alias.trim().is_empty() {
+            return Err(anyhow::anyhow!("Alias cannot be empty"));
+        }
+        if self.agents.iter().any(|a| a.name == alias) {
+            return Err(anyhow::anyhow!(
+                "Alias '{}' collides with an existing agent name",
+                alias
+            ));
+        }
+        if let Some(owner) = self.alias_owner(alias) {
+            return Err(anyhow::anyhow!(
+                "Alias '{}' is already used by agent '{}'",
+                alias,
+                owner.name
+            ));
+        }
+
+        let agent = self
+            .get_agent_mut(name)
+            .ok_or_else(|| CcagentsError::AgentNotFound(name.to_string()))?;
+        agent.aliases.push(alias.to_string());
+
+        Ok(())
+    }
+
+    /// Like [`AgentsConfig::lookup_agent`], but fails with
+    /// [`CcagentsError::AgentNotFound`] when no agent matches. Commands call
+    /// this before mutating anything.
+    pub fn require_agent(&self, name: &str) -> Result<&Agent> {
+        self.lookup_agent(name)?
+            .ok_or_else(|| CcagentsError::AgentNotFound(name.to_string()).into())
+    }
+
+    /// Returns the names of all agents matching the glob `pattern`, in config
+    /// order. Errors when the pattern is invalid or matches nothing.
+    pub fn match_agents(&self, pattern: &str) -> Result<Vec<String>> {
+        let glob = glob::Pattern::new(pattern)
+            .with_context(|| format!("Invalid glob pattern '{}'", pattern))?;
+
+        let names: Vec<String> = self
+            .agents
+            .iter()
+            .filter(|a| glob.matches(&a.name))
+            .map(|a| a.name.clone())
+            .collect();
+
+        if names.is_empty() {
+            return Err(anyhow::anyhow!("No agents match '{}'", pattern));
+        }
+
+        Ok(names)
+    }
+
+    /// Returns the set of configured agent names for fast membership checks
+    /// while scanning directories.
+    pub fn agent_names(&self) -> HashSet<&str> {
+        self.agents.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    /// Returns the paths, relative to `.claude/agents`, that configured agents
+    /// are linked at (see [`Agent::link_key`]).
+    pub fn link_names(&self) -> HashSet<String> {
+        self.agents.iter().map(|a| a.link_key()).collect()
+    }
+
     pub fn enabled_agents(&self) -> Vec<&Agent> {
         self.agents.iter().filter(|a| a.enabled).collect()
     }
@@ -74,12 +438,76 @@ impl AgentsConfig {
     }
 }
 
+/// Lexically normalizes a path by resolving `.` and `..` components without
+/// touching the filesystem.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    normalized
+}
+
+/// Project root set by the `--root` flag, overriding the current directory.
+static PROJECT_ROOT_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Makes [`get_project_root`] return `root` for the rest of the process.
+/// Fails when `root` is not an existing directory or was already set.
+pub fn set_project_root(root: &Path) -> Result<()> {
+    if !root.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Project root {:?} does not exist or is not a directory",
+            root
+        ));
+    }
+
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve project root {:?}", root))?;
+    PROJECT_ROOT_OVERRIDE
+        .set(root)
+        .map_err(|_| anyhow::anyhow!("Project root was already set"))
+}
+
+/// Returns the canonical project root: the `--root` override if set, else
+/// the current directory. Being canonical, it is a prefix of every
+/// canonicalized path inside the project.
 pub fn get_project_root() -> Result<PathBuf> {
-    std::env::current_dir().context("Failed to get current directory")
+    match PROJECT_ROOT_OVERRIDE.get() {
+        Some(root) => Ok(root.clone()),
+        None => std::env::current_dir()
+            .and_then(|dir| dir.canonicalize())
+            .context("Failed to get current directory"),
+    }
 }
 
+/// Resolves symlinks and `..` in the directories leading to `path` while
+/// keeping its last component, so a symlinked agent file is not replaced by
+/// its target. Returns `path` unchanged when its parent cannot be resolved.
+pub fn canonicalize_parent(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Ensures the directory agents are linked into exists and returns it:
+/// `.claude/agents`, or the `agentsDir` from `.claude/settings.json`.
 pub fn ensure_claude_agents_dir(project_root: &Path) -> Result<PathBuf> {
-    let claude_agents_dir = project_root.join(".claude").join("agents");
+    let claude_agents_dir = claude_settings::agents_dir(project_root);
 
     if !claude_agents_dir.exists() {
         fs::create_dir_all(&claude_agents_dir)
@@ -89,27 +517,176 @@ pub fn ensure_claude_agents_dir(project_root: &Path) -> Result<PathBuf> {
     Ok(claude_agents_dir)
 }
 
+/// Levels of subdirectories below `.claude/agents` that are scanned.
+pub const MAX_LINK_DEPTH: usize = 3;
+
+/// Reads the entries of `dir` sorted by file name, so scans visit and report
+/// them in the same order on every run and platform.
+pub fn read_dir_sorted(dir: &Path) -> Result<Vec<fs::DirEntry>> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+        .with_context(|| format!("Failed to read {:?}", dir))?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+    Ok(entries)
+}
+
+/// Lists the entries of `.claude/agents` by their key relative to it, e.g.
+/// `agent.md` or `team/backend/agent.md`. Real subdirectories are descended
+/// into (up to [`MAX_LINK_DEPTH`] levels) instead of being listed, except
+/// hidden ones and those whose key is in `agent_keys`, which are copies of
+/// directory agents. Symlinks are never followed. Entries with non-UTF-8
+/// names are skipped and returned in [`LinkScan::skipped`].
+pub fn scan_link_dir(dir: &Path, agent_keys: &HashSet<String>) -> Result<LinkScan> {
+    let mut scan = LinkScan::default();
+    scan_link_subdir(dir, "", 0, agent_keys, &mut scan)?;
+    Ok(scan)
+}
+
+/// The result of [`scan_link_dir`].
+#[derive(Debug, Default)]
+pub struct LinkScan {
+    /// Entries by their key relative to `.claude/agents`.
+    pub entries: Vec<(String, PathBuf)>,
+    /// Entries whose name is not valid UTF-8, which no agent can match.
+    pub skipped: Vec<PathBuf>,
+}
+
+fn scan_link_subdir(
+    dir: &Path,
+    prefix: &str,
+    depth: usize,
+    agent_keys: &HashSet<String>,
+    scan: &mut LinkScan,
+) -> Result<()> {
+    for entry in read_dir_sorted(dir)? {
+        let path = entry.path();
+        let Some(name) = scanned_file_name(&path) else {
+            scan.skipped.push(path);
+            continue;
+        };
+        let key = format!("{}{}", prefix, name);
+
+        let nested = path.is_dir()
+            && !path.is_symlink()
+            && !name.starts_with('.')
+            && !agent_keys.contains(&key);
+        if !nested {
+            scan.entries.push((key, path));
+        } else if depth < MAX_LINK_DEPTH {
+            scan_link_subdir(&path, &format!("{}/", key), depth + 1, agent_keys, scan)?;
+        }
+    }
+    Ok(())
+}
+
+#[allow(dead_code)]
 pub fn ensure_ccagents_dir(project_root: &Path) -> Result<PathBuf> {
-    let ccagents_dir = project_root.join(".ccagents");
+    ensure_managed_dir(project_root, Path::new(DEFAULT_MANAGED_DIR))
+}
+
+/// Fails when the config path is a directory (e.g. after a stray `mkdir` or
+/// a botched restore), which would otherwise surface as an opaque
+/// "Is a directory" error from every command.
+fn reject_config_dir(config_path: &Path) -> Result<()> {
+    if config_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "{:?} is a directory, but the agent list is stored in a file there. Move it aside (e.g. 'mv .agents.json .agents.json.bak'), then restore the file from version control or a backup.",
+            config_path
+        ));
+    }
+    Ok(())
+}
 
-    if !ccagents_dir.exists() {
-        fs::create_dir_all(&ccagents_dir)
-            .with_context(|| format!("Failed to create {:?}", ccagents_dir))?;
+/// Ensures the managed directory `dir` (relative to the project root, or
+/// absolute) exists and returns its full path.
+pub fn ensure_managed_dir(project_root: &Path, dir: &Path) -> Result<PathBuf> {
+    let managed_dir = project_root.join(dir);
+
+    if let Some(problem) = managed_dir_problem(&managed_dir) {
+        return Err(anyhow::anyhow!(
+            "{:?} {}, but agents are stored in a directory there. Move it aside and run 'ccagents sync' to recreate it.",
+            managed_dir,
+            problem
+        ));
     }
 
-    Ok(ccagents_dir)
+    if !managed_dir.exists() {
+        fs::create_dir_all(&managed_dir)
+            .with_context(|| format!("Failed to create {:?}", managed_dir))?;
+    }
+
+    Ok(managed_dir)
+}
+
+/// Describes why `path` cannot be used as a managed directory: it is a file
+/// or a symlink rather than a real directory. `None` if it is fine or does not
+/// exist yet.
+pub fn managed_dir_problem(path: &Path) -> Option<&'static str> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if metadata.file_type().is_symlink() {
+        Some("is a symlink")
+    } else if !metadata.is_dir() {
+        Some("is a file")
+    } else {
+        None
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::agent::{Agent, AgentSource};
     use tempfile::TempDir;
 
+    #[test]
+    fn test_ensure_managed_dir_rejects_file() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(".ccagents"), "oops").unwrap();
+
+        let err = ensure_ccagents_dir(temp_dir.path()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(".ccagents\" is a file"), "{}", message);
+        assert!(message.contains("Move it aside"));
+    }
+
+    #[test]
+    fn test_config_path_that_is_a_directory_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join(".agents.json")).unwrap();
+
+        let err = AgentsConfig::load(temp_dir.path()).unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains(".agents.json\" is a directory"),
+            "{}",
+            message
+        );
+        assert!(message.contains("Move it aside"));
+
+        let err = AgentsConfig::default().save(temp_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("is a directory"));
+    }
+
     #[test]
     fn test_agents_config_default() {
         let config = AgentsConfig::default();
         assert!(config.agents.is_empty());
+        assert!(config.default_enabled);
+    }
+
+    #[test]
+    fn test_default_enabled_roundtrip() {
+        let config: AgentsConfig = serde_json::from_str(r#"{"agents": []}"#).unwrap();
+        assert!(config.default_enabled);
+        assert!(!serde_json::to_string(&config)
+            .unwrap()
+            .contains("default_enabled"));
+
+        let config: AgentsConfig =
+            serde_json::from_str(r#"{"agents": [], "default_enabled": false}"#).unwrap();
+        assert!(!config.default_enabled);
+        assert!(serde_json::to_string(&config)
+            .unwrap()
+            .contains(r#""default_enabled":false"#));
     }
 
     #[test]
@@ -119,6 +696,27 @@ mod tests {
         assert!(config.agents.is_empty());
     }
 
+    #[test]
+    fn test_load_reports_parse_error_position() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".agents.json");
+        fs::write(
+            &config_path,
+            "{\n  \"agents\": [\n    {\"name\": \"a.md\" \"enabled\": true}\n  ]\n}\n",
+        )
+        .unwrap();
+
+        let message = AgentsConfig::load(temp_dir.path()).unwrap_err().to_string();
+        assert!(message.contains(&format!("{:?}", config_path)));
+        assert!(message.contains("line 3 column 21"));
+        assert!(message.contains("    3 |     {\"name\": \"a.md\" \"enabled\": true}"));
+        assert!(message.contains("ccagents doctor"));
+
+        fs::write(&config_path, b"{\"agents\": [\xff]}").unwrap();
+        let message = AgentsConfig::load(temp_dir.path()).unwrap_err().to_string();
+        assert!(message.contains("not valid UTF-8 (invalid byte at offset 12)"));
+    }
+
     #[test]
     fn test_agents_config_save_and_load() {
         let temp_dir = TempDir::new().unwrap();
@@ -205,6 +803,160 @@ mod tests {
         assert!(!config.agents[0].enabled);
     }
 
+    #[test]
+    fn test_lookup_agent_exact_and_case_insensitive() {
+        let mut config = AgentsConfig::default();
+        config.agents.push(Agent::new(
+            "Backend.md".to_string(),
+            AgentSource::Local(PathBuf::from("Backend.md")),
+        ));
+
+        let exact = config.lookup_agent("Backend.md").unwrap().unwrap();
+        assert_eq!(exact.name, "Backend.md");
+
+        let ci = config.lookup_agent("backend.MD").unwrap().unwrap();
+        assert_eq!(ci.name, "Backend.md");
+
+        assert!(config.lookup_agent("frontend.md").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_lookup_agent_ambiguous_case() {
+        let mut config = AgentsConfig::default();
+        for name in ["Agent.md", "agent.md"] {
+            config.agents.push(Agent::new(
+                name.to_string(),
+                AgentSource::Local(PathBuf::from(name)),
+            ));
+        }
+
+        // Exact matches still resolve unambiguously
+        assert_eq!(
+            config.lookup_agent("agent.md").unwrap().unwrap().name,
+            "agent.md"
+        );
+
+        let result = config.lookup_agent("AGENT.md");
+        assert!(result.unwrap_err().to_string().contains("ambiguous"));
+        assert!(config.get_agent_ci("AGENT.md").is_err());
+    }
+
+    #[test]
+    fn test_lookup_agent_by_alias() {
+        let mut config = AgentsConfig::default();
+        for name in ["backend-developer.md", "Be"] {
+            config.agents.push(Agent::new(
+                name.to_string(),
+                AgentSource::Local(PathBuf::from(name)),
+            ));
+        }
+        config.add_alias("backend-developer.md", "be").unwrap();
+
+        assert_eq!(
+            config.lookup_agent("be").unwrap().unwrap().name,
+            "backend-developer.md"
+        );
+        // Names still win over aliases, and aliases over case-insensitive matches
+        assert_eq!(config.lookup_agent("Be").unwrap().unwrap().name, "Be");
+        assert_eq!(config.lookup_agent("BE").unwrap().unwrap().name, "Be");
+        assert_eq!(
+            config.require_agent("be").unwrap().name,
+            "backend-developer.md"
+        );
+    }
+
+    #[test]
+    fn test_add_alias_rejects_collisions() {
+        let mut config = AgentsConfig::default();
+        for name in ["backend.md", "frontend.md"] {
+            config.agents.push(Agent::new(
+                name.to_string(),
+                AgentSource::Local(PathBuf::from(name)),
+            ));
+        }
+        config.add_alias("backend.md", "be").unwrap();
+
+        let err = config.add_alias("frontend.md", "be").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("already used by agent 'backend.md'"));
+        let err = config.add_alias("backend.md", "frontend.md").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("collides with an existing agent name"));
+        assert!(config.add_alias("backend.md", " ").is_err());
+        assert!(config.add_alias("missing.md", "m").is_err());
+
+        // New agents cannot take a name that is already an alias
+        let err = config
+            .add_agent(Agent::new(
+                "be".to_string(),
+                AgentSource::Local(PathBuf::from("be")),
+            ))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("already an alias of agent 'backend.md'"));
+        assert_eq!(config.agents[0].aliases, vec!["be"]);
+    }
+
+    #[test]
+    fn test_require_agent() {
+        let mut config = AgentsConfig::default();
+        config.agents.push(Agent::new(
+            "Backend.md".to_string(),
+            AgentSource::Local(PathBuf::from("Backend.md")),
+        ));
+
+        assert_eq!(
+            config.require_agent("backend.md").unwrap().name,
+            "Backend.md"
+        );
+
+        let err = config.require_agent("typo.md").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CcagentsError>(),
+            Some(CcagentsError::AgentNotFound(name)) if name == "typo.md"
+        ));
+        assert_eq!(err.to_string(), "Agent 'typo.md' not found in .agents.json");
+    }
+
+    #[test]
+    fn test_match_agents() {
+        let mut config = AgentsConfig::default();
+        for name in ["test-a.md", "backend.md", "test-b.md"] {
+            config.agents.push(Agent::new(
+                name.to_string(),
+                AgentSource::Local(PathBuf::from(name)),
+            ));
+        }
+
+        assert_eq!(
+            config.match_agents("test-*").unwrap(),
+            vec!["test-a.md", "test-b.md"]
+        );
+        assert_eq!(config.match_agents("*.md").unwrap().len(), 3);
+        assert!(config.match_agents("frontend-*").is_err());
+        assert!(config.match_agents("[").is_err());
+    }
+
+    #[test]
+    fn test_agent_names() {
+        let mut config = AgentsConfig::default();
+        for i in 0..300 {
+            config.agents.push(Agent::new(
+                format!("agent-{}.md", i),
+                AgentSource::Local(PathBuf::from(format!("agent-{}.md", i))),
+            ));
+        }
+
+        let names = config.agent_names();
+        assert_eq!(names.len(), 300);
+        assert!(names.contains("agent-0.md"));
+        assert!(names.contains("agent-299.md"));
+        assert!(!names.contains("agent-300.md"));
+    }
+
     #[test]
     fn test_enabled_disabled_agents() {
         let mut config = AgentsConfig::default();
@@ -233,6 +985,52 @@ mod tests {
         assert_eq!(disabled[0].name, "disabled");
     }
 
+    #[test]
+    fn test_add_and_link() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        fs::write(project_root.join("agent.md"), "# Agent").unwrap();
+
+        let mut config = AgentsConfig::default();
+        let agent = Agent::new(
+            "agent.md".to_string(),
+            AgentSource::Local(PathBuf::from("agent.md")),
+        );
+        config.add_and_link(agent, project_root, true).unwrap();
+
+        let link_path = project_root.join(".claude").join("agents").join("agent.md");
+        assert!(link_path.is_symlink());
+        assert_eq!(
+            fs::read_link(&link_path).unwrap(),
+            Path::new("../../agent.md")
+        );
+
+        let loaded = AgentsConfig::load(project_root).unwrap();
+        assert_eq!(loaded.agents.len(), 1);
+        assert_eq!(loaded.agents[0].name, "agent.md");
+    }
+
+    #[test]
+    fn test_add_and_link_without_link() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+
+        let mut config = AgentsConfig::default();
+        let agent = Agent::new(
+            "agent.md".to_string(),
+            AgentSource::Local(PathBuf::from("agent.md")),
+        );
+        config
+            .add_and_link(agent.clone(), project_root, false)
+            .unwrap();
+
+        assert!(!project_root.join(".claude").join("agents").exists());
+        assert_eq!(AgentsConfig::load(project_root).unwrap().agents.len(), 1);
+
+        // Duplicates are rejected before anything is written
+        assert!(config.add_and_link(agent, project_root, true).is_err());
+    }
+
     #[test]
     fn test_ensure_claude_agents_dir() {
         let temp_dir = TempDir::new().unwrap();
@@ -253,6 +1051,114 @@ mod tests {
         assert_eq!(result, temp_dir.path().join(".ccagents"));
     }
 
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path(Path::new("/project/./sub/../agent.md")),
+            PathBuf::from("/project/agent.md")
+        );
+        assert_eq!(
+            normalize_path(Path::new("../shared/agent.md")),
+            PathBuf::from("../shared/agent.md")
+        );
+    }
+
+    #[test]
+    fn test_save_normalizes_local_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path();
+        let outside = TempDir::new().unwrap();
+
+        let mut config = AgentsConfig::default();
+        for (name, path) in [
+            ("relative.md", PathBuf::from(".ccagents/relative.md")),
+            ("absolute.md", project_root.join(".ccagents/absolute.md")),
+            ("dotted.md", PathBuf::from("./sub/../.ccagents/dotted.md")),
+            ("outside.md", outside.path().join("outside.md")),
+        ] {
+            config
+                .add_agent(Agent::new(name.to_string(), AgentSource::Local(path)))
+                .unwrap();
+        }
+        config.save(project_root).unwrap();
+
+        let loaded = AgentsConfig::load(project_root).unwrap();
+        let paths: Vec<PathBuf> = loaded
+            .agents
+            .iter()
+            .map(|a| match &a.source {
+                AgentSource::Local(path) => path.clone(),
+                AgentSource::GitHub(_) => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(paths[0], PathBuf::from(".ccagents/relative.md"));
+        assert_eq!(paths[1], PathBuf::from(".ccagents/absolute.md"));
+        assert_eq!(paths[2], PathBuf::from(".ccagents/dotted.md"));
+        assert_eq!(paths[3], outside.path().join("outside.md"));
+    }
+
+    #[test]
+    fn test_normalize_makes_escaping_relative_paths_absolute() {
+        let mut config = AgentsConfig::default();
+        config.agents.push(Agent::new(
+            "shared.md".to_string(),
+            AgentSource::Local(PathBuf::from("../shared/agent.md")),
+        ));
+
+        config.normalize(Path::new("/work/project"));
+
+        match &config.agents[0].source {
+            AgentSource::Local(path) => {
+                assert_eq!(path, &PathBuf::from("/work/shared/agent.md"))
+            }
+            AgentSource::GitHub(_) => panic!("Expected Local source"),
+        }
+    }
+
+    #[test]
+    fn test_managed_dir_default_and_custom() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = AgentsConfig::default();
+        assert_eq!(config.managed_dir(), Path::new(".ccagents"));
+
+        config.managed_dir = Some(temp_dir.path().join("agents"));
+        config.save(temp_dir.path()).unwrap();
+
+        let loaded = AgentsConfig::load(temp_dir.path()).unwrap();
+        assert_eq!(loaded.managed_dir(), Path::new("agents"));
+
+        let created = ensure_managed_dir(temp_dir.path(), loaded.managed_dir()).unwrap();
+        assert!(created.is_dir());
+        assert_eq!(created, temp_dir.path().join("agents"));
+    }
+
+    #[test]
+    fn test_scans_are_sorted_by_file_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        for name in ["zeta.md", "Beta.md", "alpha.md", "team/mid.md", "team/a.md"] {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "# Agent").unwrap();
+        }
+
+        let scan = || {
+            scan_link_dir(dir, &HashSet::new())
+                .unwrap()
+                .entries
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>()
+        };
+        let first = scan();
+        assert_eq!(
+            first,
+            vec!["Beta.md", "alpha.md", "team/a.md", "team/mid.md", "zeta.md"]
+        );
+        assert_eq!(scan(), first);
+    }
+
     #[test]
     fn test_config_json_format() {
         let temp_dir = TempDir::new().unwrap();
@@ -271,4 +1177,49 @@ mod tests {
         assert!(json_content.contains("\"type\": \"GitHub\""));
         assert!(json_content.contains("\"enabled\": true"));
     }
+
+    #[test]
+    fn test_merge_manifest() {
+        let local = |name: &str| {
+            Agent::new(
+                name.to_string(),
+                AgentSource::Local(PathBuf::from(format!(".ccagents/{}", name))),
+            )
+        };
+        let mut config = AgentsConfig::default();
+        config.add_agent(local("a.md")).unwrap();
+        config.add_agent(local("b.md")).unwrap();
+
+        let mut manifest = AgentsConfig::default();
+        let mut b = local("b.md");
+        b.enabled = false;
+        manifest.add_agent(b).unwrap();
+        manifest.add_agent(local("c.md")).unwrap();
+
+        let merged = config
+            .clone()
+            .merge_manifest(manifest.clone(), ManifestMerge::Union);
+        let names: Vec<&str> = merged.agents.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["a.md", "b.md", "c.md"]);
+        assert!(!merged.get_agent("b.md").unwrap().enabled);
+
+        let merged = config.merge_manifest(manifest, ManifestMerge::Override);
+        let names: Vec<&str> = merged.agents.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["b.md", "c.md"]);
+    }
+
+    #[test]
+    fn test_parse_manifest_inline_and_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let json = r#"{"agents": [{"name": "a.md", "source": {"type": "Local", "value": ".ccagents/a.md"}, "enabled": true}]}"#;
+
+        let inline = AgentsConfig::parse_manifest(json, temp_dir.path()).unwrap();
+        assert_eq!(inline.agents.len(), 1);
+
+        fs::write(temp_dir.path().join("manifest.json"), json).unwrap();
+        let from_file = AgentsConfig::parse_manifest("manifest.json", temp_dir.path()).unwrap();
+        assert_eq!(from_file.agents[0].name, "a.md");
+
+        assert!(AgentsConfig::parse_manifest("missing.json", temp_dir.path()).is_err());
+    }
 }
@@ -1,102 +1,1259 @@
-use anyhow::{Context, Result};
+use crate::agent::{expand_env_vars, sanitize_filename, Agent, AgentSource};
+use crate::archive::{self, ArchiveEntry, ArchiveKind};
+use crate::cache::{self, DownloadCache};
+use crate::config::AgentsConfig;
+use crate::error::CcagentsError;
+use crate::github::{self, GitHubFileUrl, GitHubRepoPath, GITHUB_TOKEN_ENV};
+use anyhow::Result;
 use colored::*;
 use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::redirect::Policy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
 use std::fs;
 use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use url::{Host, Url};
 
-pub async fn download_from_github(url: &str, target_dir: &Path) -> Result<String> {
-    let parsed_url = url::Url::parse(url)?;
+/// Redirects followed before a download is abandoned.
+const MAX_REDIRECTS: usize = 10;
 
-    if parsed_url.host_str() != Some("github.com") {
-        return Err(anyhow::anyhow!("Not a GitHub URL"));
+/// Settings that influence how agent files are downloaded.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// GitHub Enterprise hosts recognized in addition to github.com.
+    pub github_hosts: Vec<String>,
+    /// Hosts requests and redirects may go to; no other host is contacted.
+    /// See [`github::allowed_hosts`].
+    pub allowed_hosts: Vec<String>,
+    /// Cache consulted before downloading; `None` always downloads.
+    pub cache: Option<DownloadCache>,
+    /// Print download progress. Off by default, so the library prints
+    /// nothing; warnings are returned to the caller either way.
+    pub progress: bool,
+    /// Revalidate cached files with the server even while they are fresh.
+    pub refresh: bool,
+    /// Extra request headers; `${VAR}` references in values are expanded.
+    pub headers: Vec<(String, String)>,
+    /// Reject responses whose `Content-Type` does not fit the file instead
+    /// of warning.
+    pub strict: bool,
+}
+
+impl Default for DownloadOptions {
+    /// Allows only [`github::BUILTIN_ALLOWED_HOSTS`].
+    fn default() -> Self {
+        Self {
+            github_hosts: Vec::new(),
+            allowed_hosts: github::BUILTIN_ALLOWED_HOSTS
+                .iter()
+                .map(|h| h.to_string())
+                .collect(),
+            cache: None,
+            progress: false,
+            refresh: false,
+            headers: Vec::new(),
+            strict: false,
+        }
+    }
+}
+
+impl DownloadOptions {
+    pub fn from_config(config: &AgentsConfig) -> Self {
+        Self {
+            github_hosts: github::enterprise_hosts(&config.github_hosts),
+            allowed_hosts: github::allowed_hosts(&config.allowed_hosts),
+            cache: DownloadCache::default_dir().map(DownloadCache::new),
+            progress: false,
+            refresh: false,
+            headers: Vec::new(),
+            strict: false,
+        }
+    }
+}
+
+/// Default branches already looked up by this process, by repository API URL.
+static DEFAULT_BRANCHES: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+#[derive(Debug, Deserialize)]
+struct RepositoryInfo {
+    default_branch: String,
+}
+
+/// Returns `url` unchanged unless it names a repository file without a
+/// branch (`https://github.com/<owner>/<repo>/<path>`); then the repository's
+/// default branch is looked up and the file's `/blob/<branch>/` URL returned.
+pub async fn resolve_default_branch(url: &str, options: &DownloadOptions) -> Result<String> {
+    let expanded = expand_env_vars(url)?;
+    if GitHubFileUrl::parse(&expanded, &options.github_hosts).is_ok() {
+        return Ok(url.to_string());
+    }
+    let Ok(repo_path) = GitHubRepoPath::parse(&expanded, &options.github_hosts) else {
+        return Ok(url.to_string());
+    };
+
+    let branch = default_branch(&repo_path, options).await?;
+    if options.progress {
+        println!(
+            "  {} Using default branch '{}' of {}/{}",
+            "→".cyan(),
+            branch,
+            repo_path.owner,
+            repo_path.repo
+        );
+    }
+    Ok(repo_path.blob_url(&branch))
+}
+
+/// Looks up the repository's default branch through the GitHub API, sending
+/// `$GITHUB_TOKEN` when set and the API is github.com's, reached over HTTPS.
+/// Lookups are cached for the life of the process.
+pub async fn default_branch(
+    repo_path: &GitHubRepoPath,
+    options: &DownloadOptions,
+) -> Result<String> {
+    let api_url = repo_path.api_url();
+    check_allowed_host(&api_url, &options.allowed_hosts)?;
+    let cache = DEFAULT_BRANCHES.get_or_init(Default::default);
+    if let Some(branch) = cache.lock().unwrap().get(&api_url) {
+        return Ok(branch.clone());
     }
 
-    let segments: Vec<&str> = parsed_url
-        .path()
-        .trim_start_matches('/')
-        .split('/')
-        .filter(|s| !s.is_empty())
-        .collect();
+    let network =
+        |e: reqwest::Error| CcagentsError::Network(format!("Failed to look up {}: {}", api_url, e));
+    // The token is for github.com; an Enterprise server, or anyone able to
+    // read plain HTTP, must never see it
+    let public_api = Url::parse(&api_url)
+        .is_ok_and(|url| url.scheme() == "https" && url.host_str() == Some(github::API_HOST));
+    let token = std::env::var(GITHUB_TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty() && public_api);
+
+    // The GitHub API rejects requests without a User-Agent
+    let client = reqwest::Client::builder()
+        .redirect(redirect_policy(options.allowed_hosts.clone()))
+        .build()?;
+    let mut request = client
+        .get(&api_url)
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+        )
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+    if let Some(token) = &token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.map_err(network)?;
 
-    // Require at least: owner/repo/blob/branch/file.ext
-    if segments.len() < 5 {
+    let status = response.status();
+    // GitHub answers 404 rather than 403 for private repositories
+    if matches!(status.as_u16(), 401 | 403 | 404) && token.is_none() {
+        let hint = if public_api {
+            format!("set {} to a token with access to it", GITHUB_TOKEN_ENV)
+        } else {
+            format!(
+                "name the branch in the URL (/blob/<branch>/...); {} is only sent to {}",
+                GITHUB_TOKEN_ENV,
+                github::API_HOST
+            )
+        };
         return Err(anyhow::anyhow!(
-            "Invalid GitHub URL. Please provide a direct link to a file.\n\
-             Example: https://github.com/user/repo/blob/main/agent.md"
+            "Repository {}/{} was not found (HTTP {}). If it is private, {}",
+            repo_path.owner,
+            repo_path.repo,
+            status.as_u16(),
+            hint
         ));
     }
-
-    // Check if it's a file URL (contains /blob/)
-    if segments[2] != "blob" {
+    if !status.is_success() {
         return Err(anyhow::anyhow!(
-            "Only direct file links are supported.\n\
-             Please navigate to the specific agent file on GitHub and use that URL.\n\
-             Example: https://github.com/user/repo/blob/main/agent.md"
+            "Failed to look up the default branch of {}/{}: HTTP {}",
+            repo_path.owner,
+            repo_path.repo,
+            status
         ));
     }
 
-    let owner = segments[0];
-    let repo = segments[1];
-    let branch = segments[3];
-    let file_path: Vec<&str> = segments[4..].to_vec();
-    let full_path = file_path.join("/");
-    let filename = file_path
-        .last()
-        .ok_or_else(|| anyhow::anyhow!("No filename in URL"))?
-        .to_string();
+    let info = response.json::<RepositoryInfo>().await.map_err(network)?;
+    cache
+        .lock()
+        .unwrap()
+        .insert(api_url, info.default_branch.clone());
+    Ok(info.default_branch)
+}
+
+/// Downloads the source of a GitHub agent into its managed directory, where
+/// `Agent::get_local_path` expects it, and returns warnings as
+/// [`download_github_file`] does. Local agents cannot be downloaded.
+pub async fn download_agent(
+    agent: &Agent,
+    project_root: &Path,
+    options: &DownloadOptions,
+) -> Result<Vec<String>> {
+    match &agent.source {
+        AgentSource::GitHub(url) => {
+            let options = DownloadOptions {
+                headers: agent.headers.clone(),
+                ..options.clone()
+            };
+            download_github_file(url, &agent.get_local_path(project_root), &options).await
+        }
+        AgentSource::Local(path) => Err(anyhow::anyhow!(
+            "Agent '{}' has a local source ({:?}) that cannot be downloaded",
+            agent.name,
+            path
+        )),
+    }
+}
+
+/// Downloads a GitHub file into `target_dir`, keeping the file name from the
+/// URL. Returns that file name; warnings are dropped.
+#[allow(dead_code)]
+pub async fn download_from_github(
+    url: &str,
+    target_dir: &Path,
+    options: &DownloadOptions,
+) -> Result<String> {
+    let github_url = GitHubFileUrl::parse(&expand_env_vars(url)?, &options.github_hosts)?;
+    let filename = sanitize_filename(github_url.filename())?;
+
+    download_github_file(url, &target_dir.join(&filename), options).await?;
+
+    Ok(filename)
+}
+
+/// Downloads a GitHub file to `target_file`, whose name may differ from the
+/// one in the URL. Returns warnings for the caller to show, e.g. about an
+/// unexpected `Content-Type` or a cache that could not be updated.
+pub async fn download_github_file(
+    url: &str,
+    target_file: &Path,
+    options: &DownloadOptions,
+) -> Result<Vec<String>> {
+    let github_url = GitHubFileUrl::parse(&expand_env_vars(url)?, &options.github_hosts)?;
+    let filename = github_url.filename().to_string();
 
     // Convert to raw content URL
-    let raw_url = format!(
-        "https://raw.githubusercontent.com/{}/{}/{}/{}",
-        owner, repo, branch, full_path
-    );
+    let raw_url = github_url.raw_url();
+    // Checked up front so a disallowed host is refused even when cached
+    check_allowed_host(&raw_url, &options.allowed_hosts)?;
+
+    if let Some(target_dir) = target_file.parent() {
+        fs::create_dir_all(target_dir)?;
+    }
+
+    // Serve recently validated files straight from the cache. Content that
+    // fails its digest check is dropped and downloaded again
+    let variant = header_fingerprint(&options.headers)?;
+    let cached = options.cache.as_ref().and_then(|cache| {
+        let entry = cache.lookup(&raw_url, &variant)?;
+        let content = cache.read(&entry).ok()?;
+        Some((entry, content))
+    });
+    if let Some((entry, content)) = &cached {
+        if entry.is_fresh() && !options.refresh {
+            fs::write(target_file, content)?;
+            if options.progress {
+                println!("  {} Using cached: {}", "→".cyan(), filename);
+            }
+            return Ok(Vec::new());
+        }
+    }
 
-    println!("  {} Downloading: {}", "→".cyan(), filename);
+    if options.progress {
+        println!("  {} Downloading: {}", "→".cyan(), filename);
+    }
+
+    let etag = cached.as_ref().and_then(|(entry, _)| entry.etag.as_deref());
+    let response = send_get(&raw_url, options, etag).await?;
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&raw_url)
-        .send()
-        .await
-        .with_context(|| format!("Failed to fetch {}", raw_url))?;
+    // The server confirmed the cached copy is still current
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let (Some(cache), Some((entry, content))) = (&options.cache, &cached) {
+            fs::write(target_file, content)?;
+            cache.touch(entry).ok();
+            if options.progress {
+                println!("  {} Not modified, using cached copy", "→".cyan());
+            }
+            return Ok(Vec::new());
+        }
+    }
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(CcagentsError::UpstreamNotFound(raw_url).into());
+    }
 
     if !response.status().is_success() {
-        return Err(anyhow::anyhow!(
+        return Err(CcagentsError::Network(format!(
             "Failed to download file: HTTP {}\n\
              Make sure the file exists and the URL is correct.",
             response.status()
-        ));
+        ))
+        .into());
     }
 
-    let total_size = response.content_length().unwrap_or(0);
+    // Login walls and error pages are often served as HTML with status 200
+    let mut warnings = Vec::new();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if let Some(problem) = content_type_problem(content_type, &filename) {
+        if options.strict {
+            return Err(anyhow::anyhow!(
+                "{} at {}; check that the URL is accessible without logging in",
+                problem,
+                raw_url
+            ));
+        }
+        warnings.push(problem);
+    }
 
-    // Create progress bar
-    let pb = ProgressBar::new(total_size);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")?
-            .progress_chars("#>-"),
-    );
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let pb = if !options.progress {
+        ProgressBar::hidden()
+    } else {
+        progress_bar(response.content_length())?
+    };
 
-    // Create target file path
-    fs::create_dir_all(target_dir)?;
-    let target_file = target_dir.join(&filename);
-    let mut file = fs::File::create(&target_file)?;
+    let mut file = fs::File::create(target_file)?;
 
     let mut downloaded: u64 = 0;
+    let mut content = Vec::new();
     let mut stream = response.bytes_stream();
 
     while let Some(chunk) = stream.next().await {
-        let chunk = chunk.context("Failed to download chunk")?;
+        let chunk = chunk
+            .map_err(|e| CcagentsError::Network(format!("Failed to download chunk: {}", e)))?;
         file.write_all(&chunk)?;
-        let new = std::cmp::min(downloaded + (chunk.len() as u64), total_size);
-        downloaded = new;
-        pb.set_position(new);
+        if options.cache.is_some() {
+            content.extend_from_slice(&chunk);
+        }
+        downloaded += chunk.len() as u64;
+        // A server may send more than it announced; never show more than 100%
+        if pb.length().is_some_and(|length| downloaded > length) {
+            pb.set_length(downloaded);
+        }
+        pb.set_position(downloaded);
     }
 
     pb.finish_with_message("Download complete");
 
-    Ok(filename)
+    // A failing cache must never fail the download itself
+    if let Some(cache) = &options.cache {
+        let stored = cache
+            .store(&raw_url, &variant, etag, &content)
+            .and_then(|_| cache.evict(cache::MAX_AGE, cache::MAX_SIZE));
+        if let Err(e) = stored {
+            warnings.push(format!("Could not update download cache: {}", e));
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Largest archive downloaded by [`download_archive`].
+const MAX_ARCHIVE_SIZE: usize = 50 * 1024 * 1024;
+
+/// Downloads the `.zip` or `.tar.gz` GitHub file at `url` (typically a
+/// release asset) and returns the agent files in it; see
+/// [`archive::read_agents`]. Nothing is written to disk or the cache.
+pub async fn download_archive(
+    url: &str,
+    kind: ArchiveKind,
+    options: &DownloadOptions,
+) -> Result<Vec<ArchiveEntry>> {
+    let github_url = GitHubFileUrl::parse(&expand_env_vars(url)?, &options.github_hosts)?;
+    let raw_url = github_url.raw_url();
+
+    if options.progress {
+        println!("  {} Downloading: {}", "→".cyan(), github_url.filename());
+    }
+
+    let response = send_get(&raw_url, options, None).await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(CcagentsError::UpstreamNotFound(raw_url).into());
+    }
+    if !response.status().is_success() {
+        return Err(CcagentsError::Network(format!(
+            "Failed to download archive: HTTP {}",
+            response.status()
+        ))
+        .into());
+    }
+
+    let mut content = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk
+            .map_err(|e| CcagentsError::Network(format!("Failed to download chunk: {}", e)))?;
+        content.extend_from_slice(&chunk);
+        if content.len() > MAX_ARCHIVE_SIZE {
+            return Err(anyhow::anyhow!(
+                "Archive {} is larger than {} bytes",
+                raw_url,
+                MAX_ARCHIVE_SIZE
+            ));
+        }
+    }
+
+    archive::read_agents(kind, &content)
+}
+
+/// Sends a GET for `raw_url` with the configured headers, made conditional
+/// on `etag` when given.
+async fn send_get(
+    raw_url: &str,
+    options: &DownloadOptions,
+    etag: Option<&str>,
+) -> Result<reqwest::Response> {
+    check_allowed_host(raw_url, &options.allowed_hosts)?;
+
+    // Headers come from `.agents.json`, which is committed, so they may only
+    // bring in secrets by reference
+    for (name, value) in &options.headers {
+        if !value.contains("${") {
+            return Err(anyhow::anyhow!(
+                "Header '{}' has a literal value; store it in an environment variable and reference it as ${{VAR}} so it is not kept in .agents.json",
+                name
+            ));
+        }
+    }
+
+    // Release assets redirect to GitHub's CDN, so redirects must be followed
+    let forwards_headers = options
+        .headers
+        .iter()
+        .any(|(name, _)| !STRIPPED_ON_REDIRECT.contains(&name.to_ascii_lowercase().as_str()));
+    let client = reqwest::Client::builder()
+        .redirect(redirect_policy_with(
+            options.allowed_hosts.clone(),
+            forwards_headers,
+        ))
+        .build()?;
+    let mut request = client.get(raw_url);
+    for (name, value) in &options.headers {
+        request = request.header(name.as_str(), expand_env_vars(value)?);
+    }
+    if let Some(etag) = etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let response = request.send().await.map_err(|e| {
+        // A rejected redirect carries the reason as the error source
+        let reason = e
+            .source()
+            .filter(|_| e.is_redirect())
+            .map(|source| format!(" ({})", source))
+            .unwrap_or_default();
+        CcagentsError::Network(format!("Failed to fetch {}: {}{}", raw_url, e, reason))
+    })?;
+    Ok(response)
+}
+
+/// Fingerprint of the request headers, with their `${VAR}` references
+/// expanded, for [`cache::CacheEntry::variant`]: content fetched with other
+/// credentials is cached separately. Empty without headers.
+fn header_fingerprint(headers: &[(String, String)]) -> Result<String> {
+    if headers.is_empty() {
+        return Ok(String::new());
+    }
+    let mut lines = Vec::new();
+    for (name, value) in headers {
+        lines.push(format!(
+            "{}: {}",
+            name.to_ascii_lowercase(),
+            expand_env_vars(value)?
+        ));
+    }
+    lines.sort();
+    Ok(cache::sha256_hex(lines.join("\n").as_bytes()))
+}
+
+/// Whether the upstream file of a GitHub agent differs from its local copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamStatus {
+    Current,
+    Changed,
+    /// The file no longer exists upstream (HTTP 404).
+    Gone,
+}
+
+/// Compares the GitHub file at `url` with `local_file` without writing
+/// either the file or the cache. When the cached copy of the URL is the local
+/// file, its ETag makes the request conditional, so an unchanged file is
+/// confirmed with `304 Not Modified` instead of being sent again; otherwise
+/// the content is compared by SHA-256. A missing local file counts as changed.
+pub async fn check_github_file(
+    url: &str,
+    local_file: &Path,
+    options: &DownloadOptions,
+) -> Result<UpstreamStatus> {
+    let github_url = GitHubFileUrl::parse(&expand_env_vars(url)?, &options.github_hosts)?;
+    let raw_url = github_url.raw_url();
+    let local_digest = fs::read(local_file)
+        .ok()
+        .map(|content| cache::sha256_hex(&content));
+
+    let variant = header_fingerprint(&options.headers)?;
+    let etag = options
+        .cache
+        .as_ref()
+        .and_then(|c| c.lookup(&raw_url, &variant))
+        .filter(|entry| local_digest.as_deref() == Some(entry.digest.as_str()))
+        .and_then(|entry| entry.etag);
+    let response = send_get(&raw_url, options, etag.as_deref()).await?;
+
+    match response.status() {
+        reqwest::StatusCode::NOT_MODIFIED if etag.is_some() => Ok(UpstreamStatus::Current),
+        reqwest::StatusCode::NOT_FOUND => Ok(UpstreamStatus::Gone),
+        status if status.is_success() => {
+            let content = response.bytes().await.map_err(|e| {
+                CcagentsError::Network(format!("Failed to fetch {}: {}", raw_url, e))
+            })?;
+            if local_digest == Some(cache::sha256_hex(&content)) {
+                Ok(UpstreamStatus::Current)
+            } else {
+                Ok(UpstreamStatus::Changed)
+            }
+        }
+        status => Err(CcagentsError::Network(format!(
+            "Failed to check {}: HTTP {}",
+            raw_url, status
+        ))
+        .into()),
+    }
+}
+
+/// Describes why a response with `content_type` is unlikely to hold the
+/// agent file `filename`: HTML where Markdown was expected, or a type that is
+/// not text at all. `None` when it looks fine or no type was sent.
+fn content_type_problem(content_type: Option<&str>, filename: &str) -> Option<String> {
+    let mime = content_type?
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let markdown = Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"));
+
+    if mime == "text/html" && markdown {
+        Some(format!(
+            "{} was served as text/html, which is usually a login or error page rather than the agent",
+            filename
+        ))
+    } else if mime.starts_with("text/")
+        || mime == "application/json"
+        || mime == "application/octet-stream"
+    {
+        None
+    } else {
+        Some(format!(
+            "{} was served as {}, which does not look like a text file",
+            filename, mime
+        ))
+    }
+}
+
+/// Headers reqwest itself drops when a redirect leaves the host.
+const STRIPPED_ON_REDIRECT: [&str; 4] = [
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "www-authenticate",
+];
+
+/// Follows at most [`MAX_REDIRECTS`] redirects, each checked against
+/// `allowed_hosts` and by [`check_redirect`] against the URL originally
+/// requested.
+fn redirect_policy(allowed_hosts: Vec<String>) -> Policy {
+    redirect_policy_with(allowed_hosts, false)
+}
+
+/// Like [`redirect_policy`], but with `forwards_headers` (custom headers
+/// that reqwest would send along) redirects leaving the origin are refused,
+/// so the headers never reach another server.
+fn redirect_policy_with(allowed_hosts: Vec<String>, forwards_headers: bool) -> Policy {
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS {
+            return attempt.error(format!("more than {} redirects", MAX_REDIRECTS));
+        }
+
+        if !is_allowed_host(attempt.url(), &allowed_hosts) {
+            let reason = format!(
+                "refusing to follow redirect to {}, whose host is not in allowed_hosts",
+                attempt.url()
+            );
+            return attempt.error(reason);
+        }
+        if forwards_headers && attempt.url().origin() != attempt.previous()[0].origin() {
+            let reason = format!(
+                "refusing to send the agent's headers along a redirect to {}",
+                attempt.url()
+            );
+            return attempt.error(reason);
+        }
+        match check_redirect(&attempt.previous()[0], attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(reason) => attempt.error(reason),
+        }
+    })
+}
+
+/// Fails before any request is sent when `url`'s host is not in
+/// `allowed_hosts`, so a tampered `.agents.json` cannot make ccagents
+/// contact arbitrary servers.
+fn check_allowed_host(url: &str, allowed_hosts: &[String]) -> Result<()> {
+    let parsed = Url::parse(url)?;
+    if is_allowed_host(&parsed, allowed_hosts) {
+        return Ok(());
+    }
+
+    Err(anyhow::anyhow!(
+        "Refusing to download from {}: host '{}' is not in allowed_hosts ({}). Add it to ${} to allow it",
+        url,
+        parsed.host_str().unwrap_or_default(),
+        allowed_hosts.join(", "),
+        github::ALLOWED_HOSTS_ENV
+    ))
+}
+
+/// Whether `url`'s host (alone, or with its port) is in `allowed_hosts`.
+fn is_allowed_host(url: &Url, allowed_hosts: &[String]) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let with_port = url.port().map(|port| format!("{}:{}", host, port));
+    allowed_hosts.iter().any(|allowed| {
+        allowed.eq_ignore_ascii_case(host)
+            || with_port
+                .as_deref()
+                .is_some_and(|h| allowed.eq_ignore_ascii_case(h))
+    })
+}
+
+/// Redirects within the origin of the requested URL are followed as is.
+/// Redirects elsewhere must use HTTPS and must not name `localhost` or a
+/// loopback, private or link-local IP address. Only literal addresses are
+/// checked; host names are not resolved, so a name pointing at the local
+/// network is kept out by `allowed_hosts` rather than here.
+fn check_redirect(origin: &Url, target: &Url) -> std::result::Result<(), String> {
+    if target.origin() == origin.origin() {
+        return Ok(());
+    }
+
+    if target.scheme() != "https" {
+        return Err(format!(
+            "refusing to follow redirect to non-HTTPS URL {}",
+            target
+        ));
+    }
+
+    let local = match target.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => is_local_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_local_ip(IpAddr::V6(ip)),
+        None => true,
+    };
+    if local {
+        return Err(format!(
+            "refusing to follow redirect to local or private address {}",
+            target
+        ));
+    }
+
+    Ok(())
+}
+
+fn is_local_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_local_ipv4(ip),
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || (first & 0xfe00) == 0xfc00 // unique local
+                || (first & 0xffc0) == 0xfe80 // link-local
+                || ip.to_ipv4_mapped().is_some_and(is_local_ipv4)
+        }
+    }
+}
+
+fn is_local_ipv4(ip: Ipv4Addr) -> bool {
+    ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+}
+
+/// A determinate bar when the response length is known, otherwise a spinner
+/// with a byte counter and transfer rate.
+fn progress_bar(content_length: Option<u64>) -> Result<ProgressBar> {
+    let pb = match content_length.filter(|&length| length > 0) {
+        Some(length) => ProgressBar::new(length).with_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "[{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+                )?
+                .progress_chars("#>-"),
+        ),
+        None => ProgressBar::new_spinner().with_style(
+            ProgressStyle::default_spinner()
+                .template("[{elapsed_precise}] {spinner:.cyan} {bytes} ({bytes_per_sec})")?,
+        ),
+    };
+
+    Ok(pb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_resolve_default_branch() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v3/repos/org/repo")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"name": "repo", "default_branch": "trunk"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            ..Default::default()
+        };
+
+        let url = format!("{}/org/repo/agents/agent.md", server.url());
+        let resolved = resolve_default_branch(&url, &options).await.unwrap();
+        assert_eq!(
+            resolved,
+            format!("{}/org/repo/blob/trunk/agents/agent.md", server.url())
+        );
+        let github_url = GitHubFileUrl::parse(&resolved, &options.github_hosts).unwrap();
+        assert_eq!(
+            github_url.raw_url(),
+            format!("{}/org/repo/raw/trunk/agents/agent.md", server.url())
+        );
+
+        // The second lookup is served from the cache
+        resolve_default_branch(&url, &options).await.unwrap();
+        mock.assert_async().await;
+
+        // URLs that already name a branch are left alone
+        let blob_url = format!("{}/org/repo/blob/main/agent.md", server.url());
+        assert_eq!(
+            resolve_default_branch(&blob_url, &options).await.unwrap(),
+            blob_url
+        );
+
+        // Private repositories look missing without a token
+        server
+            .mock("GET", "/api/v3/repos/org/private")
+            .with_status(404)
+            .create_async()
+            .await;
+        // The token is never sent to an Enterprise host, so the hint
+        // applies whether or not it is set
+        let url = format!("{}/org/private/agent.md", server.url());
+        let err = resolve_default_branch(&url, &options).await.unwrap_err();
+        assert!(err.to_string().contains("name the branch in the URL"));
+    }
+
+    #[tokio::test]
+    async fn test_download_from_enterprise_host() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agents/agent.md")
+            .with_status(200)
+            .with_body("# Enterprise agent")
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            ..DownloadOptions::default()
+        };
+        let url = format!("{}/org/repo/blob/main/agents/agent.md", server.url());
+
+        let filename = download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(filename, "agent.md");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("agent.md")).unwrap(),
+            "# Enterprise agent"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_html_for_markdown_warns_or_fails_under_strict() {
+        let mut server = mockito::Server::new_async().await;
+        let page = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(200)
+            .with_header("content-type", "text/html; charset=utf-8")
+            .with_body("<html>Sign in</html>")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+        let target = temp_dir.path().join("agent.md");
+        let mut options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            ..DownloadOptions::default()
+        };
+
+        // By default the file is kept with a warning
+        let warnings = download_github_file(&url, &target, &options).await.unwrap();
+        assert!(target.exists());
+        assert!(warnings[0].contains("served as text/html"));
+        fs::remove_file(&target).unwrap();
+
+        options.strict = true;
+        let err = download_github_file(&url, &target, &options)
+            .await
+            .unwrap_err();
+        page.assert_async().await;
+        assert!(err.to_string().contains("served as text/html"), "{}", err);
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_content_type_problem() {
+        assert!(content_type_problem(Some("text/plain; charset=utf-8"), "a.md").is_none());
+        assert!(content_type_problem(Some("application/octet-stream"), "a.md").is_none());
+        assert!(content_type_problem(Some("application/json"), "a.json").is_none());
+        assert!(content_type_problem(None, "a.md").is_none());
+        assert!(content_type_problem(Some("text/html"), "page.html").is_none());
+        assert!(content_type_problem(Some("text/html"), "a.md").is_some());
+        assert!(content_type_problem(Some("image/png"), "a.md").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_redirect_to_http() {
+        let mut server = mockito::Server::new_async().await;
+        let redirect = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(302)
+            .with_header("location", "http://example.com/agent.md")
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port(), "example.com".to_string()],
+            ..DownloadOptions::default()
+        };
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+
+        let err = download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap_err();
+
+        redirect.assert_async().await;
+        assert!(
+            err.to_string()
+                .contains("non-HTTPS URL http://example.com/agent.md"),
+            "{}",
+            err
+        );
+        assert!(!temp_dir.path().join("agent.md").exists());
+    }
+
+    #[test]
+    fn test_check_redirect() {
+        let origin = Url::parse("https://github.com/org/repo/raw/main/a.md").unwrap();
+        let check = |target: &str| check_redirect(&origin, &Url::parse(target).unwrap());
+
+        assert!(check("https://github.com/other").is_ok());
+        assert!(check("https://objects.githubusercontent.com/asset").is_ok());
+        assert!(check("http://objects.githubusercontent.com/asset").is_err());
+        assert!(check("https://127.0.0.1/").is_err());
+        assert!(check("https://10.0.0.5/").is_err());
+        assert!(check("https://169.254.169.254/latest/meta-data").is_err());
+        assert!(check("https://[::1]/").is_err());
+        assert!(check("https://localhost/").is_err());
+        assert!(check("file:///etc/passwd").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_download_release_asset_follows_redirect() {
+        let mut server = mockito::Server::new_async().await;
+        let cdn_url = format!("{}/cdn/asset-1234", server.url());
+        let release = server
+            .mock("GET", "/org/repo/releases/download/v1.0.0/agent.md")
+            .with_status(302)
+            .with_header("location", &cdn_url)
+            .create_async()
+            .await;
+        let cdn = server
+            .mock("GET", "/cdn/asset-1234")
+            .with_status(200)
+            .with_body("# Released agent")
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            ..DownloadOptions::default()
+        };
+        let url = format!(
+            "{}/org/repo/releases/download/v1.0.0/agent.md",
+            server.url()
+        );
+
+        let filename = download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap();
+
+        release.assert_async().await;
+        cdn.assert_async().await;
+        assert_eq!(filename, "agent.md");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("agent.md")).unwrap(),
+            "# Released agent"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_sends_configured_headers() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .match_header("x-api-key", "secret-123")
+            .match_header("x-team", "platform")
+            .with_status(200)
+            .with_body("# Gated agent")
+            .create_async()
+            .await;
+
+        std::env::set_var("CCAGENTS_TEST_API_KEY", "secret-123");
+        std::env::set_var("CCAGENTS_TEST_TEAM", "platform");
+        let temp_dir = TempDir::new().unwrap();
+        let mut options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            headers: vec![
+                (
+                    "X-Api-Key".to_string(),
+                    "${CCAGENTS_TEST_API_KEY}".to_string(),
+                ),
+                ("X-Team".to_string(), "${CCAGENTS_TEST_TEAM}".to_string()),
+            ],
+            ..DownloadOptions::default()
+        };
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+
+        download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("agent.md")).unwrap(),
+            "# Gated agent"
+        );
+
+        // Literal values would keep secrets in .agents.json
+        options.headers = vec![("X-Api-Key".to_string(), "secret-123".to_string())];
+        let err = download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("literal value"));
+    }
+
+    #[tokio::test]
+    async fn test_download_headers_not_sent_across_origins() {
+        let mut server = mockito::Server::new_async().await;
+        let redirect = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(302)
+            .with_header("location", "https://example.com/agent.md")
+            .create_async()
+            .await;
+
+        std::env::set_var("CCAGENTS_TEST_REDIRECT_KEY", "secret-456");
+        let temp_dir = TempDir::new().unwrap();
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port(), "example.com".to_string()],
+            headers: vec![(
+                "X-Api-Key".to_string(),
+                "${CCAGENTS_TEST_REDIRECT_KEY}".to_string(),
+            )],
+            ..DownloadOptions::default()
+        };
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+
+        let err = download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap_err();
+        redirect.assert_async().await;
+        assert!(
+            err.to_string()
+                .contains("refusing to send the agent's headers"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_without_content_length() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(200)
+            .with_chunked_body(|w| {
+                w.write_all(b"# Streamed ")?;
+                w.write_all(b"agent")
+            })
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            ..DownloadOptions::default()
+        };
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+
+        download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("agent.md")).unwrap(),
+            "# Streamed agent"
+        );
+    }
+
+    #[test]
+    fn test_progress_bar_length() {
+        assert_eq!(progress_bar(None).unwrap().length(), None);
+        assert_eq!(progress_bar(Some(0)).unwrap().length(), None);
+        assert_eq!(progress_bar(Some(42)).unwrap().length(), Some(42));
+    }
+
+    #[tokio::test]
+    async fn test_second_download_served_from_cache() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_status(200)
+            .with_header("etag", "\"v1\"")
+            .with_body("# Cached agent")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            cache: Some(DownloadCache::new(temp_dir.path().join("cache"))),
+            ..Default::default()
+        };
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+
+        let first = temp_dir.path().join("first");
+        let second = temp_dir.path().join("second");
+        download_from_github(&url, &first, &options).await.unwrap();
+        download_from_github(&url, &second, &options).await.unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(
+            fs::read_to_string(second.join("agent.md")).unwrap(),
+            "# Cached agent"
+        );
+
+        // A cached file is not served once its host is no longer allowed
+        let narrowed = DownloadOptions {
+            allowed_hosts: vec!["github.com".to_string()],
+            ..options.clone()
+        };
+        let err = download_from_github(&url, &temp_dir.path().join("third"), &narrowed)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not in allowed_hosts"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_cache_revalidated_with_etag() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path().join("cache"));
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        let raw_url = format!("{}/org/repo/raw/main/agent.md", server.url());
+        let mut entry = cache
+            .store(&raw_url, "", Some("\"v1\"".to_string()), b"# Cached")
+            .unwrap();
+
+        // Age the stored entry past the freshness window
+        entry.fetched_at -= cache::FRESH_FOR.as_secs() + 1;
+        let stale_json = serde_json::to_string(&entry).unwrap();
+        for file in fs::read_dir(temp_dir.path().join("cache/entries")).unwrap() {
+            fs::write(file.unwrap().path(), &stale_json).unwrap();
+        }
+
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+        download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("agent.md")).unwrap(),
+            "# Cached"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_github_file() {
+        let mut server = mockito::Server::new_async().await;
+        let conditional = server
+            .mock("GET", "/org/repo/raw/main/cached.md")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/org/repo/raw/main/stale.md")
+            .with_body("# New")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/org/repo/raw/main/current.md")
+            .with_body("# Same")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/org/repo/raw/main/gone.md")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let cache = DownloadCache::new(temp_dir.path().join("cache"));
+        let options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec![server.host_with_port()],
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        cache
+            .store(
+                &format!("{}/org/repo/raw/main/cached.md", server.url()),
+                "",
+                Some("\"v1\"".to_string()),
+                b"# Cached",
+            )
+            .unwrap();
+        for (name, content) in [
+            ("cached.md", "# Cached"),
+            ("stale.md", "# Old"),
+            ("current.md", "# Same"),
+            ("gone.md", "# Kept"),
+        ] {
+            fs::write(temp_dir.path().join(name), content).unwrap();
+        }
+
+        let check = |name: &str| {
+            let url = format!("{}/org/repo/blob/main/{}", server.url(), name);
+            let local = temp_dir.path().join(name);
+            let options = &options;
+            async move { check_github_file(&url, &local, options).await.unwrap() }
+        };
+        assert_eq!(check("cached.md").await, UpstreamStatus::Current);
+        assert_eq!(check("stale.md").await, UpstreamStatus::Changed);
+        assert_eq!(check("current.md").await, UpstreamStatus::Current);
+        assert_eq!(check("gone.md").await, UpstreamStatus::Gone);
+
+        conditional.assert_async().await;
+        // Nothing was written
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("stale.md")).unwrap(),
+            "# Old"
+        );
+        assert_eq!(cache.entries().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_download_only_from_allowed_hosts() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/org/repo/raw/main/agent.md")
+            .with_body("# Agent")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+        let mut options = DownloadOptions {
+            github_hosts: vec![server.host_with_port()],
+            allowed_hosts: vec!["github.com".to_string()],
+            ..DownloadOptions::default()
+        };
+
+        // Rejected without contacting the server
+        let err = download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not in allowed_hosts"));
+        assert!(!temp_dir.path().join("agent.md").exists());
+
+        options.allowed_hosts.push(server.host_with_port());
+        download_from_github(&url, temp_dir.path(), &options)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_unknown_host() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = download_from_github(
+            "https://github.mycorp.com/org/repo/blob/main/agent.md",
+            temp_dir.path(),
+            &DownloadOptions::default(),
+        )
+        .await;
+
+        assert!(result.unwrap_err().to_string().contains("Not a GitHub URL"));
+    }
 }
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Process exit codes, part of the CLI contract for scripts and CI.
+pub mod exit_code {
+    #[allow(dead_code)]
+    pub const SUCCESS: i32 = 0;
+    pub const ERROR: i32 = 1;
+    pub const ISSUES_FOUND: i32 = 2;
+    pub const NETWORK: i32 = 3;
+    pub const CONFIG_NOT_FOUND: i32 = 4;
+    pub const NO_AGENTS: i32 = 5;
+}
+
+/// Errors that map to a specific exit code. Anything else exits with
+/// [`exit_code::ERROR`].
+#[derive(Debug)]
+pub enum CcagentsError {
+    /// `doctor` found issues that were not fixed.
+    IssuesFound(usize),
+    /// A download or other network request failed.
+    Network(String),
+    /// The server answered 404 for the file at the given URL.
+    UpstreamNotFound(String),
+    /// `.agents.json` does not exist at the given path.
+    ConfigNotFound(PathBuf),
+    /// No agent with the given name is configured.
+    AgentNotFound(String),
+    /// `--require-agents` was given but no agents are configured.
+    NoAgentsConfigured,
+    /// `--fail-on-unmanaged` was given and `.claude/agents` holds these
+    /// unmanaged files.
+    UnmanagedFiles(Vec<String>),
+}
+
+impl CcagentsError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CcagentsError::IssuesFound(_) => exit_code::ISSUES_FOUND,
+            CcagentsError::Network(_) | CcagentsError::UpstreamNotFound(_) => exit_code::NETWORK,
+            CcagentsError::ConfigNotFound(_) => exit_code::CONFIG_NOT_FOUND,
+            CcagentsError::AgentNotFound(_) => exit_code::ERROR,
+            CcagentsError::NoAgentsConfigured => exit_code::NO_AGENTS,
+            CcagentsError::UnmanagedFiles(_) => exit_code::ISSUES_FOUND,
+        }
+    }
+}
+
+impl fmt::Display for CcagentsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CcagentsError::IssuesFound(count) => write!(
+                f,
+                "{} issue{} found but not fixed",
+                count,
+                if *count == 1 { "" } else { "s" }
+            ),
+            CcagentsError::Network(message) => write!(f, "{}", message),
+            CcagentsError::UpstreamNotFound(url) => write!(
+                f,
+                "Failed to download file: HTTP 404 Not Found ({})\n\
+                 Make sure the file exists and the URL is correct.",
+                url
+            ),
+            CcagentsError::ConfigNotFound(path) => write!(
+                f,
+                "No configuration found at {:?}. Run 'ccagents add <source>' to create one.",
+                path
+            ),
+            CcagentsError::AgentNotFound(name) => {
+                write!(f, "Agent '{}' not found in .agents.json", name)
+            }
+            CcagentsError::NoAgentsConfigured => write!(
+                f,
+                "No agents configured in .agents.json (required by --require-agents)"
+            ),
+            CcagentsError::UnmanagedFiles(names) => write!(
+                f,
+                "{} unmanaged file{} in .claude/agents: {}",
+                names.len(),
+                if names.len() == 1 { "" } else { "s" },
+                names.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CcagentsError {}
+
+/// Returns the exit code for `error`, looking through any added context.
+pub fn exit_code_for(error: &anyhow::Error) -> i32 {
+    error
+        .chain()
+        .find_map(|e| e.downcast_ref::<CcagentsError>())
+        .map(CcagentsError::exit_code)
+        .unwrap_or(exit_code::ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_for() {
+        let error = anyhow::Error::new(CcagentsError::Network("timeout".to_string()));
+        assert_eq!(exit_code_for(&error), exit_code::NETWORK);
+
+        let wrapped = Err::<(), _>(CcagentsError::IssuesFound(2))
+            .context("Doctor failed")
+            .unwrap_err();
+        assert_eq!(exit_code_for(&wrapped), exit_code::ISSUES_FOUND);
+
+        assert_eq!(exit_code_for(&anyhow::anyhow!("boom")), exit_code::ERROR);
+    }
+}
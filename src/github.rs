@@ -0,0 +1,561 @@
+use anyhow::Result;
+
+/// Environment variable listing extra GitHub Enterprise hosts (comma separated).
+pub const GITHUB_HOSTS_ENV: &str = "CCAGENTS_GITHUB_HOSTS";
+
+/// Environment variable listing hosts downloads may contact (comma
+/// separated), in addition to [`BUILTIN_ALLOWED_HOSTS`].
+pub const ALLOWED_HOSTS_ENV: &str = "CCAGENTS_ALLOWED_HOSTS";
+
+/// Hosts downloads may contact without further configuration: github.com
+/// and the hosts it serves raw files, API lookups and release assets from.
+pub const BUILTIN_ALLOWED_HOSTS: &[&str] = &[
+    "github.com",
+    "api.github.com",
+    "raw.githubusercontent.com",
+    "objects.githubusercontent.com",
+    "release-assets.githubusercontent.com",
+];
+
+/// Environment variable holding a token for the GitHub API, needed to look
+/// up private repositories. It is only ever sent to [`API_HOST`] over HTTPS.
+pub const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// Host of the github.com API.
+pub const API_HOST: &str = "api.github.com";
+
+const PUBLIC_HOST: &str = "github.com";
+
+/// Prefix of the `gh:owner/repo/path[@ref]` shorthand for github.com files.
+pub const SHORTHAND_PREFIX: &str = "gh:";
+
+/// File extensions accepted for GitHub agents when none are configured.
+pub const DEFAULT_ALLOWED_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// A parsed GitHub file URL of the form
+/// `https://<host>/<owner>/<repo>/blob/<branch>/<path>`, or a release asset
+/// URL `https://<host>/<owner>/<repo>/releases/download/<tag>/<asset>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubFileUrl {
+    pub scheme: String,
+    /// Host including a non-default port, e.g. `github.mycorp.com:8443`.
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+    /// Branch, tag or commit. For release assets this is the release tag.
+    pub branch: String,
+    pub path: Vec<String>,
+    /// Whether the URL points at a release asset rather than a repository file.
+    pub release_asset: bool,
+}
+
+impl GitHubFileUrl {
+    /// Parses an (already env-expanded) URL. `enterprise_hosts` lists the
+    /// GitHub Enterprise hosts recognized in addition to github.com.
+    pub fn parse(url: &str, enterprise_hosts: &[String]) -> Result<Self> {
+        let parsed_url = url::Url::parse(url)?;
+        let host = authority(&parsed_url);
+
+        if !is_github_host(&host, enterprise_hosts) {
+            return Err(anyhow::anyhow!("Not a GitHub URL"));
+        }
+
+        let segments: Vec<&str> = parsed_url
+            .path()
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        // Release assets: owner/repo/releases/download/tag/asset
+        if segments.len() == 6 && segments[2] == "releases" && segments[3] == "download" {
+            return Ok(Self {
+                scheme: parsed_url.scheme().to_string(),
+                host,
+                owner: segments[0].to_string(),
+                repo: segments[1].to_string(),
+                branch: segments[4].to_string(),
+                path: vec![segments[5].to_string()],
+                release_asset: true,
+            });
+        }
+
+        // Require at least: owner/repo/blob/branch/file.ext
+        if segments.len() < 5 || segments[2] != "blob" {
+            return Err(anyhow::anyhow!(
+                "Only direct file links and release assets are supported. Please provide a URL like:\n\
+                 https://github.com/user/repo/blob/main/agent.md\n\
+                 https://github.com/user/repo/releases/download/v1.0.0/agent.md"
+            ));
+        }
+
+        Ok(Self {
+            scheme: parsed_url.scheme().to_string(),
+            host,
+            owner: segments[0].to_string(),
+            repo: segments[1].to_string(),
+            branch: segments[3].to_string(),
+            path: segments[4..].iter().map(|s| s.to_string()).collect(),
+            release_asset: false,
+        })
+    }
+
+    pub fn filename(&self) -> &str {
+        self.path.last().map(String::as_str).unwrap_or_default()
+    }
+
+    /// Returns the repository path as a single file name, e.g.
+    /// `agents/universal/backend.md` becomes `agents-universal-backend.md`.
+    /// Characters other than ASCII alphanumerics, `.`, `_` and `-` are
+    /// replaced with `-`.
+    pub fn flattened_name(&self) -> String {
+        self.path
+            .join("-")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    }
+
+    pub fn is_enterprise(&self) -> bool {
+        !self.host.eq_ignore_ascii_case(PUBLIC_HOST)
+    }
+
+    /// Returns the URL serving the raw file content. Public GitHub uses
+    /// raw.githubusercontent.com, Enterprise hosts serve `/<owner>/<repo>/raw/...`.
+    /// Release asset URLs are already raw; GitHub redirects them to its CDN.
+    pub fn raw_url(&self) -> String {
+        let full_path = self.path.join("/");
+
+        if self.release_asset {
+            format!(
+                "{}://{}/{}/{}/releases/download/{}/{}",
+                self.scheme, self.host, self.owner, self.repo, self.branch, full_path
+            )
+        } else if self.is_enterprise() {
+            format!(
+                "{}://{}/{}/{}/raw/{}/{}",
+                self.scheme, self.host, self.owner, self.repo, self.branch, full_path
+            )
+        } else {
+            format!(
+                "https://raw.githubusercontent.com/{}/{}/{}/{}",
+                self.owner, self.repo, self.branch, full_path
+            )
+        }
+    }
+}
+
+/// A GitHub URL naming a repository file without a branch, of the form
+/// `https://<host>/<owner>/<repo>/<path>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubRepoPath {
+    pub scheme: String,
+    /// Host including a non-default port.
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+    pub path: Vec<String>,
+}
+
+impl GitHubRepoPath {
+    /// Parses an (already env-expanded) URL. URLs with a `blob`, `tree`,
+    /// `raw` or `releases` segment after the repository are not branchless
+    /// and are rejected.
+    pub fn parse(url: &str, enterprise_hosts: &[String]) -> Result<Self> {
+        let parsed_url = url::Url::parse(url)?;
+        let host = authority(&parsed_url);
+
+        if !is_github_host(&host, enterprise_hosts) {
+            return Err(anyhow::anyhow!("Not a GitHub URL"));
+        }
+
+        let segments: Vec<&str> = parsed_url
+            .path()
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        if segments.len() < 3 || matches!(segments[2], "blob" | "tree" | "raw" | "releases") {
+            return Err(anyhow::anyhow!("Not a branchless repository file URL"));
+        }
+
+        Ok(Self {
+            scheme: parsed_url.scheme().to_string(),
+            host,
+            owner: segments[0].to_string(),
+            repo: segments[1].to_string(),
+            path: segments[2..].iter().map(|s| s.to_string()).collect(),
+        })
+    }
+
+    /// Returns the GitHub API endpoint describing the repository. Enterprise
+    /// hosts serve the API under `/api/v3`.
+    pub fn api_url(&self) -> String {
+        if self.host.eq_ignore_ascii_case(PUBLIC_HOST) {
+            format!("https://{}/repos/{}/{}", API_HOST, self.owner, self.repo)
+        } else {
+            format!(
+                "{}://{}/api/v3/repos/{}/{}",
+                self.scheme, self.host, self.owner, self.repo
+            )
+        }
+    }
+
+    /// Returns the file's URL on `branch`.
+    pub fn blob_url(&self, branch: &str) -> String {
+        format!(
+            "{}://{}/{}/{}/blob/{}/{}",
+            self.scheme,
+            self.host,
+            self.owner,
+            self.repo,
+            branch,
+            self.path.join("/")
+        )
+    }
+}
+
+/// Expands the shorthand `gh:owner/repo/path/agent.md[@ref]` to the file's
+/// github.com URL: `/blob/<ref>/` when a ref is given, else the branchless
+/// form that [`GitHubRepoPath`] resolves against the default branch. Returns
+/// `None` for sources without the `gh:` prefix.
+pub fn expand_shorthand(source: &str) -> Result<Option<String>> {
+    let Some(shorthand) = source.strip_prefix(SHORTHAND_PREFIX) else {
+        return Ok(None);
+    };
+
+    let (path, git_ref) = match shorthand.rsplit_once('@') {
+        Some((path, git_ref)) if !git_ref.is_empty() => (path, Some(git_ref)),
+        Some(_) => return Err(anyhow::anyhow!("'{}' has an empty ref after '@'", source)),
+        None => (shorthand, None),
+    };
+    let segments: Vec<&str> = path.split('/').collect();
+    if segments.len() < 3 || segments.iter().any(|s| s.is_empty()) {
+        return Err(anyhow::anyhow!(
+            "'{}' is not a valid shorthand. Use gh:owner/repo/path/agent.md or gh:owner/repo/path/agent.md@ref",
+            source
+        ));
+    }
+
+    let (owner, repo, file) = (segments[0], segments[1], segments[2..].join("/"));
+    Ok(Some(match git_ref {
+        Some(git_ref) => format!(
+            "https://{}/{}/{}/blob/{}/{}",
+            PUBLIC_HOST, owner, repo, git_ref, file
+        ),
+        None => format!("https://{}/{}/{}/{}", PUBLIC_HOST, owner, repo, file),
+    }))
+}
+
+/// Returns the recognized Enterprise hosts: those in `configured` plus any
+/// listed in `CCAGENTS_GITHUB_HOSTS`.
+pub fn enterprise_hosts(configured: &[String]) -> Vec<String> {
+    with_env_hosts(configured, GITHUB_HOSTS_ENV)
+}
+
+/// Returns the hosts downloads may contact. Only the user's environment can
+/// widen the list: [`BUILTIN_ALLOWED_HOSTS`] plus the hosts listed in
+/// `CCAGENTS_ALLOWED_HOSTS` and `CCAGENTS_GITHUB_HOSTS`. A non-empty
+/// `project` list (from `.agents.json`, which may come from an untrusted
+/// clone) can only narrow them; hosts it names beyond those are ignored.
+pub fn allowed_hosts(project: &[String]) -> Vec<String> {
+    let mut trusted: Vec<String> = BUILTIN_ALLOWED_HOSTS
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+    trusted = with_env_hosts(&trusted, ALLOWED_HOSTS_ENV);
+    trusted = with_env_hosts(&trusted, GITHUB_HOSTS_ENV);
+    #[cfg(test)]
+    TEST_HOSTS.with(|hosts| trusted.extend(hosts.borrow().iter().cloned()));
+
+    if project.is_empty() {
+        return trusted;
+    }
+    trusted.retain(|host| project.iter().any(|p| p.eq_ignore_ascii_case(host)));
+    trusted
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_HOSTS: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Trusts `host` (e.g. a mock server) in [`allowed_hosts`] for the rest of
+/// the calling test, like `CCAGENTS_ALLOWED_HOSTS` would, without touching
+/// the process environment shared with other tests.
+#[cfg(test)]
+pub fn trust_test_host(host: &str) {
+    TEST_HOSTS.with(|hosts| hosts.borrow_mut().push(host.to_string()));
+}
+
+fn with_env_hosts(configured: &[String], env: &str) -> Vec<String> {
+    let mut hosts = configured.to_vec();
+
+    if let Ok(value) = std::env::var(env) {
+        hosts.extend(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|h| !h.is_empty())
+                .map(str::to_string),
+        );
+    }
+
+    hosts
+}
+
+pub fn is_github_host(host: &str, enterprise_hosts: &[String]) -> bool {
+    host.eq_ignore_ascii_case(PUBLIC_HOST)
+        || enterprise_hosts
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(host))
+}
+
+/// Returns whether `url` points at github.com or a recognized Enterprise host.
+pub fn is_github_url(url: &str, enterprise_hosts: &[String]) -> bool {
+    url::Url::parse(url)
+        .map(|parsed| is_github_host(&authority(&parsed), enterprise_hosts))
+        .unwrap_or(false)
+}
+
+/// Returns a warning when `filename` does not have one of the `allowed`
+/// extensions (compared case-insensitively, with or without a leading dot).
+/// An empty `allowed` list means [`DEFAULT_ALLOWED_EXTENSIONS`].
+pub fn unexpected_extension(filename: &str, allowed: &[String]) -> Option<String> {
+    let allowed: Vec<String> = if allowed.is_empty() {
+        DEFAULT_ALLOWED_EXTENSIONS
+            .iter()
+            .map(|e| e.to_string())
+            .collect()
+    } else {
+        allowed
+            .iter()
+            .map(|e| e.trim_start_matches('.').to_lowercase())
+            .collect()
+    };
+
+    let extension = std::path::Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+
+    match extension {
+        Some(ext) if allowed.contains(&ext) => None,
+        Some(ext) => Some(format!(
+            "'{}' has extension '.{}', expected one of: {}",
+            filename,
+            ext,
+            allowed.join(", ")
+        )),
+        None => Some(format!(
+            "'{}' has no file extension, expected one of: {}",
+            filename,
+            allowed.join(", ")
+        )),
+    }
+}
+
+fn authority(url: &url::Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_public_url() {
+        let url = GitHubFileUrl::parse(
+            "https://github.com/user/repo/blob/main/agents/backend.md",
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(url.owner, "user");
+        assert_eq!(url.repo, "repo");
+        assert_eq!(url.branch, "main");
+        assert_eq!(url.filename(), "backend.md");
+        assert!(!url.is_enterprise());
+        assert_eq!(
+            url.raw_url(),
+            "https://raw.githubusercontent.com/user/repo/main/agents/backend.md"
+        );
+    }
+
+    #[test]
+    fn test_parse_repo_path_url() {
+        let url =
+            GitHubRepoPath::parse("https://github.com/user/repo/agents/backend.md", &[]).unwrap();
+
+        assert_eq!(url.path, vec!["agents", "backend.md"]);
+        assert_eq!(url.api_url(), "https://api.github.com/repos/user/repo");
+        assert_eq!(
+            url.blob_url("trunk"),
+            "https://github.com/user/repo/blob/trunk/agents/backend.md"
+        );
+
+        assert!(GitHubRepoPath::parse("https://github.com/user/repo/blob/main/a.md", &[]).is_err());
+        assert!(GitHubRepoPath::parse("https://github.com/user/repo", &[]).is_err());
+    }
+
+    #[test]
+    fn test_expand_shorthand() {
+        let url = expand_shorthand("gh:user/repo/agents/backend.md@v1.2")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            url,
+            "https://github.com/user/repo/blob/v1.2/agents/backend.md"
+        );
+        assert_eq!(
+            GitHubFileUrl::parse(&url, &[]).unwrap().raw_url(),
+            "https://raw.githubusercontent.com/user/repo/v1.2/agents/backend.md"
+        );
+
+        // Without a ref the default branch is resolved later
+        let url = expand_shorthand("gh:user/repo/backend.md")
+            .unwrap()
+            .unwrap();
+        assert_eq!(url, "https://github.com/user/repo/backend.md");
+        assert_eq!(
+            GitHubRepoPath::parse(&url, &[]).unwrap().blob_url("main"),
+            "https://github.com/user/repo/blob/main/backend.md"
+        );
+
+        assert_eq!(expand_shorthand("./gh/agent.md").unwrap(), None);
+        assert!(expand_shorthand("gh:user/agent.md").is_err());
+        assert!(expand_shorthand("gh:user/repo//agent.md").is_err());
+        assert!(expand_shorthand("gh:user/repo/agent.md@").is_err());
+    }
+
+    #[test]
+    fn test_parse_release_asset_url() {
+        let url = GitHubFileUrl::parse(
+            "https://github.com/user/repo/releases/download/v1.2.0/backend.md",
+            &[],
+        )
+        .unwrap();
+
+        assert!(url.release_asset);
+        assert_eq!(url.branch, "v1.2.0");
+        assert_eq!(url.filename(), "backend.md");
+        assert_eq!(
+            url.raw_url(),
+            "https://github.com/user/repo/releases/download/v1.2.0/backend.md"
+        );
+
+        let result =
+            GitHubFileUrl::parse("https://github.com/user/repo/releases/download/v1.2.0", &[]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_flattened_name() {
+        let url = GitHubFileUrl::parse(
+            "https://github.com/user/repo/blob/main/agents/universal/back%20end.md",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(url.flattened_name(), "agents-universal-back-20end.md");
+
+        let url =
+            GitHubFileUrl::parse("https://github.com/user/repo/blob/main/agent.md", &[]).unwrap();
+        assert_eq!(url.flattened_name(), "agent.md");
+    }
+
+    #[test]
+    fn test_parse_enterprise_url() {
+        let hosts = vec!["github.mycorp.com".to_string()];
+        let url = GitHubFileUrl::parse(
+            "https://github.mycorp.com/org/repo/blob/develop/agents/backend.md",
+            &hosts,
+        )
+        .unwrap();
+
+        assert!(url.is_enterprise());
+        assert_eq!(
+            url.raw_url(),
+            "https://github.mycorp.com/org/repo/raw/develop/agents/backend.md"
+        );
+    }
+
+    #[test]
+    fn test_enterprise_host_with_port() {
+        let hosts = vec!["127.0.0.1:8080".to_string()];
+        let url =
+            GitHubFileUrl::parse("http://127.0.0.1:8080/org/repo/blob/main/a.md", &hosts).unwrap();
+
+        assert_eq!(
+            url.raw_url(),
+            "http://127.0.0.1:8080/org/repo/raw/main/a.md"
+        );
+    }
+
+    #[test]
+    fn test_unrecognized_host_rejected() {
+        let result = GitHubFileUrl::parse("https://github.mycorp.com/org/repo/blob/main/a.md", &[]);
+        assert!(result.unwrap_err().to_string().contains("Not a GitHub URL"));
+        assert!(!is_github_url("https://gitlab.com/org/repo", &[]));
+        assert!(is_github_url("https://GitHub.com/org/repo", &[]));
+    }
+
+    #[test]
+    fn test_repo_url_rejected() {
+        let result = GitHubFileUrl::parse("https://github.com/user/repo", &[]);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Only direct file links"));
+    }
+
+    #[test]
+    fn test_unexpected_extension() {
+        assert_eq!(unexpected_extension("agent.md", &[]), None);
+        assert_eq!(unexpected_extension("Agent.MARKDOWN", &[]), None);
+
+        let warning = unexpected_extension("diagram.png", &[]).unwrap();
+        assert!(warning.contains("'.png'"));
+        assert!(warning.contains("md, markdown, txt"));
+        assert!(unexpected_extension("README", &[]).is_some());
+
+        let allowed = vec![".yaml".to_string()];
+        assert_eq!(unexpected_extension("agent.yaml", &allowed), None);
+        assert!(unexpected_extension("agent.md", &allowed).is_some());
+    }
+
+    #[test]
+    fn test_enterprise_hosts_from_env() {
+        std::env::set_var(GITHUB_HOSTS_ENV, "ghe.one.com, ghe.two.com");
+        let hosts = enterprise_hosts(&["ghe.config.com".to_string()]);
+        std::env::remove_var(GITHUB_HOSTS_ENV);
+
+        assert_eq!(hosts, vec!["ghe.config.com", "ghe.one.com", "ghe.two.com"]);
+    }
+
+    #[test]
+    fn test_allowed_hosts_only_narrowed_by_project() {
+        let hosts = allowed_hosts(&[]);
+        assert!(hosts.iter().any(|h| h == "raw.githubusercontent.com"));
+        assert!(!hosts.iter().any(|h| h == "evil.example.com"));
+
+        // A project config cannot allow hosts the user does not trust
+        let project = vec![
+            "RAW.githubusercontent.com".to_string(),
+            "evil.example.com".to_string(),
+        ];
+        assert_eq!(allowed_hosts(&project), vec!["raw.githubusercontent.com"]);
+
+        trust_test_host("ghe.mycorp.com");
+        assert!(allowed_hosts(&[]).iter().any(|h| h == "ghe.mycorp.com"));
+    }
+}
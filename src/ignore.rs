@@ -0,0 +1,209 @@
+use crate::config::AgentsConfig;
+use anyhow::{Context, Result};
+use glob::Pattern;
+use std::fs;
+use std::path::Path;
+
+/// Name of the optional file listing extra patterns to skip during scans.
+pub const IGNORE_FILE: &str = ".ccagentsignore";
+
+/// Placeholder files used to keep otherwise empty directories in Git. They
+/// are never agents, even when re-included in `.ccagentsignore`.
+const PLACEHOLDER_FILES: &[&str] = &[".gitkeep", ".keep", ".gitignore"];
+
+/// Suffixes of editor backup and temporary files skipped by default.
+const BACKUP_SUFFIXES: &[&str] = &["~", ".swp", ".swo", ".bak", ".tmp", ".orig"];
+
+/// Rules deciding which directory entries are skipped when scanning
+/// `.claude/agents` and `.ccagents`.
+///
+/// Hidden files and common backup/temp files are always skipped unless
+/// re-included with a `!pattern` line in `.ccagentsignore`. Patterns from the
+/// config's `exclude` list are always skipped.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    ignore: Vec<Pattern>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    pub fn load(project_root: &Path) -> Result<Self> {
+        let ignore_path = project_root.join(IGNORE_FILE);
+
+        if !ignore_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&ignore_path)
+            .with_context(|| format!("Failed to read {:?}", ignore_path))?;
+
+        Self::parse(&content).with_context(|| format!("Failed to parse {:?}", ignore_path))
+    }
+
+    /// Loads `.ccagentsignore` and adds the `exclude` patterns of `config`.
+    pub fn for_config(project_root: &Path, config: &AgentsConfig) -> Result<Self> {
+        let mut rules = Self::load(project_root)?;
+        for pattern in &config.exclude {
+            rules.exclude.push(
+                Pattern::new(pattern)
+                    .with_context(|| format!("Invalid exclude pattern {:?}", pattern))?,
+            );
+        }
+        Ok(rules)
+    }
+
+    /// Parses ignore rules, one glob pattern per line. Blank lines and lines
+    /// starting with `#` are ignored; a leading `!` re-includes matches.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut rules = Self::default();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.strip_prefix('!') {
+                Some(pattern) => rules.include.push(Pattern::new(pattern)?),
+                None => rules.ignore.push(Pattern::new(line)?),
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Returns whether `path` is a real agent entry: not a placeholder,
+    /// hidden, backup or ignored file, and with a UTF-8 name. Every directory
+    /// scan uses this to decide what counts as an agent.
+    pub fn is_agent_entry(&self, path: &Path) -> bool {
+        match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => !PLACEHOLDER_FILES.contains(&name) && !self.is_ignored(name),
+            None => false,
+        }
+    }
+
+    pub fn is_ignored(&self, name: &str) -> bool {
+        if self.exclude.iter().any(|p| p.matches(name)) {
+            return true;
+        }
+        if self.include.iter().any(|p| p.matches(name)) {
+            return false;
+        }
+
+        is_hidden_or_backup(name) || self.ignore.iter().any(|p| p.matches(name))
+    }
+}
+
+/// Returns the file name of `path` if it is valid UTF-8.
+///
+/// Agent names in `.agents.json` are always UTF-8, so a non-UTF-8 name could
+/// never match one; scans skip such entries instead of treating them as an
+/// empty name, and leave reporting them to the caller (see
+/// [`SKIPPED_NAME_WARNING`]).
+pub fn scanned_file_name(path: &Path) -> Option<String> {
+    path.file_name()?.to_str().map(str::to_string)
+}
+
+/// Why a scan skipped an entry that [`scanned_file_name`] rejected.
+pub const SKIPPED_NAME_WARNING: &str = "file name is not valid UTF-8, skipped";
+
+fn is_hidden_or_backup(name: &str) -> bool {
+    name.starts_with('.') || BACKUP_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_default_rules_skip_hidden_and_backup_files() {
+        let rules = IgnoreRules::default();
+
+        assert!(rules.is_ignored(".DS_Store"));
+        assert!(rules.is_ignored(".gitkeep"));
+        assert!(rules.is_ignored("agent.md~"));
+        assert!(rules.is_ignored("agent.md.swp"));
+        assert!(!rules.is_ignored("agent.md"));
+    }
+
+    #[test]
+    fn test_placeholders_are_never_agent_entries() {
+        let dir = Path::new(".ccagents");
+        let rules = IgnoreRules::parse("!.gitkeep\n!.shared.md\n").unwrap();
+
+        assert!(!rules.is_agent_entry(&dir.join(".gitkeep")));
+        assert!(!rules.is_agent_entry(&dir.join(".keep")));
+        assert!(!IgnoreRules::default().is_agent_entry(&dir.join(".gitkeep")));
+        assert!(!IgnoreRules::default().is_agent_entry(&dir.join("agent.md~")));
+        assert!(rules.is_agent_entry(&dir.join(".shared.md")));
+        assert!(rules.is_agent_entry(&dir.join("agent.md")));
+    }
+
+    #[test]
+    fn test_parse_patterns_and_includes() {
+        let rules = IgnoreRules::parse("# comment\n\n*.draft.md\n!.special.md\n").unwrap();
+
+        assert!(rules.is_ignored("wip.draft.md"));
+        assert!(!rules.is_ignored(".special.md"));
+        assert!(rules.is_ignored(".DS_Store"));
+        assert!(!rules.is_ignored("agent.md"));
+    }
+
+    #[test]
+    fn test_config_excludes_win_over_includes() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(IGNORE_FILE),
+            "!drafts-*.md
+",
+        )
+        .unwrap();
+        let config = AgentsConfig {
+            exclude: vec!["drafts-*.md".to_string()],
+            ..AgentsConfig::default()
+        };
+
+        let rules = IgnoreRules::for_config(temp_dir.path(), &config).unwrap();
+        assert!(rules.is_ignored("drafts-api.md"));
+        assert!(!rules.is_ignored("api.md"));
+
+        let config = AgentsConfig {
+            exclude: vec!["[".to_string()],
+            ..AgentsConfig::default()
+        };
+        assert!(IgnoreRules::for_config(temp_dir.path(), &config).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scanned_file_name_skips_non_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir
+            .path()
+            .join(OsStr::from_bytes(b"agent-\xff\xfe.md"));
+        fs::write(&path, "# Agent").unwrap();
+
+        assert_eq!(scanned_file_name(&path), None);
+        assert_eq!(
+            scanned_file_name(&temp_dir.path().join("agent.md")).as_deref(),
+            Some("agent.md")
+        );
+    }
+
+    #[test]
+    fn test_load_from_project() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(!IgnoreRules::load(temp_dir.path())
+            .unwrap()
+            .is_ignored("notes.txt"));
+
+        fs::write(temp_dir.path().join(IGNORE_FILE), "notes.txt\n").unwrap();
+        assert!(IgnoreRules::load(temp_dir.path())
+            .unwrap()
+            .is_ignored("notes.txt"));
+    }
+}
@@ -1,7 +1,17 @@
 pub mod agent;
+pub mod archive;
+pub mod cache;
+pub mod claude_settings;
 pub mod config;
+pub mod downloader;
+pub mod error;
+pub mod github;
+pub mod ignore;
 pub mod linker;
+pub mod lock;
+pub mod reconcile;
 
 // Re-export commonly used types
 pub use agent::{Agent, AgentSource};
 pub use config::AgentsConfig;
+pub use reconcile::{SyncOptions, SyncReport};
@@ -1,7 +1,183 @@
+use crate::config::{normalize_path, read_dir_sorted};
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
 use std::os::unix::fs::symlink;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// How agents are placed in `.claude/agents`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LinkMode {
+    /// A symlink to the source.
+    #[default]
+    Symlink,
+    /// A copy of the source, for filesystems or tools without symlinks.
+    Copy,
+}
+
+/// Whether agent symlinks store a relative or an absolute target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStyle {
+    /// Relative to the link's directory, so links survive moving or cloning
+    /// the project.
+    #[default]
+    Relative,
+    /// The absolute path of the source.
+    Absolute,
+}
+
+/// Places `source` at `target` as a symlink (in the given `style`) or a copy,
+/// replacing whatever was there.
+pub fn link_agent(source: &Path, target: &Path, mode: LinkMode, style: LinkStyle) -> Result<()> {
+    match mode {
+        LinkMode::Symlink => create_agent_symlink(source, target, style),
+        LinkMode::Copy => {
+            if target.is_dir() && !target.is_symlink() {
+                fs::remove_dir_all(target)
+                    .with_context(|| format!("Failed to remove {:?}", target))?;
+            } else if target.exists() || target.is_symlink() {
+                fs::remove_file(target)
+                    .with_context(|| format!("Failed to remove {:?}", target))?;
+            }
+
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create parent directory for {:?}", target)
+                })?;
+            }
+
+            if source.is_dir() {
+                copy_dir_all(source, target)
+            } else {
+                copy_file(source, target)
+            }
+        }
+    }
+}
+
+/// Removes the agent placed at `target` by [`link_agent`]: a symlink, or in
+/// copy mode also a copied file or directory. Anything else at the path is an
+/// error, so an unmanaged file is never deleted by accident.
+pub fn unlink_agent(target: &Path, mode: LinkMode) -> Result<()> {
+    if mode == LinkMode::Copy && !target.is_symlink() {
+        if target.is_dir() {
+            return fs::remove_dir_all(target)
+                .with_context(|| format!("Failed to remove {:?}", target));
+        }
+        if target.exists() {
+            return fs::remove_file(target)
+                .with_context(|| format!("Failed to remove {:?}", target));
+        }
+    }
+    remove_symlink(target)
+}
+
+/// Returns whether `copy` (a regular file or directory, not a symlink) has
+/// the same content as `source`, comparing SHA-256 digests.
+pub fn copy_matches_source(source: &Path, copy: &Path) -> bool {
+    if copy.is_symlink() {
+        return false;
+    }
+
+    match (content_digest(source), content_digest(copy)) {
+        (Ok(source), Ok(copy)) => source == copy,
+        _ => false,
+    }
+}
+
+/// SHA-256 over a file's content, or over the relative paths and contents of
+/// every file below a directory.
+pub fn content_digest(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    if path.is_dir() {
+        for entry in walk_dir(path)?.iter().filter(|entry| !entry.is_dir) {
+            hash_file(&mut hasher, &entry.path, &entry.relative)?;
+        }
+    } else {
+        hash_file(&mut hasher, path, Path::new(""))?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn hash_file(hasher: &mut Sha256, path: &Path, relative: &Path) -> Result<()> {
+    hasher.update(relative.to_string_lossy().as_bytes());
+    hasher.update(fs::read(path).with_context(|| format!("Failed to read {:?}", path))?);
+    Ok(())
+}
+
+/// How many directory levels below its starting point [`walk_dir`] descends.
+const MAX_WALK_DEPTH: usize = 32;
+
+/// An entry found by [`walk_dir`].
+#[derive(Debug)]
+pub struct WalkEntry {
+    /// Path relative to the walked directory.
+    pub relative: PathBuf,
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// Lists everything below `dir`, depth first with names sorted and each
+/// directory before its contents. Symlinked directories are followed, but a
+/// directory whose canonical path was already visited is skipped and the
+/// walk fails beyond [`MAX_WALK_DEPTH`] levels, so symlink loops (e.g. an
+/// agent directory linking back into `.claude/agents`) always terminate.
+pub fn walk_dir(dir: &Path) -> Result<Vec<WalkEntry>> {
+    let root = dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {:?}", dir))?;
+    let mut visited = HashSet::from([root]);
+    let mut entries = Vec::new();
+    walk_into(dir, Path::new(""), 0, &mut visited, &mut entries)?;
+    Ok(entries)
+}
+
+fn walk_into(
+    dir: &Path,
+    relative: &Path,
+    depth: usize,
+    visited: &mut HashSet<PathBuf>,
+    entries: &mut Vec<WalkEntry>,
+) -> Result<()> {
+    if depth >= MAX_WALK_DEPTH {
+        return Err(anyhow::anyhow!(
+            "{:?} is nested more than {} directories deep",
+            dir,
+            MAX_WALK_DEPTH
+        ));
+    }
+
+    for name in read_dir_sorted(dir)?.iter().map(fs::DirEntry::file_name) {
+        let path = dir.join(&name);
+        let relative = relative.join(&name);
+
+        if path.is_dir() {
+            let canonical = path
+                .canonicalize()
+                .with_context(|| format!("Failed to resolve {:?}", path))?;
+            if !visited.insert(canonical) {
+                continue;
+            }
+            entries.push(WalkEntry {
+                relative: relative.clone(),
+                path: path.clone(),
+                is_dir: true,
+            });
+            walk_into(&path, &relative, depth + 1, visited, entries)?;
+        } else {
+            entries.push(WalkEntry {
+                relative,
+                path,
+                is_dir: false,
+            });
+        }
+    }
+
+    Ok(())
+}
 
 pub fn create_symlink(source: &Path, target: &Path) -> Result<()> {
     // Remove existing symlink if it exists
@@ -22,6 +198,40 @@ pub fn create_symlink(source: &Path, target: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Creates a symlink at `link_path` pointing at `source` in the given style.
+pub fn create_agent_symlink(source: &Path, link_path: &Path, style: LinkStyle) -> Result<()> {
+    create_symlink(&symlink_target(source, link_path, style), link_path)
+}
+
+/// The target stored in a symlink at `link_path` that points at `source`.
+/// Relative targets are computed lexically; when the two paths share nothing
+/// but the filesystem root, the absolute path is kept.
+pub fn symlink_target(source: &Path, link_path: &Path, style: LinkStyle) -> PathBuf {
+    let source = normalize_path(source);
+    let dir = match link_path.parent() {
+        Some(dir) if style == LinkStyle::Relative && source.is_absolute() => normalize_path(dir),
+        _ => return source,
+    };
+
+    let common = source
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common <= 1 {
+        return source;
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    for component in source.components().skip(common) {
+        relative.push(component);
+    }
+    relative
+}
+
 pub fn remove_symlink(target: &Path) -> Result<()> {
     if target.is_symlink() {
         fs::remove_file(target)
@@ -33,20 +243,99 @@ pub fn remove_symlink(target: &Path) -> Result<()> {
     Ok(())
 }
 
+/// State of a path expected to hold an agent symlink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkStatus {
+    /// Nothing exists at the path.
+    NotExist,
+    /// A regular file or directory occupies the path.
+    NotSymlink,
+    /// A symlink whose target does not exist.
+    Dangling,
+    /// A symlink pointing at an existing target.
+    Valid,
+}
+
+pub fn symlink_status(link_path: &Path) -> SymlinkStatus {
+    if link_path.is_symlink() {
+        // Check if the symlink points to an existing target
+        if resolve_symlink_target(link_path).is_ok() {
+            SymlinkStatus::Valid
+        } else {
+            SymlinkStatus::Dangling
+        }
+    } else if link_path.exists() {
+        SymlinkStatus::NotSymlink
+    } else {
+        SymlinkStatus::NotExist
+    }
+}
+
+#[allow(dead_code)]
 pub fn is_symlink_valid(link_path: &Path) -> bool {
-    if !link_path.is_symlink() {
-        return false;
+    symlink_status(link_path) == SymlinkStatus::Valid
+}
+
+/// Returns the canonical path a symlink points to. Relative targets are
+/// resolved against the directory containing the link. Errors when the
+/// path is not a symlink or its target does not exist.
+pub fn resolve_symlink_target(link_path: &Path) -> Result<PathBuf> {
+    let target = fs::read_link(link_path)
+        .with_context(|| format!("Failed to read symlink {:?}", link_path))?;
+
+    let absolute = if target.is_relative() {
+        link_path
+            .parent()
+            .map(|parent| parent.join(&target))
+            .unwrap_or(target)
+    } else {
+        target
+    };
+
+    absolute
+        .canonicalize()
+        .with_context(|| format!("Symlink {:?} points to missing {:?}", link_path, absolute))
+}
+
+/// Recursively copies a directory (see [`walk_dir`]), preserving file and
+/// directory permissions.
+pub fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst)?;
+
+    let entries = walk_dir(src)?;
+    for entry in &entries {
+        let dst_path = dst.join(&entry.relative);
+        if entry.is_dir {
+            fs::create_dir_all(&dst_path)?;
+        } else {
+            copy_file(&entry.path, &dst_path)?;
+        }
     }
 
-    // Check if the symlink points to an existing target
-    fs::read_link(link_path)
-        .ok()
-        .map(|target| target.exists())
-        .unwrap_or(false)
+    // Directory permissions last, so read-only directories can be filled
+    for entry in entries.iter().rev().filter(|entry| entry.is_dir) {
+        fs::set_permissions(
+            dst.join(&entry.relative),
+            fs::metadata(&entry.path)?.permissions(),
+        )?;
+    }
+    fs::set_permissions(dst, fs::metadata(src)?.permissions())?;
+
+    Ok(())
+}
+
+/// Copies a file and explicitly applies the source permissions, so scripts
+/// bundled with an agent keep their executable bit.
+pub fn copy_file(src: &Path, dst: &Path) -> Result<()> {
+    fs::copy(src, dst).with_context(|| format!("Failed to copy {:?} to {:?}", src, dst))?;
+    fs::set_permissions(dst, fs::metadata(src)?.permissions())
+        .with_context(|| format!("Failed to set permissions on {:?}", dst))?;
+
+    Ok(())
 }
 
 #[allow(dead_code)]
-pub fn get_symlink_target(link_path: &Path) -> Result<Option<std::path::PathBuf>> {
+pub fn get_symlink_target(link_path: &Path) -> Result<Option<PathBuf>> {
     if !link_path.is_symlink() {
         return Ok(None);
     }
@@ -136,6 +425,44 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_walk_dir_terminates_on_symlink_loop() {
+        let temp_dir = TempDir::new().unwrap();
+        let agent_dir = temp_dir.path().join(".ccagents/bundle");
+        fs::create_dir_all(agent_dir.join("docs")).unwrap();
+        fs::write(agent_dir.join("agent.md"), "# Agent").unwrap();
+        fs::write(agent_dir.join("docs/guide.md"), "# Guide").unwrap();
+
+        // docs/back -> the bundle itself, and a link to .claude/agents, which
+        // links back to the bundle
+        let claude_agents_dir = temp_dir.path().join(".claude/agents");
+        create_symlink(&agent_dir, &claude_agents_dir.join("bundle")).unwrap();
+        create_symlink(&agent_dir, &agent_dir.join("docs/back")).unwrap();
+        create_symlink(&claude_agents_dir, &agent_dir.join("active")).unwrap();
+
+        let relative: Vec<PathBuf> = walk_dir(&agent_dir)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.relative)
+            .collect();
+        assert_eq!(
+            relative,
+            vec![
+                PathBuf::from("active"),
+                PathBuf::from("agent.md"),
+                PathBuf::from("docs"),
+                PathBuf::from("docs/guide.md"),
+            ]
+        );
+
+        // Copying and hashing the looping directory finish too
+        let copy = temp_dir.path().join("copy");
+        copy_dir_all(&agent_dir, &copy).unwrap();
+        assert!(copy.join("docs/guide.md").is_file());
+        assert!(!copy.join("docs/back").exists());
+        assert!(copy_matches_source(&agent_dir, &copy));
+    }
+
     #[test]
     fn test_remove_nonexistent_symlink() {
         let temp_dir = TempDir::new().unwrap();
@@ -170,6 +497,70 @@ mod tests {
         assert!(!is_symlink_valid(&temp_dir.path().join("nonexistent")));
     }
 
+    #[test]
+    fn test_symlink_status() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        let valid_link = temp_dir.path().join("valid_link");
+        let dangling_link = temp_dir.path().join("dangling_link");
+        fs::write(&source, "content").unwrap();
+
+        create_symlink(&source, &valid_link).unwrap();
+        create_symlink(&temp_dir.path().join("nonexistent"), &dangling_link).unwrap();
+
+        assert_eq!(symlink_status(&valid_link), SymlinkStatus::Valid);
+        assert_eq!(symlink_status(&dangling_link), SymlinkStatus::Dangling);
+        assert_eq!(symlink_status(&source), SymlinkStatus::NotSymlink);
+        assert_eq!(symlink_status(temp_dir.path()), SymlinkStatus::NotSymlink);
+        assert_eq!(
+            symlink_status(&temp_dir.path().join("nonexistent")),
+            SymlinkStatus::NotExist
+        );
+    }
+
+    #[test]
+    fn test_symlink_target_styles() {
+        let source = Path::new("/project/.ccagents/team/agent.md");
+        let link = Path::new("/project/.claude/agents/agent.md");
+
+        assert_eq!(
+            symlink_target(source, link, LinkStyle::Relative),
+            Path::new("../../.ccagents/team/agent.md")
+        );
+        assert_eq!(symlink_target(source, link, LinkStyle::Absolute), source);
+
+        // Nothing in common but the root: stay absolute
+        let outside = Path::new("/opt/agents/agent.md");
+        assert_eq!(symlink_target(outside, link, LinkStyle::Relative), outside);
+    }
+
+    #[test]
+    fn test_resolve_symlink_target_relative_and_absolute() {
+        let temp_dir = TempDir::new().unwrap();
+        let source_dir = temp_dir.path().join(".ccagents");
+        let link_dir = temp_dir.path().join(".claude").join("agents");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::create_dir_all(&link_dir).unwrap();
+        let source = source_dir.join("agent.md");
+        fs::write(&source, "content").unwrap();
+
+        let absolute_link = link_dir.join("absolute.md");
+        let relative_link = link_dir.join("relative.md");
+        symlink(&source, &absolute_link).unwrap();
+        symlink("../../.ccagents/agent.md", &relative_link).unwrap();
+
+        let expected = source.canonicalize().unwrap();
+        assert_eq!(resolve_symlink_target(&absolute_link).unwrap(), expected);
+        assert_eq!(resolve_symlink_target(&relative_link).unwrap(), expected);
+        assert!(is_symlink_valid(&relative_link));
+
+        // Broken links and regular files do not resolve
+        let broken_link = link_dir.join("broken.md");
+        symlink("../../.ccagents/missing.md", &broken_link).unwrap();
+        assert!(resolve_symlink_target(&broken_link).is_err());
+        assert!(resolve_symlink_target(&source).is_err());
+    }
+
     #[test]
     fn test_get_symlink_target() {
         let temp_dir = TempDir::new().unwrap();
@@ -190,4 +581,66 @@ mod tests {
         let nonexistent_target = get_symlink_target(&temp_dir.path().join("nonexistent")).unwrap();
         assert_eq!(nonexistent_target, None);
     }
+
+    #[test]
+    fn test_link_agent_copy_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("agent.md");
+        let target = temp_dir.path().join("agents").join("agent.md");
+        fs::write(&source, "# Agent").unwrap();
+
+        // Replaces an existing symlink with a copy
+        create_symlink(&source, &target).unwrap();
+        link_agent(&source, &target, LinkMode::Copy, LinkStyle::Relative).unwrap();
+
+        assert!(!target.is_symlink());
+        assert!(copy_matches_source(&source, &target));
+
+        fs::write(&target, "# Edited").unwrap();
+        assert!(!copy_matches_source(&source, &target));
+    }
+
+    #[test]
+    fn test_copy_matches_source_for_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("bundle");
+        let copy = temp_dir.path().join("copy");
+        fs::create_dir_all(source.join("scripts")).unwrap();
+        fs::write(source.join("agent.md"), "# Agent").unwrap();
+        fs::write(source.join("scripts/run.sh"), "echo hi").unwrap();
+
+        link_agent(&source, &copy, LinkMode::Copy, LinkStyle::Relative).unwrap();
+        assert!(copy_matches_source(&source, &copy));
+
+        fs::write(copy.join("scripts/run.sh"), "echo bye").unwrap();
+        assert!(!copy_matches_source(&source, &copy));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_preserves_executable_bit() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let bundle = temp_dir.path().join("bundle");
+        fs::create_dir_all(bundle.join("hooks")).unwrap();
+        let script = bundle.join("hooks/pre.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let single = temp_dir.path().join("single.sh");
+        copy_file(&script, &single).unwrap();
+        assert_eq!(
+            fs::metadata(&single).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+
+        let copied = temp_dir.path().join("copied");
+        copy_dir_all(&bundle, &copied).unwrap();
+        let mode = fs::metadata(copied.join("hooks/pre.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
 }
@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Lock file beside `.claude/agents` (so `.claude/.ccagents.lock`), held
+/// while `sync` changes the links there. It lives outside the directory so a
+/// sync with nothing to do leaves the directory untouched.
+pub const LOCK_FILE: &str = ".ccagents.lock";
+
+/// How long to wait for another process to release the lock.
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A project-scoped lock: a file created exclusively that holds the owner's
+/// PID and is deleted when the lock is dropped. A process that is killed
+/// leaves the file behind; it is taken over once that PID is no longer
+/// running, and otherwise the contention error names it so it can be
+/// deleted by hand.
+#[derive(Debug)]
+pub struct ProjectLock {
+    path: PathBuf,
+}
+
+impl ProjectLock {
+    /// Creates the lock file at `path`, retrying until `timeout` while
+    /// another process holds it. Waiting does not block the async runtime.
+    pub async fn acquire(path: &Path, timeout: Duration) -> Result<Self> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id()).ok();
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let owner = fs::read_to_string(path).unwrap_or_default();
+                    if is_stale(&owner) {
+                        // Re-read first, so a lock taken over by another
+                        // process in the meantime is left alone
+                        if fs::read_to_string(path).is_ok_and(|current| current == owner) {
+                            fs::remove_file(path).ok();
+                        }
+                        continue;
+                    }
+                    if Instant::now() >= deadline {
+                        return Err(anyhow::anyhow!(
+                            "Another ccagents process is running (PID {}, lock file {:?}). Try again when it has finished, or delete the lock file if no ccagents process is running",
+                            owner.trim(),
+                            path
+                        ));
+                    }
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create lock file {:?}", path))
+                }
+            }
+        }
+    }
+}
+
+/// Whether the lock file content `owner` names a process that is no longer
+/// running. Content without a PID is never stale: its owner may not have
+/// written it yet.
+fn is_stale(owner: &str) -> bool {
+    owner
+        .trim()
+        .parse::<u32>()
+        .is_ok_and(|pid| !is_running(pid))
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return true;
+    };
+    // Signal 0 only checks that the process exists; EPERM means it does but
+    // belongs to another user
+    // SAFETY: kill with signal 0 sends nothing and touches no memory
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+impl Drop for ProjectLock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn test_lock_serializes_and_reports_contention() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE);
+
+        let (locked_tx, locked_rx) = oneshot::channel();
+        let holder = {
+            let path = path.clone();
+            tokio::spawn(async move {
+                let _lock = ProjectLock::acquire(&path, Duration::ZERO).await.unwrap();
+                locked_tx.send(()).unwrap();
+                tokio::time::sleep(Duration::from_millis(300)).await;
+            })
+        };
+        locked_rx.await.unwrap();
+
+        // Without waiting, the held lock is a clean contention error
+        let err = ProjectLock::acquire(&path, Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Another ccagents process is running"));
+
+        // Waiting succeeds once the holder has released it, which it can
+        // only do if waiting does not block the runtime
+        let started = Instant::now();
+        let lock = ProjectLock::acquire(&path, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        holder.await.unwrap();
+
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_lock_of_dead_process_is_taken_over() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE);
+        // Beyond any platform's PID range
+        fs::write(&path, format!("{}\n", i32::MAX)).unwrap();
+
+        let lock = ProjectLock::acquire(&path, Duration::ZERO).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap().trim(),
+            std::process::id().to_string()
+        );
+        drop(lock);
+
+        // A PID that is still running is not
+        fs::write(&path, format!("{}\n", std::process::id())).unwrap();
+        assert!(ProjectLock::acquire(&path, Duration::ZERO).await.is_err());
+    }
+}
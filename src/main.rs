@@ -1,15 +1,28 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use colored::*;
+use std::path::PathBuf;
 
 mod agent;
+mod archive;
+mod cache;
+mod claude_settings;
 mod commands;
 mod config;
 mod downloader;
+mod error;
+mod github;
+mod ignore;
 mod linker;
+mod lock;
+mod output;
+mod reconcile;
 mod version;
 
-use commands::{add, clean, disable, doctor, enable, import, list, sync};
+use commands::{
+    add, alias, annotate, cache as cache_cmd, check, clean, disable, doctor, edit, enable, export,
+    import, list, relink, sync, update, verify,
+};
 
 #[derive(Parser)]
 #[command(name = "ccagents")]
@@ -17,6 +30,14 @@ use commands::{add, clean, disable, doctor, enable, import, list, sync};
 #[command(author = "Darek")]
 #[command(long_version = env!("CARGO_PKG_VERSION"))]
 struct Cli {
+    /// Operate on the project at this path instead of the current directory
+    #[arg(long, global = true, value_name = "PATH")]
+    root: Option<PathBuf>,
+
+    /// Output format for list and doctor
+    #[arg(long, global = true, value_enum, default_value_t = output::OutputFormat::Table)]
+    output: output::OutputFormat,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -25,19 +46,119 @@ struct Cli {
 enum Commands {
     /// Add a new agent from a local path or GitHub URL
     Add {
-        /// Path or URL to the agent
-        source: String,
+        /// Paths or URLs of the agents to add
+        #[arg(required = true)]
+        sources: Vec<String>,
+        /// Directory for copied/downloaded files (defaults to managed_dir or .ccagents)
+        #[arg(long, value_name = "PATH")]
+        output_dir: Option<PathBuf>,
+        /// Enable the agent even if default_enabled is false
+        #[arg(long, conflicts_with = "disabled")]
+        enabled: bool,
+        /// Add the agent without enabling it
+        #[arg(long)]
+        disabled: bool,
+        /// Fail instead of warning when a GitHub file is not markdown/text
+        #[arg(long)]
+        strict: bool,
+        /// Name for the agent instead of the source file name
+        #[arg(long)]
+        name: Option<String>,
+        /// Name GitHub downloads after their repository path (agents-universal-backend.md)
+        #[arg(long, conflicts_with = "name")]
+        preserve_path: bool,
+        /// File name for the link in .claude/agents instead of the agent name
+        #[arg(long, value_name = "NAME")]
+        link_name: Option<String>,
+        /// Link the agent into this subdirectory of .claude/agents
+        #[arg(long, value_name = "DIR")]
+        subdir: Option<String>,
+        /// Description shown by 'list' (overrides the file's frontmatter)
+        #[arg(long)]
+        description: Option<String>,
+        /// Succeed without changes when the agent is already configured with the same source
+        #[arg(long)]
+        idempotent: bool,
+        /// Replace an existing agent of the same name that has a different source
+        #[arg(long)]
+        force: bool,
+        /// Record GitHub agents in .agents.json without downloading them; 'sync' fetches them later
+        #[arg(long)]
+        manifest_only: bool,
     },
     /// List all agents (enabled, disabled, and available)
-    List,
+    List {
+        /// Print stable tab-separated `STATUS NAME SOURCE` lines (same as --output plain)
+        #[arg(long)]
+        porcelain: bool,
+        /// Print only the number of agents
+        #[arg(long, conflicts_with = "porcelain")]
+        count: bool,
+        /// With --count, count only enabled agents
+        #[arg(long, requires = "count", conflicts_with = "disabled")]
+        enabled: bool,
+        /// With --count, count only disabled agents
+        #[arg(long, requires = "count")]
+        disabled: bool,
+        /// Show .ccagents and .claude/agents as trees, marking configured and linked entries
+        #[arg(long, conflicts_with_all = ["porcelain", "count"])]
+        tree: bool,
+        /// Fail with exit code 5 when no agents are configured
+        #[arg(long)]
+        require_agents: bool,
+    },
     /// Enable an agent by creating a symlink in .claude/agents
     Enable {
-        /// Name of the agent to enable
-        name: String,
+        /// Names of the agents to enable
+        #[arg(required_unless_present = "stdin")]
+        names: Vec<String>,
+        /// Download a missing GitHub source before linking
+        #[arg(long)]
+        download: bool,
+        /// Treat the names as glob patterns (e.g. 'test-*') and enable all matches
+        #[arg(long)]
+        glob: bool,
+        /// Read newline-delimited agent names from stdin
+        #[arg(long, conflicts_with_all = ["names", "glob"])]
+        stdin: bool,
     },
     /// Disable an agent by removing its symlink from .claude/agents
     Disable {
-        /// Name of the agent to disable
+        /// Names of the agents to disable
+        #[arg(required_unless_present = "stdin")]
+        names: Vec<String>,
+        /// Treat the names as glob patterns (e.g. 'test-*') and disable all matches
+        #[arg(long)]
+        glob: bool,
+        /// Also delete the agent's copy in the managed dir, e.g. .ccagents (it stays in
+        /// .agents.json); local sources elsewhere are kept
+        #[arg(long)]
+        remove_file: bool,
+        /// Read newline-delimited agent names from stdin
+        #[arg(long, conflicts_with_all = ["names", "glob"])]
+        stdin: bool,
+    },
+    /// Show or manage the short aliases of an agent
+    Alias {
+        /// Name (or alias) of the agent
+        name: String,
+        /// Add an alias, e.g. `ccagents alias backend-developer.md --add be`
+        #[arg(long, value_name = "ALIAS", conflicts_with = "remove")]
+        add: Option<String>,
+        /// Remove an alias
+        #[arg(long, value_name = "ALIAS")]
+        remove: Option<String>,
+    },
+    /// Set or clear the description of an agent shown by 'list'
+    Annotate {
+        /// Name (or alias) of the agent
+        name: String,
+        /// Description to store in .agents.json (clears it when omitted)
+        description: Option<String>,
+    },
+    /// Open an agent's file in $EDITOR
+    Edit {
+        /// Name (or alias) of the agent to edit
         name: String,
     },
     /// Sync agents based on .agents.json configuration
@@ -45,18 +166,108 @@ enum Commands {
         /// Remove orphaned entries during sync
         #[arg(short, long)]
         prune: bool,
+        /// Continue syncing remaining agents when one fails
+        #[arg(short, long)]
+        keep_going: bool,
+        /// Print the change summary as JSON
+        #[arg(long)]
+        json: bool,
+        /// Print only warnings, failures and a one-line summary (full detail on failure)
+        #[arg(long)]
+        quiet_success: bool,
+        /// How $CCAGENTS_MANIFEST combines with .agents.json
+        #[arg(long, value_enum, default_value_t = config::ManifestMerge::Override)]
+        manifest_merge: config::ManifestMerge,
+        /// Copy agents into .claude/agents instead of symlinking them
+        #[arg(long)]
+        copy: bool,
+        /// Symlink agents even when .agents.json sets prefer_copy
+        #[arg(long, conflicts_with = "copy")]
+        symlink: bool,
+        /// Fail with exit code 5 when no agents are configured
+        #[arg(long)]
+        require_agents: bool,
+        /// Fail with exit code 2 instead of warning when .claude/agents holds unmanaged files
+        #[arg(long)]
+        fail_on_unmanaged: bool,
+        /// Keep running and sync again whenever .agents.json or .ccagents changes
+        #[arg(long, conflicts_with = "json")]
+        watch: bool,
+        /// Only sync this agent, leaving all others untouched (repeatable)
+        #[arg(long, value_name = "NAME")]
+        only: Vec<String>,
+        /// Only sync agents with this tag, leaving all others untouched (repeatable)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Only link existing sources; never download, skipping GitHub agents whose file is missing
+        #[arg(long, conflicts_with = "prune")]
+        link_only: bool,
+    },
+    /// Re-download GitHub agents from their source
+    Update {
+        /// Name of the agent to update (all GitHub agents if omitted)
+        name: Option<String>,
+        /// Remove agents whose upstream file no longer exists without asking
+        #[arg(long)]
+        remove_gone: bool,
+        /// Only report which agents have upstream changes; download nothing
+        #[arg(long, conflicts_with = "remove_gone")]
+        check: bool,
+        /// Only download agents whose file is missing locally; links are not touched
+        #[arg(long, conflicts_with = "check")]
+        only_missing: bool,
+        /// Apply what --check reports: download only agents with upstream changes
+        #[arg(long, conflicts_with_all = ["name", "check", "only_missing"])]
+        all: bool,
+    },
+    /// Check agent files against the checksums recorded by add and update
+    Verify {
+        /// Offer to re-download modified GitHub agents or accept their changes
+        #[arg(long)]
+        fix: bool,
     },
+    /// Recreate all symlinks from .agents.json without downloading (e.g. after moving the project)
+    Relink,
+    /// Fix missing, broken and orphaned links; never changes .agents.json or agent files
+    Repair,
     /// Remove orphaned agents from configuration
     Clean {
         /// Skip confirmation prompt
         #[arg(short, long)]
         force: bool,
+        /// Also delete files in .ccagents that no remaining agent references
+        #[arg(long)]
+        prune_files: bool,
+    },
+    /// Run the doctor's checks silently; exit 2 if any issue is found
+    Check {
+        /// Print a one-line summary of the agent count and issues
+        #[arg(long)]
+        summary: bool,
+        /// How agents are placed in .claude/agents (default: from prefer_copy); `copy` validates copies by content
+        #[arg(long, value_enum)]
+        link_mode: Option<linker::LinkMode>,
     },
     /// Diagnose and fix issues with agent configuration
     Doctor {
         /// Automatically fix issues
         #[arg(short, long)]
         fix: bool,
+        /// Only run the given check (repeatable)
+        #[arg(long, value_enum, value_name = "CHECK")]
+        only: Vec<doctor::IssueType>,
+        /// How agents are placed in .claude/agents (default: from prefer_copy); `copy` validates copies by content
+        #[arg(long, alias = "assume-linked", value_enum)]
+        link_mode: Option<linker::LinkMode>,
+        /// Reverse the changes of the most recent --fix run
+        #[arg(long, conflicts_with_all = ["fix", "only"])]
+        undo: bool,
+        /// Explain each issue found and how to resolve it by hand
+        #[arg(long, conflicts_with = "undo")]
+        explain: bool,
+        /// Print stable tab-separated `CHECK AGENT FIXABLE DESCRIPTION` lines (same as --output plain)
+        #[arg(long, conflicts_with_all = ["fix", "undo"])]
+        porcelain: bool,
     },
     /// Import unmanaged files from .claude/agents
     Import {
@@ -65,35 +276,281 @@ enum Commands {
         /// Import all unmanaged files without confirmation
         #[arg(short, long)]
         all: bool,
+        /// Enable imported agents even if default_enabled is false
+        #[arg(long, conflicts_with = "disabled")]
+        enabled: bool,
+        /// Import agents as disabled
+        #[arg(long)]
+        disabled: bool,
+        /// Show which files would be moved, linked and added without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// What to do when .ccagents already has a file of the same name
+        #[arg(long, value_enum, default_value_t = import::ConflictStrategy::UseExisting)]
+        on_conflict: import::ConflictStrategy,
+    },
+    /// Export .agents.json as a manifest to share with other projects
+    Export {
+        /// File to write the manifest to (default: stdout)
+        #[arg(short, long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        /// Export only enabled agents (by default disabled agents are exported
+        /// too, with their enabled flags)
+        #[arg(long)]
+        enabled_only: bool,
+    },
+    /// Manage the download cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
     },
     /// Display version information
-    Version,
+    Version {
+        /// Check GitHub for a newer release
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached downloads with their size and age
+    List,
+    /// Delete cached downloads
+    Clear {
+        /// Only delete downloads last validated longer ago than this (e.g. 7d, 12h)
+        #[arg(long, value_parser = parse_duration)]
+        older_than: Option<std::time::Duration>,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if let Some(root) = &cli.root {
+        if let Err(e) = config::set_project_root(root) {
+            exit_with_error(e);
+        }
+    }
+
     let result = match cli.command {
-        Some(Commands::Add { source }) => add::execute(&source).await,
-        Some(Commands::List) => list::execute(),
-        Some(Commands::Enable { name }) => enable::execute(&name),
-        Some(Commands::Disable { name }) => disable::execute(&name),
-        Some(Commands::Sync { prune }) => sync::execute(prune),
-        None => sync::execute(false),
-        Some(Commands::Clean { force }) => clean::execute(force),
-        Some(Commands::Doctor { fix }) => doctor::execute(fix),
-        Some(Commands::Import { name, all }) => import::execute(name, all),
-        Some(Commands::Version) => {
+        Some(Commands::Add {
+            sources,
+            output_dir,
+            enabled,
+            disabled,
+            strict,
+            name,
+            preserve_path,
+            link_name,
+            subdir,
+            description,
+            idempotent,
+            force,
+            manifest_only,
+        }) => {
+            let options = add::AddOptions {
+                output_dir,
+                enabled: enabled_override(enabled, disabled),
+                strict,
+                name,
+                preserve_path,
+                link_name,
+                subdir,
+                description,
+                idempotent,
+                force,
+                manifest_only,
+            };
+            add::execute(&sources, &options).await
+        }
+        Some(Commands::List {
+            porcelain,
+            count,
+            enabled,
+            disabled,
+            tree,
+            require_agents,
+        }) => {
+            if count {
+                list::count(enabled_override(enabled, disabled), require_agents)
+            } else if tree {
+                list::tree(require_agents)
+            } else {
+                list::execute(
+                    if porcelain {
+                        output::OutputFormat::Plain
+                    } else {
+                        cli.output
+                    },
+                    require_agents,
+                )
+            }
+        }
+        Some(Commands::Enable {
+            names,
+            download,
+            glob,
+            stdin,
+        }) => enable::execute(&names, download, glob, stdin).await,
+        Some(Commands::Disable {
+            names,
+            glob,
+            remove_file,
+            stdin,
+        }) => disable::execute(&names, glob, remove_file, stdin),
+        Some(Commands::Alias { name, add, remove }) => {
+            alias::execute(&name, add.as_deref(), remove.as_deref())
+        }
+        Some(Commands::Annotate { name, description }) => {
+            annotate::execute(&name, description.as_deref())
+        }
+        Some(Commands::Edit { name }) => edit::execute(&name),
+        Some(Commands::Sync {
+            prune,
+            keep_going,
+            json,
+            quiet_success,
+            manifest_merge,
+            copy,
+            symlink,
+            require_agents,
+            fail_on_unmanaged,
+            watch,
+            only,
+            tags,
+            link_only,
+        }) => {
+            let options = sync::SyncOptions {
+                sync: reconcile::SyncOptions {
+                    prune,
+                    keep_going,
+                    manifest_merge,
+                    link_mode: match (copy, symlink) {
+                        (true, _) => Some(linker::LinkMode::Copy),
+                        (_, true) => Some(linker::LinkMode::Symlink),
+                        _ => None,
+                    },
+                    require_agents,
+                    fail_on_unmanaged,
+                    only,
+                    tags,
+                    link_only,
+                    ..Default::default()
+                },
+                json,
+                quiet_success,
+            };
+            if watch {
+                sync::watch(&options).await
+            } else {
+                sync::execute(&options).await
+            }
+        }
+        None => sync::execute(&sync::SyncOptions::default()).await,
+        Some(Commands::Update {
+            name,
+            remove_gone,
+            check,
+            only_missing,
+            all,
+        }) => update::execute(name, remove_gone, check, only_missing, all).await,
+        Some(Commands::Verify { fix }) => verify::execute(fix).await,
+        Some(Commands::Relink) => relink::execute(),
+        Some(Commands::Repair) => doctor::repair(),
+        Some(Commands::Clean { force, prune_files }) => clean::execute(force, prune_files),
+        Some(Commands::Check { summary, link_mode }) => match check::execute(summary, link_mode) {
+            // No error line: the exit code (and the optional summary) is the output
+            Ok(0) => Ok(()),
+            Ok(_) => std::process::exit(error::exit_code::ISSUES_FOUND),
+            Err(e) => Err(e),
+        },
+        Some(Commands::Doctor {
+            fix,
+            only,
+            link_mode,
+            undo,
+            explain,
+            porcelain,
+        }) => {
+            if undo {
+                doctor::undo()
+            } else {
+                let output = if porcelain {
+                    output::OutputFormat::Plain
+                } else {
+                    cli.output
+                };
+                doctor::execute(fix, explain, &only, link_mode, output)
+            }
+        }
+        Some(Commands::Import {
+            name,
+            all,
+            enabled,
+            disabled,
+            dry_run,
+            on_conflict,
+        }) => import::execute(
+            name,
+            all,
+            enabled_override(enabled, disabled),
+            dry_run,
+            on_conflict,
+        ),
+        Some(Commands::Export { file, enabled_only }) => {
+            export::execute(file.as_deref(), enabled_only)
+        }
+        Some(Commands::Cache { action }) => match action {
+            CacheAction::List => cache_cmd::list(),
+            CacheAction::Clear { older_than } => cache_cmd::clear(older_than),
+        },
+        Some(Commands::Version { check }) => {
             version::print_version_info();
-            Ok(())
+            if check {
+                version::check_for_update(version::LATEST_RELEASE_URL).await
+            } else {
+                Ok(())
+            }
         }
     };
 
     if let Err(e) = result {
-        eprintln!("{} {}", "Error:".red().bold(), e);
-        std::process::exit(1);
+        exit_with_error(e);
     }
 
     Ok(())
 }
+
+fn exit_with_error(e: anyhow::Error) -> ! {
+    eprintln!("{} {}", "Error:".red().bold(), e);
+    std::process::exit(error::exit_code_for(&e));
+}
+
+/// Maps `--enabled`/`--disabled` to `Some(true)`/`Some(false)`, e.g. to
+/// override the config's default_enabled.
+fn enabled_override(enabled: bool, disabled: bool) -> Option<bool> {
+    match (enabled, disabled) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_duration(value: &str) -> Result<std::time::Duration, String> {
+    cache_cmd::parse_duration(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        // Catches clashing argument names, e.g. a subcommand flag shadowing
+        // a global one, which clap otherwise only reports when parsing
+        Cli::command().debug_assert();
+    }
+}
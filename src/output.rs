@@ -0,0 +1,96 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// How commands that report data (`list`, `doctor`) format their output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored output for people
+    #[default]
+    Table,
+    /// A single JSON document
+    Json,
+    /// Uncolored tab-separated lines for scripts
+    Plain,
+}
+
+/// Data reported by a command. JSON output comes from the `Serialize`
+/// implementation; the other formats are rendered line by line.
+pub trait Report: Serialize {
+    fn table(&self) -> Vec<String>;
+    fn plain(&self) -> Vec<String>;
+}
+
+/// Destination commands write their reports to.
+pub trait Presenter {
+    fn present<R: Report>(&mut self, report: &R) -> Result<()>;
+}
+
+/// Prints reports to stdout in the selected format.
+pub struct StdoutPresenter {
+    format: OutputFormat,
+}
+
+impl StdoutPresenter {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl Presenter for StdoutPresenter {
+    fn present<R: Report>(&mut self, report: &R) -> Result<()> {
+        for line in render(report, self.format)? {
+            println!("{}", line);
+        }
+        Ok(())
+    }
+}
+
+/// Renders `report` as output lines in `format`.
+pub fn render<R: Report>(report: &R, format: OutputFormat) -> Result<Vec<String>> {
+    Ok(match format {
+        OutputFormat::Table => report.table(),
+        OutputFormat::Json => vec![serde_json::to_string_pretty(report)?],
+        OutputFormat::Plain => report.plain(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Names {
+        names: Vec<String>,
+    }
+
+    impl Report for Names {
+        fn table(&self) -> Vec<String> {
+            vec![format!("Names: {}", self.names.join(", "))]
+        }
+
+        fn plain(&self) -> Vec<String> {
+            self.names.clone()
+        }
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let report = Names {
+            names: vec!["a.md".to_string(), "b.md".to_string()],
+        };
+
+        assert_eq!(
+            render(&report, OutputFormat::Table).unwrap(),
+            vec!["Names: a.md, b.md"]
+        );
+        assert_eq!(
+            render(&report, OutputFormat::Plain).unwrap(),
+            vec!["a.md", "b.md"]
+        );
+
+        let json = render(&report, OutputFormat::Json).unwrap().join("\n");
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["names"][1], "b.md");
+    }
+}
@@ -0,0 +1,511 @@
+use crate::agent::{Agent, AgentSource};
+use crate::claude_settings;
+use crate::config::{
+    ensure_claude_agents_dir, normalize_path, scan_link_dir, AgentsConfig, ManifestMerge,
+};
+use crate::downloader::{download_agent, DownloadOptions};
+use crate::error::CcagentsError;
+use crate::ignore::{IgnoreRules, SKIPPED_NAME_WARNING};
+use crate::linker::{
+    copy_matches_source, link_agent, remove_symlink, symlink_target, LinkMode, LinkStyle,
+};
+use crate::lock::{ProjectLock, LOCK_FILE, LOCK_TIMEOUT};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Flags controlling a sync run.
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Remove agents whose source is missing from `.agents.json`.
+    pub prune: bool,
+    /// Keep syncing other agents when one fails.
+    pub keep_going: bool,
+    /// Print download progress. Off by default, so a sync prints nothing.
+    pub progress: bool,
+    /// How a manifest from `$CCAGENTS_MANIFEST` combines with `.agents.json`.
+    pub manifest_merge: ManifestMerge,
+    /// Whether agents are symlinked or copied into `.claude/agents`; `None`
+    /// follows the config's `prefer_copy`.
+    pub link_mode: Option<LinkMode>,
+    /// Fail instead of succeeding when no agents are configured.
+    pub require_agents: bool,
+    /// Fail with `CcagentsError::UnmanagedFiles` before touching any link
+    /// when `.claude/agents` holds unmanaged files.
+    pub fail_on_unmanaged: bool,
+    /// Restrict the sync to these agents (names or aliases).
+    pub only: Vec<String>,
+    /// Restrict the sync to agents with any of these tags.
+    pub tags: Vec<String>,
+    /// Only link existing sources; GitHub agents whose file is missing are
+    /// skipped like missing local sources instead of downloaded.
+    pub link_only: bool,
+}
+
+/// What a sync run changed in `.claude/agents`, by agent name.
+#[derive(Debug, Default, Serialize)]
+pub struct SyncChanges {
+    /// Links that did not exist before.
+    pub created: Vec<String>,
+    /// Links that were broken or pointed elsewhere and now point at the source.
+    pub repaired: Vec<String>,
+    /// Links that existed before and were not recreated.
+    pub removed: Vec<String>,
+    /// Agents whose source was downloaded.
+    pub downloaded: Vec<String>,
+    /// Links that were already current and left untouched.
+    pub unchanged: Vec<String>,
+}
+
+impl SyncChanges {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.repaired.is_empty()
+            && self.removed.is_empty()
+            && self.downloaded.is_empty()
+    }
+}
+
+/// What happened to one selected, enabled agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentOutcome {
+    /// Its link or copy was already current.
+    Unchanged,
+    /// It was linked or copied.
+    Linked,
+    /// Its `when` condition does not hold.
+    ConditionNotMet,
+    /// Its `when` condition could not be evaluated.
+    InvalidCondition(String),
+    /// Its link would land on the link of the named, earlier agent.
+    Collision(String),
+    /// Its local source does not exist.
+    SourceMissing,
+    /// Downloading or linking failed with the given message.
+    Failed(String),
+}
+
+/// The result of [`AgentsConfig::sync`].
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// No agents are configured, so nothing was synced.
+    pub no_agents: bool,
+    /// The agents came from `$CCAGENTS_MANIFEST`; `.agents.json` was not
+    /// modified.
+    pub from_manifest: bool,
+    /// Agents removed by `prune` because their source is missing.
+    pub pruned: Vec<String>,
+    /// Regular files in `.claude/agents` that no agent accounts for.
+    pub unmanaged: Vec<String>,
+    /// Outcome of each selected, enabled agent, in config order.
+    pub outcomes: Vec<(String, AgentOutcome)>,
+    /// Selected agents that are disabled.
+    pub disabled: Vec<String>,
+    pub changes: SyncChanges,
+    /// Agents that failed to sync. Without `keep_going` the run stopped at
+    /// the first.
+    pub failed: Vec<(String, anyhow::Error)>,
+    /// Warnings about agents that did sync, e.g. a download served with an
+    /// unexpected `Content-Type`, by agent name.
+    pub warnings: Vec<(String, String)>,
+}
+
+impl AgentsConfig {
+    /// Downloads missing GitHub sources and reconciles `.claude/agents` with
+    /// the configuration (and `$CCAGENTS_MANIFEST`), returning what was done.
+    ///
+    /// Agent failures and warnings are recorded in the report rather than
+    /// returned or printed; errors are reserved for problems that stop the
+    /// sync as a whole. Nothing is printed unless `progress` is set.
+    pub async fn sync(project_root: &Path, options: &SyncOptions) -> Result<SyncReport> {
+        // Concurrent syncs would remove each other's fresh links or, with
+        // `prune`, overwrite each other's `.agents.json`; held from before the
+        // config is read until the sync returns
+        let lock_path = lock_path(&claude_settings::agents_dir(project_root));
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let _lock = ProjectLock::acquire(&lock_path, LOCK_TIMEOUT).await?;
+
+        let (mut config, from_manifest) =
+            AgentsConfig::load_with_manifest(project_root, options.manifest_merge)?;
+        let mut report = SyncReport {
+            from_manifest,
+            ..SyncReport::default()
+        };
+
+        if config.agents.is_empty() {
+            if options.require_agents {
+                return Err(CcagentsError::NoAgentsConfigured.into());
+            }
+            report.no_agents = true;
+            return Ok(report);
+        }
+
+        // With `only` or `tags`, agents outside the selection and their links
+        // are left untouched
+        let selection = select_agents(&config, options)?;
+        let selected = |agent: &Agent| {
+            selection
+                .as_ref()
+                .is_none_or(|names| names.contains(&agent.name))
+        };
+
+        let claude_agents_dir = ensure_claude_agents_dir(project_root)?;
+        let previous_links = read_links(&claude_agents_dir, &config.link_names())?;
+
+        if options.prune {
+            config.agents.retain(|agent| {
+                if !selected(agent) {
+                    return true;
+                }
+                let local_path = agent.get_local_path(project_root);
+                if !local_path.exists() {
+                    report.pruned.push(agent.name.clone());
+                    // Also remove orphaned symlink if it exists
+                    let link_path = agent.get_link_path(project_root);
+                    if link_path.exists() || link_path.is_symlink() {
+                        remove_symlink(&link_path).ok();
+                    }
+                    false
+                } else {
+                    true
+                }
+            });
+
+            if !report.pruned.is_empty() && !from_manifest {
+                config.save(project_root)?;
+            }
+        }
+
+        // First, check for unmanaged files and remove stale symlinks. In copy
+        // mode, files named after configured agents are their copies
+        let ignore = IgnoreRules::for_config(project_root, &config)?;
+        let link_mode = options.link_mode.unwrap_or(config.link_mode());
+        let copy_mode = link_mode == LinkMode::Copy;
+        let configured = config.link_names();
+        let mut symlinks = Vec::new();
+        if claude_agents_dir.exists() {
+            let scan = scan_link_dir(&claude_agents_dir, &configured)?;
+            for path in &scan.skipped {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                report
+                    .warnings
+                    .push((name.into_owned(), SKIPPED_NAME_WARNING.to_string()));
+            }
+            for (name, path) in scan.entries {
+                if path.is_symlink() {
+                    symlinks.push((name, path));
+                } else if path.is_file() {
+                    // Regular file - not managed by ccagents
+                    if ignore.is_agent_entry(&path)
+                        && !(copy_mode && configured.contains(name.as_str()))
+                    {
+                        report.unmanaged.push(name);
+                    }
+                }
+            }
+        }
+
+        // Fail before touching any link
+        if options.fail_on_unmanaged && !report.unmanaged.is_empty() {
+            return Err(CcagentsError::UnmanagedFiles(report.unmanaged).into());
+        }
+
+        // Links that already point at their agent's source are kept as they
+        // are, so a sync with nothing to do touches nothing
+        let current = if copy_mode {
+            HashSet::new()
+        } else {
+            current_links(&config, project_root)
+        };
+        let selected_links: Option<HashSet<String>> = selection.as_ref().map(|_| {
+            config
+                .agents
+                .iter()
+                .filter(|agent| selected(agent))
+                .map(Agent::link_key)
+                .collect()
+        });
+        for (name, path) in &symlinks {
+            let in_selection = selected_links
+                .as_ref()
+                .is_none_or(|links| links.contains(name));
+            if in_selection && !current.contains(name) {
+                remove_symlink(path).ok();
+            }
+        }
+
+        // Sync enabled agents
+        let download_options = DownloadOptions {
+            progress: options.progress,
+            ..DownloadOptions::from_config(&config)
+        };
+        let mut link_owners: HashMap<String, &str> = HashMap::new();
+        let mut linked = HashSet::new();
+        for agent in config.enabled_agents() {
+            // Unselected agents keep their links, which still take precedence
+            // in collisions as in a full sync
+            if !selected(agent) {
+                if matches!(agent.condition_met(), Ok(true)) {
+                    let key = link_collision_key(&agent.get_link_path(project_root));
+                    link_owners.entry(key).or_insert(&agent.name);
+                }
+                continue;
+            }
+
+            // Skip agents whose `when` condition does not hold; their link was
+            // already removed above
+            match agent.condition_met() {
+                Ok(true) => {}
+                Ok(false) => {
+                    report
+                        .outcomes
+                        .push((agent.name.clone(), AgentOutcome::ConditionNotMet));
+                    continue;
+                }
+                Err(e) => {
+                    report.outcomes.push((
+                        agent.name.clone(),
+                        AgentOutcome::InvalidCondition(e.to_string()),
+                    ));
+                    continue;
+                }
+            }
+
+            // Two agents whose links would land on the same path (e.g. names
+            // that differ only by case) would clobber each other; the first
+            // one wins
+            let key = link_collision_key(&agent.get_link_path(project_root));
+            if let Some(owner) = link_owners.get(&key) {
+                report.outcomes.push((
+                    agent.name.clone(),
+                    AgentOutcome::Collision(owner.to_string()),
+                ));
+                continue;
+            }
+            link_owners.insert(key, &agent.name);
+
+            // Whether a copy from an earlier copy-mode sync is already current
+            let link_path = agent.get_link_path(project_root);
+            let local_path = agent.get_local_path(project_root);
+            let existing_copy = (copy_mode && link_path.exists() && !link_path.is_symlink())
+                .then(|| copy_matches_source(&local_path, &link_path));
+
+            if existing_copy == Some(true) || current.contains(&agent.link_key()) {
+                linked.insert(agent.name.as_str());
+                report
+                    .outcomes
+                    .push((agent.name.clone(), AgentOutcome::Unchanged));
+                report.changes.unchanged.push(agent.name.clone());
+                continue;
+            }
+
+            match sync_agent(
+                agent,
+                project_root,
+                &download_options,
+                options.link_only,
+                link_mode,
+                config.link_style,
+                &mut report,
+            )
+            .await
+            {
+                Ok(true) => {
+                    linked.insert(agent.name.as_str());
+                    report
+                        .outcomes
+                        .push((agent.name.clone(), AgentOutcome::Linked));
+                    // Current links and copies were skipped above, so an
+                    // earlier link or copy was stale
+                    match (
+                        existing_copy,
+                        previous_links.contains_key(&agent.link_key()),
+                    ) {
+                        (None, false) => report.changes.created.push(agent.name.clone()),
+                        _ => report.changes.repaired.push(agent.name.clone()),
+                    }
+                }
+                Ok(false) => {
+                    report
+                        .outcomes
+                        .push((agent.name.clone(), AgentOutcome::SourceMissing));
+                }
+                Err(e) => {
+                    report
+                        .outcomes
+                        .push((agent.name.clone(), AgentOutcome::Failed(e.to_string())));
+                    report.failed.push((agent.name.clone(), e));
+                    if !options.keep_going {
+                        return Ok(report);
+                    }
+                }
+            }
+        }
+
+        report.disabled = config
+            .disabled_agents()
+            .into_iter()
+            .filter(|agent| selected(agent))
+            .map(|agent| agent.name.clone())
+            .collect();
+
+        let mut removed: Vec<String> = previous_links
+            .keys()
+            .filter(|name| {
+                let path = claude_agents_dir.join(name);
+                !path.is_symlink() && !path.exists()
+            })
+            .cloned()
+            .collect();
+
+        // Copies of agents that are no longer linked (disabled, skipped) go
+        // too, unless their path belongs to another agent's copy: the winner
+        // of a collision, or a link name shared with a disabled agent
+        if copy_mode {
+            for agent in config.agents.iter().filter(|agent| selected(agent)) {
+                let link_path = agent.get_link_path(project_root);
+                let owned_by_other = link_owners
+                    .get(&link_collision_key(&link_path))
+                    .is_some_and(|owner| *owner != agent.name);
+                if !linked.contains(agent.name.as_str())
+                    && !owned_by_other
+                    && link_path.exists()
+                    && !link_path.is_symlink()
+                {
+                    if link_path.is_dir() {
+                        fs::remove_dir_all(&link_path)?;
+                    } else {
+                        fs::remove_file(&link_path)?;
+                    }
+                    removed.push(agent.name.clone());
+                }
+            }
+        }
+        removed.sort();
+        report.changes.removed = removed;
+
+        Ok(report)
+    }
+}
+
+/// Path of the sync lock for the links in `claude_agents_dir`.
+fn lock_path(claude_agents_dir: &Path) -> PathBuf {
+    claude_agents_dir
+        .parent()
+        .unwrap_or(claude_agents_dir)
+        .join(LOCK_FILE)
+}
+
+/// Names of the agents selected by `only` and `tags`, or `None` to sync all
+/// agents. An agent is selected when it is named by `only` or carries one of
+/// the tags; unknown names and a selection matching nothing are errors.
+fn select_agents(config: &AgentsConfig, options: &SyncOptions) -> Result<Option<HashSet<String>>> {
+    if options.only.is_empty() && options.tags.is_empty() {
+        return Ok(None);
+    }
+
+    let mut names = HashSet::new();
+    for name in &options.only {
+        names.insert(config.require_agent(name)?.name.clone());
+    }
+    for agent in &config.agents {
+        if agent.tags.iter().any(|tag| options.tags.contains(tag)) {
+            names.insert(agent.name.clone());
+        }
+    }
+    if names.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No agents are tagged {}",
+            options.tags.join(" or ")
+        ));
+    }
+    Ok(Some(names))
+}
+
+/// Link keys of enabled agents whose symlink already points at their
+/// existing source, in the configured link style, and would be linked by
+/// this sync: the condition holds and
+/// no earlier agent claims the same link path.
+fn current_links(config: &AgentsConfig, project_root: &Path) -> HashSet<String> {
+    let mut owners = HashSet::new();
+    let mut current = HashSet::new();
+    for agent in config.enabled_agents() {
+        if !matches!(agent.condition_met(), Ok(true)) {
+            continue;
+        }
+        let link_path = agent.get_link_path(project_root);
+        if !owners.insert(link_collision_key(&link_path)) {
+            continue;
+        }
+
+        let local_path = agent.get_local_path(project_root);
+        let expected = symlink_target(&local_path, &link_path, config.link_style);
+        if local_path.exists() && fs::read_link(&link_path).ok() == Some(expected) {
+            current.insert(agent.link_key());
+        }
+    }
+    current
+}
+
+/// Key identifying the file a link path resolves to. Case is folded because
+/// common filesystems (macOS, Windows) are case-insensitive.
+fn link_collision_key(link_path: &Path) -> String {
+    normalize_path(link_path).to_string_lossy().to_lowercase()
+}
+
+/// Returns the symlinks in `dir` and its subdirectories by their key (see
+/// [`scan_link_dir`]), mapped to their targets.
+fn read_links(dir: &Path, agent_keys: &HashSet<String>) -> Result<HashMap<String, PathBuf>> {
+    let mut links = HashMap::new();
+    // Skipped entries are reported by the unmanaged-file scan of `sync`
+    for (name, path) in scan_link_dir(dir, agent_keys)?.entries {
+        if !path.is_symlink() {
+            continue;
+        }
+        if let Ok(target) = fs::read_link(&path) {
+            links.insert(name, target);
+        }
+    }
+    Ok(links)
+}
+
+/// Downloads a missing GitHub source if needed and links the agent, noting
+/// the download and its warnings in `report`. Returns `false` when a local
+/// source is missing, or any source with `link_only`, and the agent was
+/// skipped.
+async fn sync_agent(
+    agent: &Agent,
+    project_root: &Path,
+    options: &DownloadOptions,
+    link_only: bool,
+    link_mode: LinkMode,
+    link_style: LinkStyle,
+    report: &mut SyncReport,
+) -> Result<bool> {
+    let local_path = agent.get_local_path(project_root);
+    let link_path = agent.get_link_path(project_root);
+
+    // Ensure the source exists
+    if !local_path.exists() {
+        match &agent.source {
+            AgentSource::GitHub(_) if !link_only => {
+                let warnings = download_agent(agent, project_root, options).await?;
+                report.changes.downloaded.push(agent.name.clone());
+                report.warnings.extend(
+                    warnings
+                        .into_iter()
+                        .map(|warning| (agent.name.clone(), warning)),
+                );
+            }
+            _ => return Ok(false),
+        }
+    }
+
+    // Create the symlink or copy
+    link_agent(&local_path, &link_path, link_mode, link_style)?;
+    Ok(true)
+}
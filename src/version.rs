@@ -1,9 +1,26 @@
+use crate::error::CcagentsError;
+use anyhow::Result;
 use colored::*;
+use serde::Deserialize;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 pub const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 
+/// GitHub API endpoint describing the latest ccagents release.
+pub const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/bitropy/ccagents/releases/latest";
+
+/// When set (to anything non-empty), ccagents makes no optional network
+/// requests such as the update check.
+pub const OFFLINE_ENV: &str = "CCAGENTS_OFFLINE";
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+}
+
 pub fn print_version_info() {
     println!("{} {}", PKG_NAME.cyan().bold(), VERSION.green());
 
@@ -20,6 +37,86 @@ pub fn print_version_info() {
     println!("Author: {}", AUTHORS.dimmed());
 }
 
+/// Tells the user whether a newer release than this build is available.
+/// Never installs anything.
+pub async fn check_for_update(release_url: &str) -> Result<()> {
+    if std::env::var(OFFLINE_ENV).is_ok_and(|v| !v.is_empty()) {
+        println!(
+            "{} Offline mode ({} is set), skipping update check",
+            "ℹ".blue(),
+            OFFLINE_ENV
+        );
+        return Ok(());
+    }
+
+    let release = fetch_latest_release(release_url).await?;
+    let latest = release.tag_name.trim_start_matches('v');
+
+    println!();
+    println!("Current version: {}", VERSION.green());
+    println!("Latest version:  {}", latest.green());
+
+    if is_newer(latest, VERSION) {
+        println!(
+            "\n{} A newer version is available: {}",
+            "↑".yellow().bold(),
+            release.html_url.cyan()
+        );
+    } else {
+        println!(
+            "\n{} You are running the latest version",
+            "✓".green().bold()
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn fetch_latest_release(release_url: &str) -> Result<Release> {
+    let network =
+        |e: reqwest::Error| CcagentsError::Network(format!("Failed to check for updates: {}", e));
+
+    // The GitHub API rejects requests without a User-Agent
+    let response = reqwest::Client::new()
+        .get(release_url)
+        .header(
+            reqwest::header::USER_AGENT,
+            format!("{}/{}", PKG_NAME, VERSION),
+        )
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(network)?;
+
+    if !response.status().is_success() {
+        return Err(CcagentsError::Network(format!(
+            "Failed to check for updates: HTTP {}",
+            response.status()
+        ))
+        .into());
+    }
+
+    Ok(response.json::<Release>().await.map_err(network)?)
+}
+
+/// Compares dotted version numbers (an optional leading `v` is ignored).
+/// Components that are not numbers, or missing (`1.0` is `1.0.0`), compare
+/// as 0.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        let mut parts: Vec<u64> = version
+            .trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .take(3)
+            .map(|part| part.parse().unwrap_or(0))
+            .collect();
+        parts.resize(3, 0);
+        parts
+    };
+
+    parse(candidate) > parse(current)
+}
+
 #[allow(dead_code)]
 pub fn get_version_string() -> String {
     if let Some(git_describe) = option_env!("GIT_DESCRIBE") {
@@ -28,3 +125,42 @@ pub fn get_version_string() -> String {
         VERSION.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("v0.1.10", "0.1.9"));
+        assert!(is_newer("1.0.0", "0.99.99"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("v0.0.9", "0.1.0"));
+        assert!(!is_newer("1.0.0", "1.0"));
+        assert!(!is_newer("v1.0", "1.0.0"));
+        assert!(is_newer("1.1", "1.0.9"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_release_reports_newer_tag() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/repos/bitropy/ccagents/releases/latest")
+            .match_header("user-agent", mockito::Matcher::Regex("^ccagents/".to_string()))
+            .with_status(200)
+            .with_body(
+                r#"{"tag_name": "v99.0.0", "html_url": "https://github.com/bitropy/ccagents/releases/tag/v99.0.0"}"#,
+            )
+            .create_async()
+            .await;
+
+        let url = format!("{}/repos/bitropy/ccagents/releases/latest", server.url());
+        let release = fetch_latest_release(&url).await.unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(release.tag_name, "v99.0.0");
+        assert!(release.html_url.ends_with("/v99.0.0"));
+        assert!(is_newer(&release.tag_name, VERSION));
+    }
+}
@@ -1,8 +1,11 @@
 use ccagents::agent::{Agent, AgentSource};
+use ccagents::cache::CACHE_DIR_ENV;
 use ccagents::config::{ensure_ccagents_dir, ensure_claude_agents_dir, AgentsConfig};
 use ccagents::linker::{create_symlink, is_symlink_valid};
+use ccagents::reconcile::{AgentOutcome, SyncOptions};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use tempfile::TempDir;
 
 #[test]
@@ -398,3 +401,584 @@ fn test_mixed_agents_directory() {
     assert_eq!(directories.len(), 1, "Should have 1 directory");
     assert!(directories.contains(&"subdir".to_string()));
 }
+
+/// The ccagents binary, run in `dir` with its download cache in `dir/.cache`
+/// rather than the user's.
+fn ccagents(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ccagents"));
+    command
+        .current_dir(dir)
+        .env(CACHE_DIR_ENV, dir.join(".cache"));
+    command
+}
+
+/// Runs the ccagents binary in `project_root` and returns its exit code.
+fn run_ccagents(project_root: &Path, args: &[&str], envs: &[(&str, &str)]) -> i32 {
+    ccagents(project_root)
+        .args(args)
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap()
+        .status
+        .code()
+        .unwrap()
+}
+
+#[test]
+fn test_exit_code_doctor_unfixed_issues() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+
+    // A missing local source cannot be fixed automatically
+    let mut config = AgentsConfig::default();
+    config
+        .add_agent(Agent::new(
+            "gone.md".to_string(),
+            AgentSource::Local(PathBuf::from("gone.md")),
+        ))
+        .unwrap();
+    config.save(project_root).unwrap();
+
+    assert_eq!(run_ccagents(project_root, &["doctor"], &[]), 2);
+    assert_eq!(run_ccagents(project_root, &["doctor", "--fix"], &[]), 2);
+}
+
+#[test]
+fn test_exit_code_check() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+
+    let source = ensure_ccagents_dir(project_root).unwrap().join("agent.md");
+    fs::write(&source, "# Agent").unwrap();
+    let mut config = AgentsConfig::default();
+    let agent = Agent::new(
+        "agent.md".to_string(),
+        AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+    );
+    config.add_agent(agent.clone()).unwrap();
+    config.save(project_root).unwrap();
+    ensure_claude_agents_dir(project_root).unwrap();
+    create_symlink(&source, &agent.get_link_path(project_root)).unwrap();
+
+    assert_eq!(run_ccagents(project_root, &["check"], &[]), 0);
+
+    // Removing the source leaves a broken symlink behind
+    fs::remove_file(&source).unwrap();
+    assert_eq!(run_ccagents(project_root, &["check"], &[]), 2);
+    assert_eq!(run_ccagents(project_root, &["check", "--summary"], &[]), 2);
+}
+
+#[test]
+fn test_prefer_copy_adds_and_syncs_copies() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    fs::write(project_root.join("added.md"), "# Added").unwrap();
+    let source = ensure_ccagents_dir(project_root).unwrap().join("synced.md");
+    fs::write(&source, "# Synced").unwrap();
+
+    let mut config = AgentsConfig {
+        prefer_copy: true,
+        ..AgentsConfig::default()
+    };
+    config
+        .add_agent(Agent::new(
+            "synced.md".to_string(),
+            AgentSource::Local(PathBuf::from(".ccagents/synced.md")),
+        ))
+        .unwrap();
+    config.save(project_root).unwrap();
+    assert!(fs::read_to_string(project_root.join(".agents.json"))
+        .unwrap()
+        .contains(r#""prefer_copy": true"#));
+
+    assert_eq!(run_ccagents(project_root, &["add", "added.md"], &[]), 0);
+    assert_eq!(run_ccagents(project_root, &["sync"], &[]), 0);
+
+    let claude_agents_dir = project_root.join(".claude/agents");
+    for (name, content) in [("added.md", "# Added"), ("synced.md", "# Synced")] {
+        let path = claude_agents_dir.join(name);
+        assert!(!path.is_symlink(), "{} is a symlink", name);
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    // The health checks expect copies, and report drift in them
+    assert_eq!(run_ccagents(project_root, &["check"], &[]), 0);
+    assert_eq!(run_ccagents(project_root, &["doctor"], &[]), 0);
+    fs::write(claude_agents_dir.join("synced.md"), "# Edited").unwrap();
+    assert_eq!(run_ccagents(project_root, &["check"], &[]), 2);
+
+    assert_eq!(run_ccagents(project_root, &["sync", "--symlink"], &[]), 0);
+    assert!(claude_agents_dir.join("synced.md").is_symlink());
+}
+
+#[test]
+fn test_exit_code_success_and_generic_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    AgentsConfig::default().save(project_root).unwrap();
+
+    assert_eq!(run_ccagents(project_root, &["doctor"], &[]), 0);
+    assert_eq!(
+        run_ccagents(project_root, &["enable", "missing.md"], &[]),
+        1
+    );
+}
+
+#[test]
+fn test_exit_code_config_not_found() {
+    let temp_dir = TempDir::new().unwrap();
+
+    assert_eq!(
+        run_ccagents(temp_dir.path(), &["enable", "agent.md"], &[]),
+        4
+    );
+}
+
+#[test]
+fn test_exit_code_require_agents() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    AgentsConfig::default().save(project_root).unwrap();
+
+    assert_eq!(run_ccagents(project_root, &["sync"], &[]), 0);
+    assert_eq!(
+        run_ccagents(project_root, &["sync", "--require-agents"], &[]),
+        5
+    );
+    assert_eq!(
+        run_ccagents(project_root, &["list", "--require-agents"], &[]),
+        5
+    );
+}
+
+#[test]
+fn test_exit_code_network_failure() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/org/repo/raw/main/agent.md")
+        .with_status(404)
+        .create();
+
+    let temp_dir = TempDir::new().unwrap();
+    let url = format!("{}/org/repo/blob/main/agent.md", server.url());
+    let code = run_ccagents(
+        temp_dir.path(),
+        &["add", &url],
+        &[("CCAGENTS_GITHUB_HOSTS", &server.host_with_port())],
+    );
+
+    assert_eq!(code, 3);
+}
+
+#[test]
+fn test_agent_not_found_leaves_project_untouched() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    AgentsConfig::default().save(project_root).unwrap();
+    let before = fs::read_to_string(project_root.join(".agents.json")).unwrap();
+
+    for command in ["enable", "disable"] {
+        let output = ccagents(project_root)
+            .args([command, "typo.md"])
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(1), "{}", command);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Agent 'typo.md' not found"),
+            "{}: {}",
+            command,
+            stderr
+        );
+    }
+
+    let after = fs::read_to_string(project_root.join(".agents.json")).unwrap();
+    assert_eq!(before, after);
+    assert!(!project_root.join(".claude").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_import_skips_non_utf8_file_names() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+    fs::write(
+        claude_agents_dir.join(OsStr::from_bytes(b"bad-\xff.md")),
+        "# Bad",
+    )
+    .unwrap();
+    fs::write(claude_agents_dir.join("good.md"), "# Good").unwrap();
+
+    let output = ccagents(project_root)
+        .args(["import", "--all"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("file name is not valid UTF-8"));
+    let config = AgentsConfig::load(project_root).unwrap();
+    assert_eq!(config.agents.len(), 1);
+    assert!(config.get_agent("good.md").is_some());
+    assert!(config.get_agent("").is_none());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_sync_reports_non_utf8_file_names_as_warnings() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+    fs::write(
+        claude_agents_dir.join(OsStr::from_bytes(b"bad-\xff.md")),
+        "# Bad",
+    )
+    .unwrap();
+    fs::write(project_root.join("local.md"), "# Local").unwrap();
+
+    let mut config = AgentsConfig::default();
+    config
+        .add_agent(Agent::new(
+            "local.md".to_string(),
+            AgentSource::Local(PathBuf::from("local.md")),
+        ))
+        .unwrap();
+    config.save(project_root).unwrap();
+
+    let report = AgentsConfig::sync(project_root, &SyncOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(report.warnings.len(), 1);
+    assert_eq!(report.warnings[0].0, "bad-\u{fffd}.md");
+    assert!(report.warnings[0].1.contains("not valid UTF-8"));
+}
+
+#[tokio::test]
+async fn test_sync_as_library() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    fs::write(project_root.join("local.md"), "# Local").unwrap();
+
+    let mut config = AgentsConfig::default();
+    config
+        .add_agent(Agent::new(
+            "local.md".to_string(),
+            AgentSource::Local(PathBuf::from("local.md")),
+        ))
+        .unwrap();
+    config
+        .add_agent(Agent::new(
+            "missing.md".to_string(),
+            AgentSource::Local(PathBuf::from("missing.md")),
+        ))
+        .unwrap();
+    config.save(project_root).unwrap();
+
+    let report = AgentsConfig::sync(project_root, &SyncOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(report.changes.created, vec!["local.md"]);
+    assert_eq!(
+        report.outcomes,
+        vec![
+            ("local.md".to_string(), AgentOutcome::Linked),
+            ("missing.md".to_string(), AgentOutcome::SourceMissing),
+        ]
+    );
+    assert!(report.failed.is_empty());
+    assert!(is_symlink_valid(
+        &project_root.join(".claude/agents/local.md")
+    ));
+
+    // A second sync finds the link current
+    let report = AgentsConfig::sync(project_root, &SyncOptions::default())
+        .await
+        .unwrap();
+    assert!(report.changes.is_empty());
+    assert_eq!(report.changes.unchanged, vec!["local.md"]);
+}
+
+#[test]
+fn test_list_count() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+
+    let mut config = AgentsConfig::default();
+    for (name, enabled) in [("a.md", true), ("b.md", true), ("c.md", false)] {
+        let mut agent = Agent::new(
+            name.to_string(),
+            AgentSource::Local(PathBuf::from(format!(".ccagents/{}", name))),
+        );
+        agent.enabled = enabled;
+        config.add_agent(agent).unwrap();
+    }
+    config.save(project_root).unwrap();
+
+    let count = |args: &[&str]| {
+        let output = ccagents(project_root).args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(count(&["list", "--count"]), "3\n");
+    assert_eq!(count(&["list", "--count", "--enabled"]), "2\n");
+    assert_eq!(count(&["list", "--count", "--disabled"]), "1\n");
+}
+
+#[test]
+fn test_export_to_stdout_and_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+
+    let mut config = AgentsConfig::default();
+    for (name, enabled) in [("on.md", true), ("off.md", false)] {
+        let mut agent = Agent::new(
+            name.to_string(),
+            AgentSource::Local(PathBuf::from(format!(".ccagents/{}", name))),
+        );
+        agent.enabled = enabled;
+        config.add_agent(agent).unwrap();
+    }
+    config.save(project_root).unwrap();
+
+    let export = |args: &[&str]| {
+        let output = ccagents(project_root).args(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let names = |manifest: &str| {
+        AgentsConfig::parse_manifest(manifest, project_root)
+            .unwrap()
+            .agents
+            .into_iter()
+            .map(|a| a.name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(names(&export(&["export"])), vec!["on.md", "off.md"]);
+    assert_eq!(names(&export(&["export", "--enabled-only"])), vec!["on.md"]);
+
+    export(&["export", "-f", "team.json"]);
+    let written = fs::read_to_string(project_root.join("team.json")).unwrap();
+    assert_eq!(names(&written), vec!["on.md", "off.md"]);
+}
+
+#[test]
+fn test_sync_uses_manifest_from_env() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path().join("project");
+    let ccagents_dir = ensure_ccagents_dir(&project_root).unwrap();
+    fs::write(ccagents_dir.join("ci.md"), "# CI agent").unwrap();
+
+    let manifest_path = temp_dir.path().join("manifest.json");
+    fs::write(
+        &manifest_path,
+        r#"{"agents": [{"name": "ci.md", "source": {"type": "Local", "value": ".ccagents/ci.md"}, "enabled": true}]}"#,
+    )
+    .unwrap();
+
+    let code = run_ccagents(
+        &project_root,
+        &["sync"],
+        &[("CCAGENTS_MANIFEST", manifest_path.to_str().unwrap())],
+    );
+
+    assert_eq!(code, 0);
+    assert!(is_symlink_valid(&project_root.join(".claude/agents/ci.md")));
+    // The manifest is never written to disk as .agents.json
+    assert!(!project_root.join(".agents.json").exists());
+}
+
+#[test]
+fn test_sync_json_stdout_is_only_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+    fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+
+    let mut config = AgentsConfig::default();
+    config
+        .add_agent(Agent::new(
+            "agent.md".to_string(),
+            AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+        ))
+        .unwrap();
+    config.save(project_root).unwrap();
+
+    let output = ccagents(project_root)
+        .args(["sync", "--json"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let changes: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(changes["created"], serde_json::json!(["agent.md"]));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Syncing agents..."));
+}
+
+#[test]
+fn test_root_flag_targets_another_project() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path().join("project");
+    let elsewhere = temp_dir.path().join("elsewhere");
+    fs::create_dir_all(&elsewhere).unwrap();
+
+    let ccagents_dir = ensure_ccagents_dir(&project_root).unwrap();
+    fs::write(ccagents_dir.join("agent.md"), "# Agent").unwrap();
+    let mut config = AgentsConfig::default();
+    config
+        .add_agent(Agent::new(
+            "agent.md".to_string(),
+            AgentSource::Local(PathBuf::from(".ccagents/agent.md")),
+        ))
+        .unwrap();
+    config.save(&project_root).unwrap();
+
+    let root = project_root.to_str().unwrap();
+    assert_eq!(run_ccagents(&elsewhere, &["--root", root, "sync"], &[]), 0);
+    assert!(is_symlink_valid(
+        &project_root.join(".claude/agents/agent.md")
+    ));
+    assert!(!elsewhere.join(".claude").exists());
+
+    let output = ccagents(&elsewhere)
+        .args(["list", "--count", "--root", root])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+
+    // The root must be an existing directory
+    let missing = temp_dir.path().join("missing");
+    assert_eq!(
+        run_ccagents(
+            &elsewhere,
+            &["--root", missing.to_str().unwrap(), "list"],
+            &[]
+        ),
+        1
+    );
+}
+
+#[test]
+fn test_add_through_symlinked_cwd_stores_relative_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path().join("project");
+    fs::create_dir_all(&project_root).unwrap();
+    fs::write(project_root.join("agent.md"), "# Agent").unwrap();
+    fs::write(project_root.join("other.md"), "# Other").unwrap();
+    let link = temp_dir.path().join("link");
+    std::os::unix::fs::symlink(&project_root, &link).unwrap();
+
+    // Working directory and source path both reached via the symlink
+    let source = link.join("agent.md");
+    assert_eq!(
+        run_ccagents(&link, &["add", source.to_str().unwrap()], &[]),
+        0
+    );
+
+    // --root via the symlink, source via the real path
+    let root = link.to_str().unwrap();
+    let source = project_root.join("other.md");
+    assert_eq!(
+        run_ccagents(
+            temp_dir.path(),
+            &["--root", root, "add", source.to_str().unwrap()],
+            &[]
+        ),
+        0
+    );
+
+    let content = fs::read_to_string(project_root.join(".agents.json")).unwrap();
+    let config: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(config["agents"][0]["source"]["value"], "agent.md");
+    assert_eq!(config["agents"][1]["source"]["value"], "other.md");
+    assert!(!project_root.join(".ccagents").exists());
+}
+
+#[test]
+fn test_output_json_for_list_and_doctor() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    let ccagents_dir = ensure_ccagents_dir(project_root).unwrap();
+    fs::write(ccagents_dir.join("linked.md"), "# Linked").unwrap();
+
+    let mut config = AgentsConfig::default();
+    config
+        .add_and_link(
+            Agent::new(
+                "linked.md".to_string(),
+                AgentSource::Local(PathBuf::from(".ccagents/linked.md")),
+            ),
+            project_root,
+            true,
+        )
+        .unwrap();
+    config
+        .add_agent(Agent::new(
+            "gone.md".to_string(),
+            AgentSource::Local(PathBuf::from(".ccagents/gone.md")),
+        ))
+        .unwrap();
+    config.save(project_root).unwrap();
+
+    let json = |args: &[&str]| {
+        let output = ccagents(project_root).args(args).output().unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (output.status.code().unwrap(), value)
+    };
+
+    let (code, list) = json(&["--output", "json", "list"]);
+    assert_eq!(code, 0);
+    assert_eq!(list["agents"][0]["name"], "linked.md");
+    assert_eq!(list["agents"][0]["status"], "linked");
+    assert_eq!(list["agents"][1]["status"], "source_missing");
+
+    // The issue is still reported through the exit code
+    let (code, doctor) = json(&["doctor", "--output", "json"]);
+    assert_eq!(code, 2);
+    let issues = doctor["issues"].as_array().unwrap();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0]["agent_name"], "gone.md");
+    assert_eq!(issues[0]["issue_type"], "missing-source");
+}
+
+#[test]
+fn test_import_dry_run_changes_nothing() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_root = temp_dir.path();
+    let claude_agents_dir = ensure_claude_agents_dir(project_root).unwrap();
+    fs::write(claude_agents_dir.join("first.md"), "# First").unwrap();
+    fs::write(claude_agents_dir.join("second.md"), "# Second").unwrap();
+
+    let dry_run = |args: &[&str]| {
+        let output = ccagents(project_root).args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = dry_run(&["import", "--dry-run"]);
+    assert!(stdout.contains("move .claude/agents/first.md to .ccagents/first.md"));
+    assert!(stdout.contains("replace .claude/agents/second.md with a symlink"));
+    assert!(stdout.contains("add 'second.md' to .agents.json"));
+
+    let stdout = dry_run(&["import", "first.md", "--dry-run", "--disabled"]);
+    assert!(stdout.contains("add 'first.md' to .agents.json (disabled)"));
+    assert!(!stdout.contains("second.md"));
+
+    // Nothing moved, linked or configured
+    assert!(!project_root.join(".ccagents").exists());
+    assert!(!project_root.join(".agents.json").exists());
+    for name in ["first.md", "second.md"] {
+        let path = claude_agents_dir.join(name);
+        assert!(path.is_file() && !path.is_symlink());
+    }
+}